@@ -0,0 +1,375 @@
+//! Allocation measurements with a tracking global allocator
+//!
+//! [`AllocatedBytes`] and [`Allocations`] measure the bytes allocated and the number of
+//! allocations and print throughputs like `B alloc/elem` and `allocs/elem`.
+//! They are useful to spot accidental allocations per element, e.g. per row in parsers.
+//!
+//! The allocations are counted by [`TrackingAllocator`], which must be registered as
+//! the global allocator of the benchmark:
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::allocation::{AllocatedBytes, TrackingAllocator};
+//!
+//! #[global_allocator]
+//! static GLOBAL: TrackingAllocator = TrackingAllocator::system();
+//!
+//! let c = Criterion::default().with_measurement(AllocatedBytes::new());
+//! ```
+//!
+//! They take the options of [`InvertedThroughput`](crate::InvertedThroughput) like
+//! [`AllocatedBytes::precision`].
+//!
+//! The counters are shared by all threads, so allocations by other threads while measuring
+//! are counted too.
+
+use crate::units::scale_per_unit;
+use crate::{inversion_options, Inversion};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The global allocator counting allocations made through the inner allocator
+///
+/// Reallocations are counted as allocations of the new size.
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl TrackingAllocator<System> {
+    /// Returns a `TrackingAllocator` wrapping the system allocator
+    pub const fn system() -> Self {
+        TrackingAllocator { inner: System }
+    }
+}
+
+impl<A> TrackingAllocator<A> {
+    /// Returns a `TrackingAllocator` wrapping `inner`
+    pub const fn new(inner: A) -> Self {
+        TrackingAllocator { inner }
+    }
+}
+
+fn track(size: usize) {
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size());
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        track(layout.size());
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+/// The custom measurement of the bytes allocated through [`TrackingAllocator`]
+pub struct AllocatedBytes {
+    inversion: Inversion,
+}
+
+impl AllocatedBytes {
+    /// Returns a new `AllocatedBytes`
+    pub fn new() -> Self {
+        AllocatedBytes {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for AllocatedBytes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement for AllocatedBytes {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        ALLOCATED_BYTES.load(Ordering::SeqCst)
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        ALLOCATED_BYTES.load(Ordering::SeqCst) - i
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for AllocatedBytes {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&AllocatedBytesFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&AllocatedBytesFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&AllocatedBytesFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion.scale_throughputs(
+            &AllocatedBytesFormatter,
+            typical_value,
+            throughput,
+            values,
+        )
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_for_machines(&AllocatedBytesFormatter, values)
+    }
+}
+
+/// The custom measurement of the number of allocations through [`TrackingAllocator`]
+pub struct Allocations {
+    inversion: Inversion,
+}
+
+impl Allocations {
+    /// Returns a new `Allocations`
+    pub fn new() -> Self {
+        Allocations {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for Allocations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        ALLOCATIONS.load(Ordering::SeqCst)
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        ALLOCATIONS.load(Ordering::SeqCst) - i
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Allocations {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&AllocationsFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&AllocationsFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&AllocationsFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&AllocationsFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_for_machines(&AllocationsFormatter, values)
+    }
+}
+
+/// The formatter of allocated bytes, printing throughputs in the format
+/// `[bytes] alloc/[element or byte]`
+pub struct AllocatedBytesFormatter;
+
+impl ValueFormatter for AllocatedBytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        let (factor, unit) = if typical_value < 1e3 {
+            (1.0, "B alloc")
+        } else if typical_value < 1e6 {
+            (1e-3, "KB alloc")
+        } else if typical_value < 1e9 {
+            (1e-6, "MB alloc")
+        } else {
+            (1e-9, "GB alloc")
+        };
+        for val in values {
+            *val *= factor;
+        }
+        unit
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B alloc"
+    }
+}
+
+/// The formatter of numbers of allocations, printing throughputs in the format
+/// `allocs/[element or byte]`
+pub struct AllocationsFormatter;
+
+impl ValueFormatter for AllocationsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::thrpt_line;
+    use test_case::test_case;
+
+    #[test]
+    fn test_tracking_allocator() {
+        let allocator = TrackingAllocator::system();
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let (allocated_bytes, allocations) = (AllocatedBytes::new(), Allocations::new());
+        let bytes = allocated_bytes.start();
+        let allocations_start = allocations.start();
+        unsafe {
+            let ptr = allocator.alloc(layout);
+            let ptr = allocator.realloc(ptr, layout, 128);
+            allocator.dealloc(ptr, Layout::from_size_align(128, 8).unwrap());
+        }
+        assert_eq!(allocated_bytes.end(bytes), 192);
+        assert_eq!(allocations.end(allocations_start), 2);
+    }
+
+    #[test]
+    fn test_bound_order() {
+        let throughput = Throughput::Elements(10);
+        assert_eq!(
+            thrpt_line(
+                &AllocatedBytes::new(),
+                &throughput,
+                [1000.0, 2000.0, 4000.0]
+            ),
+            [
+                "100.00 B alloc/elem",
+                "200.00 B alloc/elem",
+                "400.00 B alloc/elem"
+            ]
+        );
+        // counts of allocations are often equal across samples
+        assert_eq!(
+            thrpt_line(&Allocations::new(), &throughput, [20.0, 20.0, 30.0]),
+            [
+                "2.0000 allocs/elem",
+                "2.0000 allocs/elem",
+                "3.0000 allocs/elem"
+            ]
+        );
+    }
+
+    #[test_case(Throughput::Elements(10), 2000.0, 200.0, "B alloc/elem" ; "test bytes per element")]
+    #[test_case(Throughput::Bytes(1), 2000.0, 2.0, "KB alloc/byte" ; "test kilobytes per byte")]
+    fn test_allocated_bytes_throughputs(
+        throughput: Throughput,
+        value: f64,
+        expected_value: f64,
+        expected_unit: &str,
+    ) {
+        let mut values = [value];
+        let unit = AllocatedBytesFormatter.scale_throughputs(value, &throughput, &mut values);
+        assert_eq!(unit, expected_unit);
+        assert!((values[0] - expected_value).abs() < expected_value * 1e-12);
+    }
+
+    #[test]
+    fn test_allocations_throughputs() {
+        let mut values = [5.0];
+        let unit =
+            AllocationsFormatter.scale_throughputs(5.0, &Throughput::Elements(1000), &mut values);
+        assert_eq!(unit, "allocs/elem");
+        assert_eq!(values, [0.005]);
+    }
+}
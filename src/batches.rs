@@ -0,0 +1,84 @@
+//! Benchmarking with durations measured externally in batches
+//!
+//! Some workloads are timed outside of the process, e.g. by an FPGA, a remote service or
+//! a GPU query, and process elements in batches of a fixed maximum size.
+//! [`iter_batches`] wires such durations into [`Bencher::iter_custom`] so that an iteration
+//! of criterion stands for an element.
+//! Set `Throughput::Elements(1)` for the benchmark to get the time per element:
+//!
+//! ```no_run
+//! use criterion::{Criterion, Throughput};
+//! use criterion_inverted_throughput::batches::iter_batches;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//! use std::time::Duration;
+//!
+//! # fn run_on_device(_elements: u64) -> Duration { Duration::ZERO }
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("device");
+//! g.throughput(Throughput::Elements(1));
+//! g.bench_function("filter", |b| {
+//!     // the device processes at most 4096 elements at once and reports the time of a batch
+//!     iter_batches(b, |batch_size| run_on_device(batch_size), 4096)
+//! });
+//! g.finish();
+//! ```
+
+use criterion::measurement::Measurement;
+use criterion::Bencher;
+use std::time::Duration;
+
+/// Runs `batch` on the elements criterion requests, split into batches of at most
+/// `elements_per_batch` elements, and reports the sum of the durations `batch` returns
+///
+/// `batch` is given the number of elements in the batch. The elements of all batches sum up
+/// to the number of iterations criterion requests, so the time per iteration is the time
+/// per element.
+///
+/// # Panics
+///
+/// Panics if `elements_per_batch` is zero.
+pub fn iter_batches<M, F>(bencher: &mut Bencher<'_, M>, mut batch: F, elements_per_batch: u64)
+where
+    M: Measurement<Value = Duration>,
+    F: FnMut(u64) -> Duration,
+{
+    assert_ne!(
+        elements_per_batch, 0,
+        "`elements_per_batch` must not be zero"
+    );
+    bencher.iter_custom(|iters| run_batches(iters, &mut batch, elements_per_batch));
+}
+
+fn run_batches<F>(elements: u64, batch: &mut F, elements_per_batch: u64) -> Duration
+where
+    F: FnMut(u64) -> Duration,
+{
+    let mut remaining = elements;
+    let mut total = Duration::ZERO;
+    while remaining > 0 {
+        let batch_size = remaining.min(elements_per_batch);
+        total += batch(batch_size);
+        remaining -= batch_size;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_batches() {
+        let mut batch_sizes = vec![];
+        let total = run_batches(
+            10,
+            &mut |batch_size| {
+                batch_sizes.push(batch_size);
+                Duration::from_nanos(batch_size * 3)
+            },
+            4,
+        );
+        assert_eq!(batch_sizes, vec![4, 4, 2]);
+        assert_eq!(total, Duration::from_nanos(30));
+    }
+}
@@ -0,0 +1,434 @@
+//! Prints inverted throughputs of the results criterion stored
+//!
+//! It is useful to get `[time]/[element or byte]` for benchmarks which are not measured
+//! by `InvertedThroughput`.
+
+use criterion_inverted_throughput::__criterion::measurement::ValueFormatter;
+use criterion_inverted_throughput::compare::{compare, table};
+use criterion_inverted_throughput::complexity;
+use criterion_inverted_throughput::environment::Environment;
+use criterion_inverted_throughput::export::{
+    export_with, throughput_label, write_textfile_with, ExportOptions, Format,
+};
+use criterion_inverted_throughput::history::{self, drifted, trends};
+use criterion_inverted_throughput::numbers::NumberFormat;
+use criterion_inverted_throughput::results::{self, BenchRecord};
+use criterion_inverted_throughput::settings::{Settings, SETTINGS_FILE};
+use criterion_inverted_throughput::InvertedThroughput;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: criterion-invert [COMMAND] [OPTIONS]
+       criterion-invert compare <OLD> <NEW> [OPTIONS]
+
+Prints time per element or byte of the benchmark results criterion stored
+
+Commands:
+    show                  print a table of the results (default)
+    export                print the results in the format of `--format`
+    compare               print the differences from the baseline OLD to NEW
+    record                append the results to the history
+    history               print the trends of the last runs in the history
+                          and flag the benchmarks drifted beyond the threshold
+    complexity            print the growth of the functions benchmarked with
+                          several sizes
+    save                  write the estimates per element or byte to
+                          `inverted_estimates.json` of each benchmark
+    check                 check the results against the budgets of the settings
+                          and fail if any is over budget, matches no benchmark,
+                          or if no rule has a budget
+
+Options:
+    --dir <DIR>           the directory criterion stores results in
+                          [default: $CRITERION_HOME, $CARGO_TARGET_DIR/criterion
+                          or target/criterion, like criterion]
+    --baseline <NAME>     the baseline to read [default: new]
+    --group <GROUP>       only use the benchmarks in the group
+    --format <FORMAT>     the format of `export`, `md`, `csv`, `bencher` or `prom`
+                          [default: md]
+    --output <FILE>       the file `export` writes to instead of the standard output
+    --media <SECONDS>     the duration of media in an element or byte, adding
+                          the realtime multiples to `export`
+    --ghz <GHZ>           add the approximate cycles at GHZ to `export`
+                          [default: the frequency of the saved environment]
+    --history <DIR>       the directory the history is stored in
+                          [default: target/criterion-inverted/history]
+    --runs <N>            the number of the last runs `history` uses [default: 10]
+    --threshold <PERCENT> the change `history` flags as a drift [default: 5]
+    --thousands <CHAR>    separate thousands of numbers in `show`, and Markdown
+                          and CSV of `export`, by CHAR
+    --decimal-mark <CHAR> the decimal mark of them [default: .]
+    --precision <N>       print N decimals in Markdown and CSV of `export`
+    --width <N>           pad the values in `show` and Markdown to N characters
+    --config <FILE>       the settings `check` reads
+                          [default: inverted-throughput.toml]
+    -h, --help            print this help";
+
+enum Command {
+    Show,
+    Export,
+    Compare { old: String, new: String },
+    Record,
+    History,
+    Complexity,
+    Save,
+    Check,
+}
+
+struct Options {
+    command: Command,
+    dir: PathBuf,
+    baseline: String,
+    group: Option<String>,
+    format: Format,
+    output: Option<PathBuf>,
+    media: Option<Duration>,
+    ghz: Option<f64>,
+    history: PathBuf,
+    runs: usize,
+    threshold: f64,
+    numbers: NumberFormat,
+    config: Option<PathBuf>,
+}
+
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        command: Command::Show,
+        dir: results::criterion_home(),
+        baseline: results::LATEST_BASELINE.to_string(),
+        group: None,
+        format: Format::Markdown,
+        output: None,
+        media: None,
+        ghz: None,
+        history: history::history_dir(),
+        runs: 10,
+        threshold: 5.0,
+        numbers: NumberFormat::new(),
+        config: None,
+    };
+    let mut args = args.iter().peekable();
+    match args.peek().map(|arg| arg.as_str()) {
+        Some("show") => {
+            args.next();
+        }
+        Some("export") => {
+            args.next();
+            options.command = Command::Export;
+        }
+        Some("compare") => {
+            args.next();
+            let mut baseline = || {
+                args.next_if(|arg| !arg.starts_with('-'))
+                    .cloned()
+                    .ok_or("`compare` requires two baselines OLD and NEW")
+            };
+            let old = baseline()?;
+            let new = baseline()?;
+            options.command = Command::Compare { old, new };
+        }
+        Some("record") => {
+            args.next();
+            options.command = Command::Record;
+        }
+        Some("history") => {
+            args.next();
+            options.command = Command::History;
+        }
+        Some("complexity") => {
+            args.next();
+            options.command = Command::Complexity;
+        }
+        Some("save") => {
+            args.next();
+            options.command = Command::Save;
+        }
+        Some("check") => {
+            args.next();
+            options.command = Command::Check;
+        }
+        _ => {}
+    }
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for `{}`", arg))
+        };
+        match arg.as_str() {
+            "--dir" => options.dir = PathBuf::from(value()?),
+            "--baseline" => options.baseline = value()?,
+            "--group" => options.group = Some(value()?),
+            "--format" => options.format = value()?.parse()?,
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            "--media" => {
+                let media = value()?
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .filter(|media| !media.is_zero())
+                    .ok_or("invalid `--media`: expected a positive number of seconds")?;
+                options.media = Some(media);
+            }
+            "--ghz" => {
+                let ghz = value()?
+                    .parse()
+                    .ok()
+                    .filter(|ghz: &f64| ghz.is_finite() && *ghz > 0.0)
+                    .ok_or("invalid `--ghz`: expected a positive number")?;
+                options.ghz = Some(ghz);
+            }
+            "--history" => options.history = PathBuf::from(value()?),
+            "--runs" => {
+                options.runs = value()?
+                    .parse()
+                    .map_err(|e| format!("invalid `--runs`: {}", e))?
+            }
+            "--threshold" => {
+                options.threshold = value()?
+                    .parse()
+                    .map_err(|e| format!("invalid `--threshold`: {}", e))?
+            }
+            "--thousands" => {
+                options.numbers = options.numbers.thousands_separator(char(&value()?)?)
+            }
+            "--decimal-mark" => options.numbers = options.numbers.decimal_mark(char(&value()?)?),
+            "--precision" => {
+                options.numbers = options.numbers.precision(
+                    value()?
+                        .parse()
+                        .map_err(|e| format!("invalid `--precision`: {}", e))?,
+                )
+            }
+            "--width" => {
+                options.numbers = options.numbers.width(
+                    value()?
+                        .parse()
+                        .map_err(|e| format!("invalid `--width`: {}", e))?,
+                )
+            }
+            "--config" => options.config = Some(PathBuf::from(value()?)),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn char(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("`{}` is not a character", value)),
+    }
+}
+
+fn print_table(records: &[BenchRecord]) {
+    let formatter = InvertedThroughput::new();
+    let numbers = NumberFormat::current();
+    let value = |throughput, value| {
+        numbers.pad(&numbers.localize(&formatter.format_throughput(throughput, value)))
+    };
+    let rows: Vec<(&str, String, String)> = records
+        .iter()
+        .map(|record| {
+            let estimate = record.estimates.typical();
+            let dispersion = record.estimates.dispersion();
+            let (throughput, per_unit) = match &record.throughput {
+                Some(throughput) => (
+                    throughput_label(throughput),
+                    format!(
+                        "[{} {} {}] σ {} (CV {:.1}%)",
+                        value(throughput, estimate.lower_bound),
+                        value(throughput, estimate.point_estimate),
+                        value(throughput, estimate.upper_bound),
+                        value(throughput, dispersion.std_dev),
+                        dispersion.coefficient_of_variation * 100.0,
+                    ),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            (record.id.as_str(), throughput, per_unit)
+        })
+        .collect();
+
+    let id_width = rows.iter().map(|r| r.0.len()).chain([9]).max().unwrap();
+    let throughput_width = rows.iter().map(|r| r.1.len()).chain([10]).max().unwrap();
+    println!(
+        "{:<id_width$}  {:>throughput_width$}  time per unit",
+        "benchmark", "throughput"
+    );
+    for (id, throughput, per_unit) in rows {
+        println!(
+            "{:<id_width$}  {:>throughput_width$}  {}",
+            id, throughput, per_unit
+        );
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let Some(options) = parse_args(args)? else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    let _numbers = options.numbers.apply();
+    match &options.command {
+        Command::Show => print_table(&load_records(&options, &options.baseline)?),
+        Command::Export => {
+            let records = load_records(&options, &options.baseline)?;
+            let mut export_options = ExportOptions::new();
+            // the environment `environment::Setup` saved, if any
+            if let Ok(environment) = Environment::load(&options.dir) {
+                export_options = export_options.environment(environment);
+            }
+            if let Some(media) = options.media {
+                export_options = export_options.per_media_seconds(media);
+            }
+            if let Some(ghz) = options.ghz {
+                export_options = export_options.cycles_at(ghz);
+            }
+            match (&options.output, options.format) {
+                (Some(output), Format::Prometheus) => {
+                    write_textfile_with(output, &records, &export_options)?
+                }
+                (Some(output), format) => {
+                    fs::write(output, export_with(&records, format, &export_options))?
+                }
+                (None, format) => print!("{}", export_with(&records, format, &export_options)),
+            }
+        }
+        Command::Compare { old, new } => {
+            let old = load_records(&options, old)?;
+            let new = load_records(&options, new)?;
+            print!("{}", table(&compare(&old, &new)));
+        }
+        Command::Record => {
+            let records = load_records(&options, &options.baseline)?;
+            let count = history::record_in(
+                &options.history,
+                &records,
+                history::git_sha(),
+                history::now(),
+            )
+            .map_err(|e| format!("failed to write {}: {}", options.history.display(), e))?;
+            println!(
+                "recorded {} benchmarks to {}",
+                count,
+                options.history.display()
+            );
+        }
+        Command::History => {
+            let mut entries = history::load(&options.history)
+                .map_err(|e| format!("failed to read {}: {}", options.history.display(), e))?;
+            if let Some(group) = &options.group {
+                entries.retain(|entry| entry.id.starts_with(&format!("{}/", group)));
+            }
+            let trends = trends(&entries, options.runs);
+            print!("{}", table(&trends));
+            for trend in drifted(&trends, options.threshold) {
+                println!(
+                    "drifted: {} changed by {:.2}% over the last {} runs",
+                    trend.id,
+                    trend.change(),
+                    options.runs
+                );
+            }
+        }
+        Command::Complexity => {
+            for complexity in complexity::estimate(&load_records(&options, &options.baseline)?) {
+                println!("{}", complexity);
+            }
+        }
+        Command::Save => {
+            let records = load_records(&options, &options.baseline)?;
+            let count =
+                results::write_inverted_estimates(&options.dir, &options.baseline, &records)
+                    .map_err(|e| format!("failed to write {}: {}", options.dir.display(), e))?;
+            println!(
+                "saved {} in {} benchmarks",
+                results::INVERTED_ESTIMATES_FILE,
+                count
+            );
+        }
+        Command::Check => {
+            let settings = match &options.config {
+                Some(config) => Settings::load_from(config)?.with_env()?,
+                None => Settings::load()?,
+            };
+            if !settings.has_budgets() {
+                return Err(format!(
+                    "no rule has a budget; add `budget` to a `[[bench]]` of {}",
+                    options
+                        .config
+                        .as_deref()
+                        .unwrap_or(Path::new(SETTINGS_FILE))
+                        .display()
+                )
+                .into());
+            }
+            let records = load_records(&options, &options.baseline)?;
+            let formatter = InvertedThroughput::new();
+            let numbers = NumberFormat::current();
+            let mut failed = 0;
+            for rule in settings.unmatched_budgets(&records) {
+                println!("failed: the budget of `{}` matches no benchmark", rule.id);
+                failed += 1;
+            }
+            for (id, result) in settings.check_budgets(&records) {
+                match result {
+                    Ok(estimate) => match records.iter().find(|record| record.id == id) {
+                        Some(BenchRecord {
+                            throughput: Some(throughput),
+                            estimates,
+                            ..
+                        }) => {
+                            let typical = estimates.typical().point_estimate;
+                            let formatted = formatter.format_throughput(throughput, typical);
+                            println!("ok: {} {}", id, numbers.localize(formatted.trim_start()))
+                        }
+                        // budgets are checked only with throughputs
+                        _ => println!("ok: {} {}", id, estimate.point_estimate),
+                    },
+                    Err(e) => {
+                        println!("failed: {}", e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} budgets failed", failed).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn load_records(options: &Options, baseline: &str) -> Result<Vec<BenchRecord>, Box<dyn Error>> {
+    let mut records = results::load_records(&options.dir, baseline)
+        .map_err(|e| format!("failed to read {}: {}", options.dir.display(), e))?;
+    if let Some(group) = &options.group {
+        records.retain(|record| &record.group_id == group);
+    }
+    if records.is_empty() {
+        return Err(format!(
+            "no results of baseline `{}` in {}",
+            baseline,
+            options.dir.display()
+        )
+        .into());
+    }
+    Ok(records)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("criterion-invert: {}", e);
+        process::exit(1);
+    }
+}
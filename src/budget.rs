@@ -0,0 +1,336 @@
+//! Asserting budgets of time per element or byte on benchmark results
+//!
+//! Run the benchmarks first, then check their latest results against budgets,
+//! e.g. in a test run after `cargo bench` in CI:
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde")] {
+//! use criterion_inverted_throughput::budget::{assert_budget, Budget};
+//!
+//! // panics if `parse/1k` costs more than 75 ns per element
+//! assert_budget("parse/1k", Budget::ns_per_elem(75.0));
+//! # }
+//! ```
+//!
+//! Loading the results requires the `serde` feature, and [`Budget::check`] checks
+//! a loaded result without it.
+
+use crate::export::short;
+#[cfg(feature = "serde")]
+use crate::results;
+use crate::results::{BenchRecord, Estimate};
+use std::error::Error;
+use std::fmt;
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::str::FromStr;
+
+/// The maximum time per element, byte or bit allowed for a benchmark
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    limit: f64,
+    unit: &'static str,
+}
+
+impl Budget {
+    /// Returns a budget of `limit` nanoseconds per element
+    pub fn ns_per_elem(limit: f64) -> Self {
+        Budget {
+            limit,
+            unit: "elem",
+        }
+    }
+
+    /// Returns a budget of `limit` nanoseconds per byte
+    pub fn ns_per_byte(limit: f64) -> Self {
+        Budget {
+            limit,
+            unit: "byte",
+        }
+    }
+
+    /// Returns a budget of `limit` nanoseconds per bit, for `Throughput::Bits` of
+    /// criterion 0.7 or later
+    pub fn ns_per_bit(limit: f64) -> Self {
+        Budget { limit, unit: "bit" }
+    }
+
+    /// Returns the limit in nanoseconds per element, byte or bit
+    pub fn limit(&self) -> f64 {
+        self.limit
+    }
+
+    /// Parses a budget like [`Budget::from_str`], where `label` like `row` is also a label
+    /// of elements, as in `75 ns/row`
+    pub fn parse_labeled(s: &str, label: Option<&str>) -> Result<Self, String> {
+        let invalid = || format!("invalid budget `{}`, expected e.g. `75 ns/elem`", s);
+        let (limit, unit) = s.trim().split_once(' ').ok_or_else(invalid)?;
+        let limit: f64 = limit.parse().map_err(|_| invalid())?;
+        let (time_unit, denominator) = unit.trim().split_once('/').ok_or_else(invalid)?;
+        let nanos = match time_unit {
+            "ps" => 1e-3,
+            "ns" => 1.0,
+            "µs" | "us" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            _ => return Err(invalid()),
+        };
+        Ok(match denominator {
+            "elem" => Budget::ns_per_elem(limit * nanos),
+            "byte" => Budget::ns_per_byte(limit * nanos),
+            "bit" => Budget::ns_per_bit(limit * nanos),
+            _ if Some(denominator) == label => Budget::ns_per_elem(limit * nanos),
+            _ => {
+                return Err(format!(
+                    "unknown unit `{}` of budget `{}`, expected `elem`, `byte`, `bit`{}",
+                    denominator,
+                    s,
+                    label.map_or(String::new(), |label| format!(" or `{}`", label))
+                ))
+            }
+        })
+    }
+
+    /// Returns the unit of the budget, `elem`, `byte` or `bit`
+    pub fn unit(&self) -> &'static str {
+        self.unit
+    }
+
+    /// Checks the typical estimate per element, byte or bit of `record` is within the budget
+    ///
+    /// Returns the estimate per element, byte or bit if it is.
+    pub fn check(&self, record: &BenchRecord) -> Result<Estimate, BudgetError> {
+        let (Some(unit), Some(estimate)) = (record.unit(), record.time_per_unit()) else {
+            return Err(BudgetError::NoThroughput(record.id.clone()));
+        };
+        if unit != self.unit {
+            return Err(BudgetError::UnitMismatch {
+                id: record.id.clone(),
+                expected: self.unit,
+                actual: unit,
+            });
+        }
+        if estimate.point_estimate > self.limit {
+            return Err(BudgetError::Exceeded {
+                id: record.id.clone(),
+                budget: *self,
+                actual: estimate,
+            });
+        }
+        Ok(estimate)
+    }
+}
+
+impl FromStr for Budget {
+    type Err = String;
+
+    /// Parses a budget like `75 ns/elem`, `1.5 µs/byte` or `10 ps/bit`
+    ///
+    /// Other units like `ns/bytes` are rejected; see [`Budget::parse_labeled`] for labels
+    /// of elements like `ns/row`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Budget::parse_labeled(s, None)
+    }
+}
+
+/// The error returned when a benchmark is not checked within its budget
+#[derive(Debug)]
+pub enum BudgetError {
+    /// The results could not be read
+    Io(io::Error),
+    /// No result of the benchmark is stored
+    NotFound(String),
+    /// The benchmark has no throughput
+    NoThroughput(String),
+    /// The unit of the throughput differs from the one of the budget
+    UnitMismatch {
+        /// The id of the benchmark
+        id: String,
+        /// The unit of the budget
+        expected: &'static str,
+        /// The unit of the throughput
+        actual: &'static str,
+    },
+    /// The benchmark costs more than the budget
+    Exceeded {
+        /// The id of the benchmark
+        id: String,
+        /// The budget of the benchmark
+        budget: Budget,
+        /// The estimate per element or byte of the benchmark
+        actual: Estimate,
+    },
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::Io(e) => write!(f, "failed to read results: {}", e),
+            BudgetError::NotFound(id) => write!(f, "no result of `{}`", id),
+            BudgetError::NoThroughput(id) => write!(f, "`{}` has no throughput", id),
+            BudgetError::UnitMismatch {
+                id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "`{}` is measured per {} but the budget is per {}",
+                id, actual, expected
+            ),
+            BudgetError::Exceeded { id, budget, actual } => write!(
+                f,
+                "`{}` costs {} ns/{} over the budget {} ns/{}",
+                id,
+                short(actual.point_estimate),
+                budget.unit,
+                short(budget.limit),
+                budget.unit
+            ),
+        }
+    }
+}
+
+impl Error for BudgetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BudgetError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BudgetError {
+    fn from(e: io::Error) -> Self {
+        BudgetError::Io(e)
+    }
+}
+
+/// Checks the result of the benchmark `id` saved as `baseline` under `criterion_home`
+/// is within `budget`
+#[cfg(feature = "serde")]
+pub fn check_budget_in(
+    criterion_home: &Path,
+    baseline: &str,
+    id: &str,
+    budget: Budget,
+) -> Result<Estimate, BudgetError> {
+    let records = results::load_records(criterion_home, baseline)?;
+    let record = records
+        .iter()
+        .find(|record| record.id == id)
+        .ok_or_else(|| BudgetError::NotFound(id.to_string()))?;
+    budget.check(record)
+}
+
+/// Checks the latest result of the benchmark `id` is within `budget`
+#[cfg(feature = "serde")]
+pub fn check_budget(id: &str, budget: Budget) -> Result<Estimate, BudgetError> {
+    check_budget_in(
+        &results::criterion_home(),
+        results::LATEST_BASELINE,
+        id,
+        budget,
+    )
+}
+
+/// Asserts the latest result of the benchmark `id` is within `budget`
+///
+/// # Panics
+///
+/// Panics if the result exceeds the budget or cannot be checked.
+#[cfg(feature = "serde")]
+#[track_caller]
+pub fn assert_budget(id: &str, budget: Budget) {
+    if let Err(e) = check_budget(id, budget) {
+        panic!("budget assertion failed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RecordBuilder;
+    use criterion::Throughput;
+    use test_case::test_case;
+
+    fn record(throughput: Option<Throughput>, point_estimate: f64) -> BenchRecord {
+        RecordBuilder::new("parse/10", point_estimate)
+            .throughput(throughput)
+            .ids("parse", None, Some("10"))
+            .build()
+    }
+
+    #[test]
+    fn test_check() {
+        let record = record(Some(Throughput::Elements(10)), 750.0);
+        assert_eq!(
+            Budget::ns_per_elem(75.0)
+                .check(&record)
+                .unwrap()
+                .point_estimate,
+            75.0
+        );
+        assert_eq!(
+            Budget::ns_per_elem(74.9)
+                .check(&record)
+                .unwrap_err()
+                .to_string(),
+            "`parse/10` costs 75.000 ns/elem over the budget 74.900 ns/elem"
+        );
+        assert!(matches!(
+            Budget::ns_per_byte(75.0).check(&record),
+            Err(BudgetError::UnitMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("75 ns/elem".parse(), Ok(Budget::ns_per_elem(75.0)));
+        assert_eq!("1.5 µs/byte".parse(), Ok(Budget::ns_per_byte(1500.0)));
+        assert_eq!("10 ps/bit".parse(), Ok(Budget::ns_per_bit(0.01)));
+        assert!("75".parse::<Budget>().is_err());
+        assert!("75 min/elem".parse::<Budget>().is_err());
+        assert_eq!(
+            Budget::parse_labeled("2 ms/row", Some("row")),
+            Ok(Budget::ns_per_elem(2e6))
+        );
+    }
+
+    #[test_case("50 ns/bytes" ; "plural")]
+    #[test_case("50 ns/B" ; "symbol")]
+    #[test_case("50 ns/row" ; "label of another rule")]
+    #[test_case("50 ns/" ; "empty")]
+    fn test_parse_unknown_unit(budget: &str) {
+        assert_eq!(
+            Budget::parse_labeled(budget, Some("frame")).unwrap_err(),
+            format!(
+                "unknown unit `{}` of budget `{}`, expected `elem`, `byte`, `bit` or `frame`",
+                budget.split_once('/').unwrap().1,
+                budget
+            )
+        );
+        assert!(budget.parse::<Budget>().is_err());
+    }
+
+    #[test]
+    fn test_check_bits() {
+        let record = record(Some(Throughput::Bytes(10)), 750.0);
+        assert!(matches!(
+            Budget::ns_per_bit(75.0).check(&record),
+            Err(BudgetError::UnitMismatch {
+                expected: "bit",
+                actual: "byte",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_check_without_throughput() {
+        assert!(matches!(
+            Budget::ns_per_elem(75.0).check(&record(None, 750.0)),
+            Err(BudgetError::NoThroughput(_))
+        ));
+    }
+}
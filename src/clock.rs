@@ -0,0 +1,257 @@
+//! Clock sources of [`InvertedThroughput`](crate::InvertedThroughput)
+//!
+//! By default, times are measured with `std::time::Instant` like criterion's `WallTime`.
+//! Select another clock with [`InvertedThroughput::with_clock`](crate::InvertedThroughput::with_clock),
+//! e.g. [`ClockSource::MonotonicRaw`] not slewed by NTP, or [`ClockSource::Boottime`]
+//! counting the time while the system is suspended.
+
+use std::time::Duration;
+
+/// The clock to measure times with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClockSource {
+    /// `std::time::Instant`, same as criterion's `WallTime`
+    #[default]
+    Instant,
+    /// `CLOCK_MONOTONIC` (Unix)
+    Monotonic,
+    /// `CLOCK_MONOTONIC_RAW`, not adjusted by NTP (Linux, Android and Apple platforms)
+    MonotonicRaw,
+    /// `CLOCK_BOOTTIME`, including the time the system is suspended (Linux and Android)
+    Boottime,
+    /// `QueryPerformanceCounter` (Windows)
+    PerformanceCounter,
+}
+
+impl ClockSource {
+    /// Returns whether the clock is available on this platform
+    pub fn is_supported(self) -> bool {
+        self == ClockSource::Instant || self.now().is_some()
+    }
+
+    /// Returns the current time of the clock in nanoseconds,
+    /// or `None` for [`ClockSource::Instant`] and unsupported clocks
+    pub(crate) fn now(self) -> Option<u64> {
+        match self {
+            ClockSource::Instant => None,
+            ClockSource::PerformanceCounter => performance_counter(),
+            _ => clock_gettime(self),
+        }
+    }
+}
+
+/// The start of a measurement by [`InvertedThroughput`](crate::InvertedThroughput)
+pub struct ClockStart<I>(pub(crate) Start<I>);
+
+pub(crate) enum Start<I> {
+    // the intermediate of the inner measurement
+    Inner(I),
+    // nanoseconds of the selected clock
+    Nanos(u64),
+}
+
+pub(crate) fn elapsed(clock: ClockSource, start: u64) -> Duration {
+    let end = clock
+        .now()
+        .unwrap_or_else(|| panic!("{:?} is not supported", clock));
+    Duration::from_nanos(end.saturating_sub(start))
+}
+
+#[cfg(unix)]
+fn clock_gettime(clock: ClockSource) -> Option<u64> {
+    let clock_id = match clock {
+        ClockSource::Monotonic => libc::CLOCK_MONOTONIC,
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios"
+        ))]
+        ClockSource::MonotonicRaw => libc::CLOCK_MONOTONIC_RAW,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        ClockSource::Boottime => libc::CLOCK_BOOTTIME,
+        _ => return None,
+    };
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid pointer to a `timespec`
+    if unsafe { libc::clock_gettime(clock_id, &mut ts) } != 0 {
+        return None;
+    }
+    Some(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+}
+
+#[cfg(not(unix))]
+fn clock_gettime(_clock: ClockSource) -> Option<u64> {
+    None
+}
+
+#[cfg(windows)]
+fn performance_counter() -> Option<u64> {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn QueryPerformanceCounter(count: *mut i64) -> i32;
+        fn QueryPerformanceFrequency(frequency: *mut i64) -> i32;
+    }
+
+    let mut count = 0;
+    let mut frequency = 0;
+    // SAFETY: the pointers are valid pointers to `i64`
+    if unsafe { QueryPerformanceCounter(&mut count) == 0 }
+        || unsafe { QueryPerformanceFrequency(&mut frequency) == 0 }
+        || frequency <= 0
+    {
+        return None;
+    }
+    Some((count as u128 * 1_000_000_000 / frequency as u128) as u64)
+}
+
+#[cfg(not(windows))]
+fn performance_counter() -> Option<u64> {
+    None
+}
+
+/// Returns the CPU time consumed by all threads of the process in nanoseconds,
+/// or `None` if it is not supported on this platform
+pub(crate) fn process_cpu_time() -> Option<u64> {
+    #[cfg(unix)]
+    {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `ts` is a valid pointer to a `timespec`
+        if unsafe { libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut ts) } != 0 {
+            return None;
+        }
+        Some(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+    }
+    #[cfg(windows)]
+    {
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetCurrentProcess() -> isize;
+            fn GetProcessTimes(
+                process: isize,
+                creation: *mut u64,
+                exit: *mut u64,
+                kernel: *mut u64,
+                user: *mut u64,
+            ) -> i32;
+        }
+
+        let (mut creation, mut exit, mut kernel, mut user) = (0, 0, 0, 0);
+        // SAFETY: the pointers are valid pointers to `u64` laid out like `FILETIME`
+        if unsafe {
+            GetProcessTimes(
+                GetCurrentProcess(),
+                &mut creation,
+                &mut exit,
+                &mut kernel,
+                &mut user,
+            )
+        } == 0
+        {
+            return None;
+        }
+        // in 100 nanoseconds
+        Some((kernel + user) * 100)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        None
+    }
+}
+
+/// Returns the frequency of the CPU in GHz, or `None` if it is not detected
+///
+/// On Linux, it is the maximum frequency of the first CPU in sysfs, or the current one
+/// in `/proc/cpuinfo`. On macOS, it is `hw.cpufrequency`, which Apple silicon does not have.
+pub fn cpu_frequency_ghz() -> Option<f64> {
+    #[cfg(target_os = "linux")]
+    {
+        let max_khz =
+            std::fs::read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq")
+                .ok()
+                .and_then(|khz| khz.trim().parse::<f64>().ok());
+        match max_khz {
+            Some(khz) => Some(khz / 1e6),
+            None => cpuinfo_ghz(&std::fs::read_to_string("/proc/cpuinfo").ok()?),
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut hz: u64 = 0;
+        let mut size = std::mem::size_of::<u64>();
+        // SAFETY: the name is NUL-terminated, and `hz` is valid for `size` bytes
+        let result = unsafe {
+            libc::sysctlbyname(
+                b"hw.cpufrequency\0".as_ptr() as *const libc::c_char,
+                &mut hz as *mut u64 as *mut libc::c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        (result == 0 && hz > 0).then(|| hz as f64 / 1e9)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+// returns the frequency of the first CPU in `/proc/cpuinfo` in GHz
+#[cfg(target_os = "linux")]
+fn cpuinfo_ghz(cpuinfo: &str) -> Option<f64> {
+    cpuinfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "cpu MHz")
+        .and_then(|(_, mhz)| mhz.trim().parse::<f64>().ok())
+        .map(|mhz| mhz / 1e3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instant_is_supported() {
+        assert!(ClockSource::Instant.is_supported());
+        assert_eq!(ClockSource::Instant.now(), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_clocks() {
+        for clock in [
+            ClockSource::Monotonic,
+            ClockSource::MonotonicRaw,
+            ClockSource::Boottime,
+        ] {
+            assert!(clock.is_supported());
+            let start = clock.now().unwrap();
+            assert!(clock.now().unwrap() >= start);
+        }
+        assert!(!ClockSource::PerformanceCounter.is_supported());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_process_cpu_time() {
+        let start = process_cpu_time().unwrap();
+        assert!(process_cpu_time().unwrap() >= start);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cpuinfo_ghz() {
+        let cpuinfo = "processor\t: 0\nmodel name\t: CPU\ncpu MHz\t\t: 3000.000\n\nprocessor\t: 1\ncpu MHz\t\t: 1000.0\n";
+        assert_eq!(cpuinfo_ghz(cpuinfo), Some(3.0));
+        assert_eq!(cpuinfo_ghz("processor\t: 0\n"), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Benchmarking with cold caches
+//!
+//! Criterion warms up the routine and runs it back to back, so the data it touches stays
+//! in the caches and the time per element is the steady-state one. Lookup tables and other
+//! data touched once in a while are slower when they are evicted between calls.
+//! [`iter_cold`] evicts the caches by touching a large buffer before each iteration, and
+//! [`bench_cold_and_warm`] registers the benchmark twice to print both costs:
+//!
+//! ```no_run
+//! use criterion::{Criterion, Throughput};
+//! use criterion_inverted_throughput::cold_cache::bench_cold_and_warm;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//! use std::hint::black_box;
+//!
+//! # let table = vec![0u32; 1 << 16];
+//! # let keys = vec![1usize, 100, 10000];
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("table");
+//! g.throughput(Throughput::Elements(keys.len() as u64));
+//! // `table/lookup/warm` and `table/lookup/cold`
+//! bench_cold_and_warm(&mut g, "lookup", || {
+//!     keys.iter().map(|&key| table[black_box(key)]).sum::<u32>()
+//! });
+//! g.finish();
+//! ```
+//!
+//! Each cold iteration is measured alone, so the overhead of the measurement is included
+//! in the time of the iteration. The buffer evicts the data caches, but not necessarily
+//! the instruction caches or the TLBs.
+
+use criterion::measurement::Measurement;
+use criterion::{BatchSize, Bencher, BenchmarkGroup};
+use std::hint::black_box;
+
+/// The size of the buffer [`CacheEvictor::new`] touches, larger than the last level caches
+/// of most machines
+pub const DEFAULT_EVICTION_BYTES: usize = 64 << 20;
+
+// the stride of touches, the size of a cache line of most machines
+const CACHE_LINE_BYTES: usize = 64;
+
+/// The buffer touched to evict the data caches
+pub struct CacheEvictor {
+    buffer: Vec<u8>,
+}
+
+impl CacheEvictor {
+    /// Returns a `CacheEvictor` touching [`DEFAULT_EVICTION_BYTES`] bytes
+    pub fn new() -> Self {
+        Self::with_size(DEFAULT_EVICTION_BYTES)
+    }
+
+    /// Returns a `CacheEvictor` touching `bytes` bytes, which should be larger than
+    /// the last level cache
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is zero.
+    pub fn with_size(bytes: usize) -> Self {
+        assert_ne!(bytes, 0, "the size of the eviction buffer must not be zero");
+        CacheEvictor {
+            buffer: vec![0; bytes],
+        }
+    }
+
+    /// Returns the size of the buffer in bytes
+    pub fn size(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Writes a cache line after another over the buffer, evicting the data of other
+    /// cache lines
+    pub fn evict(&mut self) {
+        for byte in self.buffer.iter_mut().step_by(CACHE_LINE_BYTES) {
+            *byte = byte.wrapping_add(1);
+        }
+        black_box(&mut self.buffer);
+    }
+}
+
+impl Default for CacheEvictor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs `routine` as [`Bencher::iter`] does, evicting the caches with `evictor` before
+/// each iteration
+///
+/// The evictions are not measured.
+pub fn iter_cold<M, O, R>(bencher: &mut Bencher<'_, M>, evictor: &mut CacheEvictor, mut routine: R)
+where
+    M: Measurement,
+    R: FnMut() -> O,
+{
+    bencher.iter_batched(|| evictor.evict(), |()| routine(), BatchSize::PerIteration);
+}
+
+/// Registers `routine` in `group` twice, as `{id}/warm` run by [`Bencher::iter`] and
+/// `{id}/cold` run by [`iter_cold`] with a [`CacheEvictor::new`]
+///
+/// The throughput of the group is shared, so both print times per element or byte.
+pub fn bench_cold_and_warm<M, O, R>(group: &mut BenchmarkGroup<'_, M>, id: &str, mut routine: R)
+where
+    M: Measurement,
+    R: FnMut() -> O,
+{
+    group.bench_function(format!("{}/warm", id), |b| b.iter(&mut routine));
+    let mut evictor = CacheEvictor::new();
+    group.bench_function(format!("{}/cold", id), |b| {
+        iter_cold(b, &mut evictor, &mut routine)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict() {
+        let mut evictor = CacheEvictor::with_size(200);
+        assert_eq!(evictor.size(), 200);
+        evictor.evict();
+        evictor.evict();
+        let touched: Vec<usize> = (0..evictor.size())
+            .filter(|&i| evictor.buffer[i] == 2)
+            .collect();
+        assert_eq!(touched, vec![0, 64, 128, 192]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_evictor() {
+        CacheEvictor::with_size(0);
+    }
+}
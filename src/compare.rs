@@ -0,0 +1,231 @@
+//! Comparing two baselines of benchmark results in time per element or byte
+//!
+//! Like [critcmp](https://github.com/BurntSushi/critcmp), but the differences are
+//! reported as deltas of `[time]/[element or byte]` and percentage changes
+//! instead of `[elements or bytes]/s`.
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde")] {
+//! use criterion_inverted_throughput::compare::{compare, table};
+//! use criterion_inverted_throughput::results::{criterion_home, load_records};
+//!
+//! let old = load_records(&criterion_home(), "main").unwrap();
+//! let new = load_records(&criterion_home(), "new").unwrap();
+//! print!("{}", table(&compare(&old, &new)));
+//! # }
+//! ```
+
+use crate::export::short;
+use crate::results::{BenchRecord, Estimate};
+use criterion::measurement::{Measurement, WallTime};
+
+/// The difference of a benchmark between two baselines
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comparison {
+    /// The full id of the benchmark like `group/function/value`
+    pub id: String,
+    /// The unit of the throughput, `elem` or `byte`
+    pub unit: &'static str,
+    /// The typical estimate per element or byte in the old baseline
+    pub old: Estimate,
+    /// The typical estimate per element or byte in the new baseline
+    pub new: Estimate,
+}
+
+// `Comparison` with its unit not static, to be deserialized
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawComparison {
+    id: String,
+    unit: String,
+    old: Estimate,
+    new: Estimate,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Comparison {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = RawComparison::deserialize(deserializer)?;
+        Ok(Comparison {
+            id: raw.id,
+            unit: crate::static_unit(&raw.unit).map_err(serde::de::Error::custom)?,
+            old: raw.old,
+            new: raw.new,
+        })
+    }
+}
+
+impl Comparison {
+    /// Returns the difference of the point estimates per element or byte in nanoseconds,
+    /// positive for regressions
+    pub fn delta(&self) -> f64 {
+        self.new.point_estimate - self.old.point_estimate
+    }
+
+    /// Returns the change of the point estimates in percent, positive for regressions
+    pub fn change(&self) -> f64 {
+        (self.new.point_estimate / self.old.point_estimate - 1.0) * 100.0
+    }
+}
+
+/// Compares the benchmarks in both `old` and `new` in time per element or byte,
+/// sorted by delta with the largest regression first
+///
+/// Benchmarks without throughput, or whose unit differs between the baselines, are skipped.
+/// The throughput amounts may differ since the estimates are compared per element or byte.
+pub fn compare(old: &[BenchRecord], new: &[BenchRecord]) -> Vec<Comparison> {
+    let mut comparisons: Vec<Comparison> = new
+        .iter()
+        .filter_map(|new| {
+            let old = old.iter().find(|old| old.id == new.id)?;
+            let unit = new.unit()?;
+            if old.unit()? != unit {
+                return None;
+            }
+            Some(Comparison {
+                id: new.id.clone(),
+                unit,
+                old: old.time_per_unit()?,
+                new: new.time_per_unit()?,
+            })
+        })
+        .collect();
+    comparisons.sort_by(|a, b| b.delta().total_cmp(&a.delta()));
+    comparisons
+}
+
+/// Returns a text table of `comparisons` with a row per benchmark like
+/// `benchmark  old  new  delta  change`
+pub fn table(comparisons: &[Comparison]) -> String {
+    let formatter = WallTime;
+    let header = [
+        "benchmark".to_string(),
+        "old".to_string(),
+        "new".to_string(),
+        "delta".to_string(),
+        "change".to_string(),
+    ];
+    let rows: Vec<[String; 5]> = comparisons
+        .iter()
+        .map(|comparison| {
+            let mut values = [
+                comparison.old.point_estimate,
+                comparison.new.point_estimate,
+                comparison.delta(),
+            ];
+            // scale all values with the old one to show them in the same unit
+            let time_unit = formatter
+                .formatter()
+                .scale_values(comparison.old.point_estimate, &mut values);
+            let unit = format!("{}/{}", time_unit, comparison.unit);
+            [
+                comparison.id.clone(),
+                format!("{} {}", short(values[0]), unit),
+                format!("{} {}", short(values[1]), unit),
+                format!("{}{} {}", sign(values[2]), short(values[2].abs()), unit),
+                format!(
+                    "{}{:.2}%",
+                    sign(comparison.change()),
+                    comparison.change().abs()
+                ),
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 5];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let mut line = format!("{:<width$}", row[0], width = widths[0]);
+        for (width, cell) in widths.iter().zip(row).skip(1) {
+            line.push_str(&format!("  {:>width$}", cell, width = width));
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn sign(value: f64) -> &'static str {
+    if value < 0.0 {
+        "-"
+    } else {
+        "+"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RecordBuilder;
+    use criterion::Throughput;
+
+    fn record(id: &str, throughput: Option<Throughput>, point_estimate: f64) -> BenchRecord {
+        RecordBuilder::new(id, point_estimate)
+            .throughput(throughput)
+            .build()
+    }
+
+    fn comparisons() -> Vec<Comparison> {
+        let old = vec![
+            record("parse/10", Some(Throughput::Elements(10)), 1000.0),
+            record("parse/20", Some(Throughput::Elements(20)), 2000.0),
+            record("parse/bytes", Some(Throughput::Bytes(1000)), 2000.0),
+            record("parse/none", None, 1000.0),
+            record("parse/unit", Some(Throughput::Bytes(10)), 1000.0),
+        ];
+        let new = vec![
+            record("parse/10", Some(Throughput::Elements(10)), 1100.0),
+            record("parse/20", Some(Throughput::Elements(20)), 1500.0),
+            // compared per byte even if the amount changed
+            record("parse/bytes", Some(Throughput::Bytes(2000)), 4000.0),
+            record("parse/none", None, 1000.0),
+            record("parse/unit", Some(Throughput::Elements(10)), 1000.0),
+            record("parse/added", Some(Throughput::Elements(10)), 1000.0),
+        ];
+        compare(&old, &new)
+    }
+
+    #[test]
+    fn test_compare() {
+        let comparisons = comparisons();
+        let ids: Vec<&str> = comparisons.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["parse/10", "parse/bytes", "parse/20"]);
+        assert_eq!(comparisons[0].unit, "elem");
+        assert_eq!(comparisons[0].delta(), 10.0);
+        assert!((comparisons[0].change() - 10.0).abs() < 1e-9);
+        assert_eq!(comparisons[1].delta(), 0.0);
+        assert_eq!(comparisons[2].delta(), -25.0);
+        assert_eq!(comparisons[2].change(), -25.0);
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            table(&comparisons()),
+            "benchmark               old             new            delta   change\n\
+             parse/10     100.00 ns/elem  110.00 ns/elem  +10.000 ns/elem  +10.00%\n\
+             parse/bytes  2.0000 ns/byte  2.0000 ns/byte  +0.0000 ns/byte   +0.00%\n\
+             parse/20     100.00 ns/elem  75.000 ns/elem  -25.000 ns/elem  -25.00%\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let comparisons = comparisons();
+        let json = serde_json::to_string(&comparisons).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Comparison>>(&json).unwrap(),
+            comparisons
+        );
+    }
+}
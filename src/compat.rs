@@ -0,0 +1,70 @@
+//! The shim over the supported major versions of criterion
+//!
+//! The features `criterion_0_4` to `criterion_0_8` select the version, which the crate
+//! uses as `criterion` (see the `extern crate` items in the crate root). They are mutually
+//! exclusive, so `--all-features` fails to compile.
+//!
+//! `Measurement` and `ValueFormatter` are the same in these versions, and `Throughput`
+//! gained variants, which are matched only here.
+
+use criterion::Throughput;
+
+/// The kind of the amount of a throughput
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Amount {
+    Bytes,
+    BytesDecimal,
+    Elements,
+    // criterion 0.7 or later
+    #[allow(dead_code)]
+    Bits,
+}
+
+/// Returns the amount of `throughput` and its kind
+///
+/// `Throughput::ElementsAndBytes` of criterion 0.8 is counted in elements as criterion does.
+pub(crate) fn amount(throughput: &Throughput) -> (u64, Amount) {
+    match *throughput {
+        Throughput::Bytes(v) => (v, Amount::Bytes),
+        Throughput::BytesDecimal(v) => (v, Amount::BytesDecimal),
+        Throughput::Elements(v) => (v, Amount::Elements),
+        #[cfg(any(feature = "criterion_0_7", feature = "criterion_0_8"))]
+        Throughput::Bits(v) => (v, Amount::Bits),
+        #[cfg(feature = "criterion_0_8")]
+        Throughput::ElementsAndBytes { elements, .. } => (elements, Amount::Elements),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amount() {
+        assert_eq!(amount(&Throughput::Bytes(10)), (10, Amount::Bytes));
+        assert_eq!(
+            amount(&Throughput::BytesDecimal(10)),
+            (10, Amount::BytesDecimal)
+        );
+        assert_eq!(amount(&Throughput::Elements(10)), (10, Amount::Elements));
+    }
+
+    #[cfg(feature = "criterion_0_8")]
+    #[test]
+    fn test_amount_0_8() {
+        use crate::InvertedThroughput;
+        use criterion::measurement::ValueFormatter;
+
+        assert_eq!(amount(&Throughput::Bits(80)), (80, Amount::Bits));
+        let throughput = Throughput::ElementsAndBytes {
+            elements: 10,
+            bytes: 80,
+        };
+        assert_eq!(amount(&throughput), (10, Amount::Elements));
+        let measure = InvertedThroughput::new();
+        assert_eq!(
+            measure.format_throughput(&Throughput::Bits(80), 1234.0),
+            "15.425 ns/bit"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Empirical complexity of benchmarks across input sizes
+//!
+//! A group benchmarking a function with sizes like 1k, 10k and 100k elements has
+//! the cost per element at each size. [`estimate`] fits the cost per element `c(n)` to
+//! `k`, `k·log2(n)` and `k·n`, and picks the one with the least normalized root mean
+//! square error, which tells the growth of the total time in big-O:
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde")] {
+//! use criterion_inverted_throughput::complexity::estimate;
+//! use criterion_inverted_throughput::results::{criterion_home, load_records, LATEST_BASELINE};
+//!
+//! // after `cargo bench`
+//! let records = load_records(&criterion_home(), LATEST_BASELINE).unwrap();
+//! for complexity in estimate(&records) {
+//!     // like `sort/std: O(n log n), 3.1250 ns/elem·log2(n) (rms 0.52%, 3 sizes)`
+//!     println!("{}", complexity);
+//! }
+//! # }
+//! ```
+//!
+//! The benchmarks of a function are those sharing the group and the function id with
+//! different throughputs. Noisy results or sizes in a narrow range may fit the wrong growth,
+//! so check the error, and sweep sizes over a few orders of magnitude.
+
+use crate::export::short;
+use crate::results::BenchRecord;
+use crate::units::{checked_amount, scale_time};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The growth of the cost per element or byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Growth {
+    /// Constant cost per element, `O(n)` in total
+    Constant,
+    /// Cost per element growing with `log n`, `O(n log n)` in total
+    Logarithmic,
+    /// Cost per element growing with `n`, `O(n²)` in total
+    Linear,
+}
+
+impl Growth {
+    /// Returns the growth of the total time in big-O like `O(n log n)`
+    pub fn big_o(self) -> &'static str {
+        match self {
+            Growth::Constant => "O(n)",
+            Growth::Logarithmic => "O(n log n)",
+            Growth::Linear => "O(n²)",
+        }
+    }
+
+    // the function of the size the cost per element is proportional to
+    fn scale(self, n: f64) -> f64 {
+        match self {
+            Growth::Constant => 1.0,
+            Growth::Logarithmic => n.log2(),
+            Growth::Linear => n,
+        }
+    }
+}
+
+/// The fit of the costs per element or byte to a growth
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fit {
+    /// The growth of the cost per element or byte
+    pub growth: Growth,
+    /// The coefficient `k` of the growth in nanoseconds per element or byte,
+    /// the asymptotic cost of a constant growth
+    pub coefficient: f64,
+    /// The root mean square error divided by the mean cost
+    pub rms: f64,
+}
+
+impl Fit {
+    /// Returns the cost per element or byte the fit predicts for `n` elements or bytes
+    /// in nanoseconds
+    pub fn predict(&self, n: f64) -> f64 {
+        self.coefficient * self.growth.scale(n)
+    }
+}
+
+/// Fits `points` of sizes and costs per element or byte in nanoseconds to the growth
+/// with the least normalized root mean square error
+///
+/// Returns `None` if there are less than 2 distinct sizes.
+pub fn fit(points: &[(f64, f64)]) -> Option<Fit> {
+    let mut sizes: Vec<f64> = points.iter().map(|&(n, _)| n).collect();
+    sizes.sort_by(f64::total_cmp);
+    sizes.dedup();
+    if sizes.len() < 2 {
+        return None;
+    }
+    let mean = points.iter().map(|&(_, cost)| cost).sum::<f64>() / points.len() as f64;
+    [Growth::Constant, Growth::Logarithmic, Growth::Linear]
+        .into_iter()
+        .map(|growth| {
+            // the least squares of `cost = k·scale(n)`
+            let (cross, square) = points
+                .iter()
+                .fold((0.0, 0.0), |(cross, square), &(n, cost)| {
+                    let scale = growth.scale(n);
+                    (cross + cost * scale, square + scale * scale)
+                });
+            let coefficient = cross / square;
+            let squared_error = points
+                .iter()
+                .map(|&(n, cost)| (cost - coefficient * growth.scale(n)).powi(2))
+                .sum::<f64>();
+            let rms = (squared_error / points.len() as f64).sqrt() / mean;
+            Fit {
+                growth,
+                coefficient,
+                rms,
+            }
+        })
+        .min_by(|a, b| a.rms.total_cmp(&b.rms))
+}
+
+/// The estimated complexity of a function of a group
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Complexity {
+    /// The group and the function like `group/function`
+    pub id: String,
+    /// The unit of the throughputs, `elem` or `byte`
+    pub unit: &'static str,
+    /// The number of sizes fitted
+    pub sizes: usize,
+    /// The best fit
+    pub fit: Fit,
+}
+
+impl fmt::Display for Complexity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut values = [self.fit.coefficient];
+        let time_unit = scale_time(self.fit.coefficient, &mut values);
+        let scale = match self.fit.growth {
+            Growth::Constant => "",
+            Growth::Logarithmic => "·log2(n)",
+            Growth::Linear => "·n",
+        };
+        write!(
+            f,
+            "{}: {}, {} {}/{}{} (rms {:.2}%, {} sizes)",
+            self.id,
+            self.fit.growth.big_o(),
+            short(values[0]),
+            time_unit,
+            self.unit,
+            scale,
+            self.fit.rms * 100.0,
+            self.sizes
+        )
+    }
+}
+
+/// Estimates the complexity of each function of `records` benchmarked with 2 or more sizes,
+/// sorted by id
+///
+/// Benchmarks without throughput, and functions with mixed units, are skipped.
+pub fn estimate(records: &[BenchRecord]) -> Vec<Complexity> {
+    let mut functions: BTreeMap<String, Vec<(&'static str, f64, f64)>> = BTreeMap::new();
+    for record in records {
+        let (Some(throughput), Some(estimate)) = (&record.throughput, record.time_per_unit())
+        else {
+            continue;
+        };
+        let Ok((amount, unit)) = checked_amount(throughput) else {
+            continue;
+        };
+        let id = match &record.function_id {
+            Some(function_id) => format!("{}/{}", record.group_id, function_id),
+            None => record.group_id.clone(),
+        };
+        functions
+            .entry(id)
+            .or_default()
+            .push((unit, amount, estimate.point_estimate));
+    }
+    functions
+        .into_iter()
+        .filter_map(|(id, points)| {
+            let unit = points[0].0;
+            if points.iter().any(|&(u, _, _)| u != unit) {
+                return None;
+            }
+            let points: Vec<(f64, f64)> = points.iter().map(|&(_, n, cost)| (n, cost)).collect();
+            Some(Complexity {
+                id,
+                unit,
+                sizes: points.len(),
+                fit: fit(&points)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RecordBuilder;
+    use criterion::Throughput;
+    use test_case::test_case;
+
+    fn record(function_id: &str, elements: u64, point_estimate: f64) -> BenchRecord {
+        RecordBuilder::new(
+            &format!("sort/{}/{}", function_id, elements),
+            point_estimate,
+        )
+        .throughput(Some(Throughput::Elements(elements)))
+        .margin(0.0)
+        .ids("sort", Some(function_id), Some(&elements.to_string()))
+        .build()
+    }
+
+    #[test_case(|_| 3.0, Growth::Constant ; "constant")]
+    #[test_case(|n: f64| 2.0 * n.log2() * (1.0 + 0.01 * n.log10().sin()), Growth::Logarithmic ; "logarithmic")]
+    #[test_case(|n| 0.5 * n + 1.0, Growth::Linear ; "linear")]
+    fn test_fit(cost: fn(f64) -> f64, expected: Growth) {
+        let points: Vec<(f64, f64)> = [1e3, 1e4, 1e5, 1e6]
+            .into_iter()
+            .map(|n| (n, cost(n)))
+            .collect();
+        assert_eq!(fit(&points).unwrap().growth, expected);
+    }
+
+    #[test]
+    fn test_fit_single_size() {
+        assert_eq!(fit(&[(10.0, 1.0), (10.0, 2.0)]), None);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let records = [
+            // total times of 10 ns per element
+            record("radix", 1_000, 10e3),
+            record("radix", 100_000, 10e5),
+            // total times of 2·n·log2(n) ns
+            record("merge", 1 << 10, 20.0 * (1 << 10) as f64),
+            record("merge", 1 << 20, 40.0 * (1 << 20) as f64),
+            record("single", 1_000, 10e3),
+        ];
+        let complexities = estimate(&records);
+        assert_eq!(complexities.len(), 2);
+        assert_eq!(complexities[0].id, "sort/merge");
+        assert_eq!(complexities[0].fit.growth, Growth::Logarithmic);
+        assert_eq!(complexities[0].fit.predict(1024.0), 20.0);
+        assert_eq!(
+            complexities[1].to_string(),
+            "sort/radix: O(n), 10.000 ns/elem (rms 0.00%, 2 sizes)"
+        );
+    }
+}
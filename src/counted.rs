@@ -0,0 +1,171 @@
+//! Throughputs counted while benchmarking
+//!
+//! Criterion divides by the throughput set before a benchmark, but inputs like fuzzed
+//! corpora have element counts known only after processing them. [`iter_counted`] gives
+//! the routine a [`Counter`] to add the processed elements to, and scales the time of
+//! each sample to the time per counted element, so that it is printed with
+//! `Throughput::Elements(1)`. [`bench_counted`] sets the throughput and registers
+//! the benchmark at once:
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::counted::bench_counted;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! # let inputs = vec!["1,2,3".to_string(), "4,5".to_string()];
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("parse");
+//! let mut inputs = inputs.iter().cycle();
+//! bench_counted(&mut g, "fuzzed", |counter| {
+//!     let fields = inputs.next().unwrap().split(',').count();
+//!     counter.add(fields as u64);
+//!     fields
+//! });
+//! g.finish();
+//! ```
+//!
+//! The time per element of a sample is the total time divided by the total count, so
+//! iterations processing more elements weigh more. The `time` line shows the time per
+//! element too. Regions paused by [`pause`](crate::pausable::pause) are excluded.
+
+use crate::pausable::Stopwatch;
+use criterion::measurement::Measurement;
+use criterion::{Bencher, BenchmarkGroup, Throughput};
+use std::hint::black_box;
+use std::time::Duration;
+
+/// The counter of the elements or bytes processed in a sample
+#[derive(Debug, Default)]
+pub struct Counter {
+    count: u64,
+}
+
+impl Counter {
+    /// Adds `count` processed elements or bytes
+    pub fn add(&mut self, count: u64) {
+        self.count += count;
+    }
+
+    /// Returns the elements or bytes counted so far in the sample
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Runs `routine` in [`Bencher::iter_custom`], scaling the times to the times per element
+/// or byte added to the [`Counter`]
+///
+/// Set `Throughput::Elements(1)` or `Throughput::Bytes(1)` to the group.
+///
+/// # Panics
+///
+/// Panics if the routine counts nothing in a sample.
+pub fn iter_counted<M, O, R>(bencher: &mut Bencher<'_, M>, mut routine: R)
+where
+    M: Measurement<Value = Duration>,
+    R: FnMut(&mut Counter) -> O,
+{
+    bencher.iter_custom(|iters| {
+        let mut counter = Counter::default();
+        let stopwatch = Stopwatch::start();
+        for _ in 0..iters {
+            black_box(routine(&mut counter));
+        }
+        per_count(stopwatch.elapsed(), iters, counter.count)
+    });
+}
+
+// scales the time of `iters` iterations, which criterion divides by `iters`,
+// to the time per counted element
+fn per_count(elapsed: Duration, iters: u64, count: u64) -> Duration {
+    assert!(count > 0, "the routine counted no elements or bytes");
+    let nanos = elapsed.as_nanos() * iters as u128 / count as u128;
+    Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+}
+
+/// Registers the benchmark `id` in `group` running `routine` by [`iter_counted`] with
+/// `Throughput::Elements(1)`
+pub fn bench_counted<M, O, R>(group: &mut BenchmarkGroup<'_, M>, id: &str, routine: R)
+where
+    M: Measurement<Value = Duration>,
+    R: FnMut(&mut Counter) -> O,
+{
+    bench_with_throughput(group, id, Throughput::Elements(1), routine);
+}
+
+/// Registers the benchmark `id` in `group` running `routine` by [`iter_counted`] with
+/// `Throughput::Bytes(1)`
+pub fn bench_counted_bytes<M, O, R>(group: &mut BenchmarkGroup<'_, M>, id: &str, routine: R)
+where
+    M: Measurement<Value = Duration>,
+    R: FnMut(&mut Counter) -> O,
+{
+    bench_with_throughput(group, id, Throughput::Bytes(1), routine);
+}
+
+fn bench_with_throughput<M, O, R>(
+    group: &mut BenchmarkGroup<'_, M>,
+    id: &str,
+    throughput: Throughput,
+    mut routine: R,
+) where
+    M: Measurement<Value = Duration>,
+    R: FnMut(&mut Counter) -> O,
+{
+    group.throughput(throughput);
+    group.bench_function(id, |b| iter_counted(b, &mut routine));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use crate::results::{load_records, LATEST_BASELINE};
+    #[cfg(feature = "serde")]
+    use criterion::Criterion;
+    #[cfg(feature = "serde")]
+    use std::env;
+    #[cfg(feature = "serde")]
+    use std::fs;
+
+    #[test]
+    fn test_per_count() {
+        // 10 iterations of 4 elements in 2 µs
+        assert_eq!(
+            per_count(Duration::from_micros(2), 10, 40),
+            Duration::from_nanos(500)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_nothing_counted() {
+        per_count(Duration::from_micros(2), 10, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bench_counted() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-counted");
+        let _ = fs::remove_dir_all(&home);
+        let mut c = Criterion::default()
+            .output_directory(&home)
+            .sample_size(10)
+            .warm_up_time(Duration::from_millis(1))
+            .measurement_time(Duration::from_millis(10))
+            .without_plots();
+        let mut g = c.benchmark_group("sum");
+        let mut sizes = [10u64, 1000].into_iter().cycle();
+        bench_counted(&mut g, "variable", |counter| {
+            let size = sizes.next().unwrap();
+            counter.add(size);
+            (0..size).map(black_box).sum::<u64>()
+        });
+        g.finish();
+
+        let records = load_records(&home, LATEST_BASELINE).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].throughput, Some(Throughput::Elements(1)));
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
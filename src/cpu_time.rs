@@ -0,0 +1,361 @@
+//! Wall time and CPU time measured together
+//!
+//! [`WallAndCpuTime`] measures the wall time and the CPU time of the process in each sample.
+//! Criterion analyzes and prints the wall times like [`InvertedThroughput`](crate::InvertedThroughput),
+//! and the utilization of each benchmark, the CPU time divided by the wall time, is stored
+//! in [`UTILIZATION_FILE`] next to criterion's `estimates.json`. [`results`](crate::results)
+//! loads it, and [`export`](crate::export) adds the CPU times per element or byte and
+//! the utilizations to the summaries and the tables:
+//!
+//! ```text
+//! | benchmark | throughput | lower | point | upper | cpu | utilization |
+//! |:--|--:|--:|--:|--:|--:|--:|
+//! | read/1k | 1000 elem | 68.049 ns/elem | 68.381 ns/elem | 68.849 ns/elem | 34.190 ns/elem | 50.0% |
+//! ```
+//!
+//! A low utilization tells the routine waits for I/O or locks, and a utilization over 100%
+//! tells it keeps other threads busy.
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::cpu_time::WallAndCpuTime;
+//!
+//! let c = Criterion::default().with_measurement(WallAndCpuTime::new());
+//! ```
+//!
+//! The CPU time is the one of all threads of the process, so other threads running while
+//! measuring are counted too. The utilization is the one of all iterations of the benchmark,
+//! including the warm-up, which criterion measures like the samples without telling them apart.
+//!
+//! With the `serde` feature, the utilizations are written when the measurement is dropped
+//! with the `Criterion` of the group, under `results::criterion_home`
+//! or `WallAndCpuTime::output_directory`, and not with `--output-format bencher`. Without it,
+//! only the wall times are reported.
+
+use crate::clock::process_cpu_time;
+#[cfg(feature = "serde")]
+use crate::results::{criterion_home, load_records, write_utilization, Estimate, LATEST_BASELINE};
+use crate::{inversion_options, Inversion, TimeFormatter};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub use crate::results::UTILIZATION_FILE;
+
+/// The custom measurement of wall times and CPU times printing them per element or byte
+///
+/// # Panics
+///
+/// Measuring panics if the CPU time of the process is not supported on this platform
+/// (Unix and Windows are supported).
+pub struct WallAndCpuTime {
+    inversion: Inversion,
+    report: Mutex<CpuReport>,
+    reported: Mutex<Vec<Reported>>,
+    #[cfg(feature = "serde")]
+    output_directory: Option<PathBuf>,
+}
+
+/// The wall time and the CPU time of a sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WallCpu {
+    /// The elapsed wall time
+    pub wall: Duration,
+    /// The CPU time consumed by the process
+    pub cpu: Duration,
+}
+
+impl WallCpu {
+    /// Returns the CPU time divided by the wall time, or `None` if the wall time is zero
+    pub fn utilization(&self) -> Option<f64> {
+        if self.wall.is_zero() {
+            None
+        } else {
+            Some(self.cpu.as_secs_f64() / self.wall.as_secs_f64())
+        }
+    }
+}
+
+// the state of the benchmark being measured or reported
+#[derive(Default)]
+struct CpuReport {
+    // the sum of the samples of the benchmark, and of the iterations of the warm-up
+    total: WallCpu,
+    // whether criterion began to report the benchmark
+    reporting: bool,
+    // the bounds of the `time` line, the first values criterion formats
+    time_line: Vec<f64>,
+}
+
+// the utilization of a reported benchmark, not written yet
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Reported {
+    // the lower bound, the point estimate and the upper bound of the wall time
+    time_line: [f64; 3],
+    utilization: f64,
+}
+
+#[cfg(feature = "serde")]
+impl Reported {
+    // whether `estimate` is the estimate of the `time` line, up to the precision of JSON
+    fn is_of(&self, estimate: &Estimate) -> bool {
+        let nearly_eq = |a: f64, b: f64| (a - b).abs() <= a.abs() * 1e-12;
+        nearly_eq(self.time_line[0], estimate.lower_bound)
+            && nearly_eq(self.time_line[1], estimate.point_estimate)
+            && nearly_eq(self.time_line[2], estimate.upper_bound)
+    }
+}
+
+impl WallAndCpuTime {
+    /// Returns a new `WallAndCpuTime`
+    pub fn new() -> Self {
+        WallAndCpuTime {
+            inversion: Inversion::new(),
+            report: Mutex::new(CpuReport::default()),
+            reported: Mutex::new(Vec::new()),
+            #[cfg(feature = "serde")]
+            output_directory: None,
+        }
+    }
+
+    /// Sets the directory to write the utilizations to, the one passed to
+    /// `Criterion::output_directory`
+    ///
+    /// The default is [`criterion_home`].
+    #[cfg(feature = "serde")]
+    pub fn output_directory(mut self, path: &Path) -> Self {
+        self.output_directory = Some(path.to_path_buf());
+        self
+    }
+
+    inversion_options!();
+
+    // records the value criterion formats, to remember the utilization with the `time` line
+    fn report_value(&self, value: f64) {
+        let mut report = self.report.lock().unwrap();
+        report.reporting = true;
+        if report.time_line.len() == 3 {
+            return;
+        }
+        report.time_line.push(value);
+        if let (&[lower, point, upper], Some(utilization)) =
+            (report.time_line.as_slice(), report.total.utilization())
+        {
+            self.reported.lock().unwrap().push(Reported {
+                time_line: [lower, point, upper],
+                utilization,
+            });
+        }
+    }
+
+    // writes the utilizations of the reported benchmarks to the directories of their results
+    // under `criterion_home`, returning the number of written files
+    #[cfg(feature = "serde")]
+    fn write_utilizations(&self, criterion_home: &Path) -> io::Result<usize> {
+        let reported = std::mem::take(&mut *self.reported.lock().unwrap());
+        if reported.is_empty() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        for record in load_records(criterion_home, LATEST_BASELINE)? {
+            if let Some(reported) = reported
+                .iter()
+                .rev()
+                .find(|reported| reported.is_of(record.estimates.typical()))
+            {
+                let dir = criterion_home
+                    .join(&record.directory_name)
+                    .join(LATEST_BASELINE);
+                write_utilization(&dir, reported.utilization)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+impl Default for WallAndCpuTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Drop for WallAndCpuTime {
+    fn drop(&mut self) {
+        if self.reported.lock().unwrap().is_empty() {
+            return;
+        }
+        let output_directory = self.output_directory.clone().unwrap_or_else(criterion_home);
+        if let Err(e) = self.write_utilizations(&output_directory) {
+            eprintln!("failed to write CPU utilizations: {}", e);
+        }
+    }
+}
+
+fn cpu_now() -> u64 {
+    process_cpu_time().expect("the CPU time of the process is not supported on this platform")
+}
+
+impl Measurement for WallAndCpuTime {
+    type Intermediate = (Instant, u64);
+    type Value = WallCpu;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        {
+            let mut report = self.report.lock().unwrap();
+            if report.reporting {
+                // forget the previous benchmark
+                *report = CpuReport::default();
+            }
+        }
+        (Instant::now(), cpu_now())
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        let value = WallCpu {
+            cpu: Duration::from_nanos(cpu_now().saturating_sub(i.1)),
+            wall: i.0.elapsed(),
+        };
+        let mut report = self.report.lock().unwrap();
+        report.total = self.add(&report.total, &value);
+        value
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        WallCpu {
+            wall: v1.wall + v2.wall,
+            cpu: v1.cpu + v2.cpu,
+        }
+    }
+    fn zero(&self) -> Self::Value {
+        WallCpu::default()
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        val.wall.as_nanos() as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for WallAndCpuTime {
+    fn format_value(&self, value: f64) -> String {
+        self.report_value(value);
+        self.inversion.format_value(&TimeFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&TimeFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&TimeFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&TimeFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&TimeFormatter, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utilization() {
+        let value = WallCpu {
+            wall: Duration::from_millis(200),
+            cpu: Duration::from_millis(100),
+        };
+        assert_eq!(value.utilization(), Some(0.5));
+        assert_eq!(WallCpu::default().utilization(), None);
+    }
+
+    #[test]
+    fn test_report_value() {
+        let measure = WallAndCpuTime::new();
+        measure.report.lock().unwrap().total = WallCpu {
+            wall: Duration::from_millis(200),
+            cpu: Duration::from_millis(100),
+        };
+        for value in [1900.0, 2000.0, 2100.0, 1800.0, 2200.0] {
+            measure.report_value(value);
+        }
+        // the values formatted after the `time` line are not recorded
+        assert_eq!(
+            *measure.reported.lock().unwrap(),
+            [Reported {
+                time_line: [1900.0, 2000.0, 2100.0],
+                utilization: 0.5,
+            }]
+        );
+        #[cfg(feature = "serde")]
+        assert!(measure.reported.lock().unwrap()[0].is_of(&Estimate {
+            lower_bound: 1900.0,
+            point_estimate: 2000.0,
+            upper_bound: 2100.0,
+            standard_error: 1.0,
+        }));
+
+        // the next benchmark
+        measure.start();
+        assert_eq!(measure.report.lock().unwrap().total, WallCpu::default());
+        measure.reported.lock().unwrap().clear();
+    }
+
+    #[cfg(all(any(unix, windows), feature = "serde"))]
+    #[test]
+    fn test_write_utilizations() {
+        use crate::export;
+        use criterion::Criterion;
+        use std::env;
+        use std::fs;
+        use std::hint::black_box;
+
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-cpu-time");
+        let _ = fs::remove_dir_all(&home);
+        let measure = WallAndCpuTime::new().output_directory(&home);
+        let mut c = Criterion::default()
+            .with_measurement(measure)
+            .output_directory(&home)
+            .sample_size(10)
+            .warm_up_time(Duration::from_millis(1))
+            .measurement_time(Duration::from_millis(10))
+            .without_plots();
+        let mut g = c.benchmark_group("cpu");
+        g.throughput(Throughput::Elements(100));
+        g.bench_function("sum", |b| {
+            b.iter(|| black_box((0..100u64).map(black_box).sum::<u64>()))
+        });
+        g.finish();
+        drop(c);
+
+        let records = load_records(&home, LATEST_BASELINE).unwrap();
+        assert_eq!(records.len(), 1);
+        let utilization = records[0].utilization.unwrap();
+        assert!(utilization > 0.0, "{}", utilization);
+        let summary = &export::summaries(&records)[0];
+        assert_eq!(summary.utilization, Some(utilization));
+        assert_eq!(summary.cpu_time, Some(summary.point_estimate * utilization));
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+//! Interoperation with [criterion-cycles-per-byte](https://crates.io/crates/criterion-cycles-per-byte)
+//!
+//! [`InvertedCyclesPerByte`] wraps `CyclesPerByte` as the inner measurement and prints
+//! throughputs like `cycles/byte` and `cycles/elem` through the same pipeline as
+//! [`InvertedThroughput`](crate::InvertedThroughput), with the same options.
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::cycles::InvertedCyclesPerByte;
+//! use criterion_inverted_throughput::Precision;
+//!
+//! let c = Criterion::default()
+//!     .with_measurement(InvertedCyclesPerByte::new().precision(Precision::Decimals(2)));
+//! ```
+
+use crate::{inversion_options, Inversion};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+use criterion_cycles_per_byte::CyclesPerByte;
+
+/// The custom measurement of CPU cycles printing them per element or byte
+pub struct InvertedCyclesPerByte {
+    inner: CyclesPerByte,
+    inversion: Inversion,
+}
+
+impl InvertedCyclesPerByte {
+    /// Returns a new `InvertedCyclesPerByte`
+    pub fn new() -> Self {
+        InvertedCyclesPerByte {
+            inner: CyclesPerByte,
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for InvertedCyclesPerByte {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement for InvertedCyclesPerByte {
+    type Intermediate = <CyclesPerByte as Measurement>::Intermediate;
+    type Value = <CyclesPerByte as Measurement>::Value;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        self.inner.start()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        self.inner.end(i)
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        self.inner.add(v1, v2)
+    }
+    fn zero(&self) -> Self::Value {
+        self.inner.zero()
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        self.inner.to_f64(val)
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for InvertedCyclesPerByte {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(self.inner.formatter(), value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(self.inner.formatter(), throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(self.inner.formatter(), typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(self.inner.formatter(), typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_for_machines(self.inner.formatter(), values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MachineOutput, Precision};
+    use test_case::test_case;
+
+    #[test_case(Throughput::Elements(10), "12.346 cycles/elem" ; "test elements")]
+    #[test_case(Throughput::Bytes(10), "12.346 cycles/byte" ; "test bytes")]
+    fn test_format_throughput(throughput: Throughput, expected: &str) {
+        let measure = InvertedCyclesPerByte::new();
+        assert_eq!(measure.format_throughput(&throughput, 123.456), expected);
+    }
+
+    #[test]
+    fn test_options() {
+        let measure = InvertedCyclesPerByte::new()
+            .machine_output(MachineOutput::PerUnit)
+            .throughput_multiplier(2.0)
+            .precision(Precision::Decimals(1));
+        assert_eq!(
+            measure.format_throughput(&Throughput::Bytes(10), 123.456),
+            "   6.2 cycles/byte"
+        );
+        let mut values = [100.0];
+        assert_eq!(measure.scale_for_machines(&mut values), "cycles/byte");
+        assert_eq!(values, [5.0]);
+    }
+}
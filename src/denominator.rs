@@ -0,0 +1,403 @@
+//! Labels of the elements of throughputs
+//!
+//! A `Criterion` shares one measurement among its groups, so the unit of elements
+//! cannot be set per measurement. Label the elements of the benchmarks run in the current
+//! thread while a guard lives, e.g. `ns/row` instead of `ns/elem`:
+//!
+//! ```no_run
+//! use criterion::{Criterion, Throughput};
+//! use criterion_inverted_throughput::{denominator, InvertedThroughput};
+//!
+//! # let input = "a,b\nc,d\n";
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("parse");
+//! let _label = denominator::label_elements("row");
+//! g.throughput(Throughput::Elements(2));
+//! g.bench_function("csv", |b| b.iter(|| input.lines().count()));
+//! g.finish();
+//! ```
+//!
+//! Bytes are always labeled `byte`.
+//!
+//! For domain-specific units, implement [`DenomFormatter`] and set it with
+//! [`InvertedThroughput::denom_formatter`](crate::InvertedThroughput::denom_formatter):
+//!
+//! ```
+//! use criterion::Throughput;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! // an element is a 8x8x8 block of voxels
+//! let measurement = InvertedThroughput::new().denom_formatter(
+//!     |time_unit: &str, _: &Throughput| (format!("{}/voxel", time_unit), 1.0 / 512.0),
+//! );
+//! ```
+//!
+//! [`InvertedSpec`] divides by the product of several labeled factors, like `ns/(row·col)`
+//! for matrix kernels. Set it with [`InvertedThroughput::per`](crate::InvertedThroughput::per)
+//! for all benchmarks, or label the benchmarks of a group sweeping sizes:
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::denominator::InvertedSpec;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("transpose");
+//! for (m, n) in [(64, 64), (256, 1024)] {
+//!     let spec = InvertedSpec::new([("row", m), ("col", n)]);
+//!     // `Throughput::Elements(m * n)` printed like `ns/(row·col)`
+//!     g.throughput(spec.throughput());
+//!     let _label = spec.label_elements();
+//!     g.bench_function(format!("{}x{}", m, n), |b| b.iter(|| m * n));
+//! }
+//! g.finish();
+//! ```
+//!
+//! [`FixedUnit`] prints times in a fixed time unit like `ms/frame`, as the presets
+//! [`InvertedThroughput::per_frame`](crate::InvertedThroughput::per_frame),
+//! [`per_pixel`](crate::InvertedThroughput::per_pixel) and
+//! [`per_sample`](crate::InvertedThroughput::per_sample) do.
+
+use criterion::Throughput;
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+thread_local! {
+    static ELEMENTS_LABEL: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// The guard labeling elements until it is dropped
+///
+/// The previous label is restored on drop, so guards can be nested.
+#[must_use = "the label is reset when the guard is dropped"]
+pub struct LabelGuard {
+    previous: Option<&'static str>,
+    // the label is thread-local
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for LabelGuard {
+    fn drop(&mut self) {
+        ELEMENTS_LABEL.with(|label| label.set(self.previous));
+    }
+}
+
+/// Labels the elements of throughputs printed in the current thread as `label`,
+/// like `ns/row` with `row`, until the returned guard is dropped
+pub fn label_elements(label: &str) -> LabelGuard {
+    let label = intern(label.to_string());
+    LabelGuard {
+        previous: ELEMENTS_LABEL.with(|current| current.replace(Some(label))),
+        _not_send: PhantomData,
+    }
+}
+
+/// The formatter of the denominators of inverted throughputs
+///
+/// It is implemented for closures with the same signature as [`DenomFormatter::format`].
+pub trait DenomFormatter: Send + Sync {
+    /// Returns the unit of values in `time_unit` (like `ns`) per `throughput`, like `ns/voxel`,
+    /// and the factor to multiply the values per element or byte by
+    fn format(&self, time_unit: &str, throughput: &Throughput) -> (String, f64);
+
+    /// Returns the amount to divide times per iteration by instead of the amount of
+    /// `throughput`, if any
+    fn amount(&self, throughput: &Throughput) -> Option<f64> {
+        let _ = throughput;
+        None
+    }
+}
+
+impl<F> DenomFormatter for F
+where
+    F: Fn(&str, &Throughput) -> (String, f64) + Send + Sync,
+{
+    fn format(&self, time_unit: &str, throughput: &Throughput) -> (String, f64) {
+        self(time_unit, throughput)
+    }
+}
+
+/// The [`DenomFormatter`] printing times per element in a fixed time unit with a label,
+/// like `ms/frame`
+///
+/// Times per byte or bit are printed as usual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedUnit {
+    time_unit: &'static str,
+    label: &'static str,
+}
+
+impl FixedUnit {
+    /// Returns a `FixedUnit` printing times in `time_unit` per `label`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `time_unit` is not one of `ps`, `ns`, `µs`, `ms` and `s`.
+    pub fn new(time_unit: &str, label: &str) -> Self {
+        let time_unit = match time_unit {
+            "ps" => "ps",
+            "ns" => "ns",
+            "µs" => "µs",
+            "ms" => "ms",
+            "s" => "s",
+            _ => panic!("unknown time unit `{}`", time_unit),
+        };
+        FixedUnit {
+            time_unit,
+            label: intern(label.to_string()),
+        }
+    }
+}
+
+// returns the nanoseconds of a time unit criterion scales times to
+fn unit_nanos(time_unit: &str) -> Option<f64> {
+    match time_unit {
+        "ps" => Some(1e-3),
+        "ns" => Some(1.0),
+        "µs" => Some(1e3),
+        "ms" => Some(1e6),
+        "s" => Some(1e9),
+        _ => None,
+    }
+}
+
+impl DenomFormatter for FixedUnit {
+    fn format(&self, time_unit: &str, throughput: &Throughput) -> (String, f64) {
+        let (_, unit) = crate::units::throughput_amount(throughput);
+        if unit != "elem" {
+            return (format!("{}/{}", time_unit, unit), 1.0);
+        }
+        match (unit_nanos(time_unit), unit_nanos(self.time_unit)) {
+            (Some(from), Some(to)) => (format!("{}/{}", self.time_unit, self.label), from / to),
+            _ => (format!("{}/{}", time_unit, self.label), 1.0),
+        }
+    }
+}
+
+/// The denominator of the product of labeled factors, like `ns/(row·col)` for `m` rows and
+/// `n` columns
+///
+/// As a [`DenomFormatter`], it divides times per iteration by the product of the factors
+/// instead of the amount of the throughput.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvertedSpec {
+    factors: Vec<(&'static str, u64)>,
+}
+
+impl InvertedSpec {
+    /// Returns a spec of `factors` of labels and counts, like `[("row", m), ("col", n)]`
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no factors, a count is zero, or the product overflows `u64`.
+    pub fn new<'a>(factors: impl IntoIterator<Item = (&'a str, u64)>) -> Self {
+        let factors: Vec<(&'static str, u64)> = factors
+            .into_iter()
+            .map(|(label, count)| (intern(label.to_string()), count))
+            .collect();
+        assert!(!factors.is_empty(), "a spec requires at least one factor");
+        let spec = InvertedSpec { factors };
+        assert!(
+            spec.factors.iter().all(|&(_, count)| count > 0),
+            "the factors of {} must be positive",
+            spec.label()
+        );
+        assert!(
+            spec.checked_amount().is_some(),
+            "the product of the factors of {} overflows",
+            spec.label()
+        );
+        spec
+    }
+
+    /// Returns the labels and the counts of the factors
+    pub fn factors(&self) -> &[(&'static str, u64)] {
+        &self.factors
+    }
+
+    /// Returns the product of the counts of the factors
+    pub fn amount(&self) -> u64 {
+        self.checked_amount().unwrap()
+    }
+
+    fn checked_amount(&self) -> Option<u64> {
+        self.factors
+            .iter()
+            .try_fold(1u64, |product, &(_, count)| product.checked_mul(count))
+    }
+
+    /// Returns the label of the denominator, like `(row·col)`, or `row` for a single factor
+    pub fn label(&self) -> &'static str {
+        match &self.factors[..] {
+            [(label, _)] => label,
+            factors => {
+                let labels: Vec<&str> = factors.iter().map(|&(label, _)| label).collect();
+                intern(format!("({})", labels.join("·")))
+            }
+        }
+    }
+
+    /// Returns `Throughput::Elements` of the product of the factors
+    pub fn throughput(&self) -> Throughput {
+        Throughput::Elements(self.amount())
+    }
+
+    /// Labels the elements of throughputs printed in the current thread as the spec,
+    /// like [`label_elements`]
+    pub fn label_elements(&self) -> LabelGuard {
+        label_elements(self.label())
+    }
+}
+
+impl DenomFormatter for InvertedSpec {
+    fn format(&self, time_unit: &str, _: &Throughput) -> (String, f64) {
+        (format!("{}/{}", time_unit, self.label()), 1.0)
+    }
+
+    fn amount(&self, _: &Throughput) -> Option<f64> {
+        Some(self.amount() as f64)
+    }
+}
+
+/// Returns the label of elements in the current thread, if any
+pub(crate) fn elements_label() -> Option<&'static str> {
+    ELEMENTS_LABEL.with(Cell::get)
+}
+
+/// Returns a static string equal to `s`
+///
+/// Units are returned as `&'static str` to criterion, so that custom units are leaked once
+/// per distinct string.
+pub(crate) fn intern(s: String) -> &'static str {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut interned = INTERNED.lock().unwrap();
+    match interned.get(s.as_str()) {
+        Some(interned) => interned,
+        None => {
+            let s = Box::leak(s.into_boxed_str());
+            interned.insert(s);
+            s
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InvertedThroughput;
+    use criterion::measurement::ValueFormatter;
+    use test_case::test_case;
+
+    #[test]
+    fn test_label_elements() {
+        let measure = InvertedThroughput::new().ascii_units(true);
+        let unit = |throughput: &Throughput| measure.scale_throughputs(2e4, throughput, &mut [2e4]);
+        assert_eq!(unit(&Throughput::Elements(10)), "us/elem");
+        {
+            let _label = label_elements("row");
+            assert_eq!(unit(&Throughput::Elements(10)), "us/row");
+            assert_eq!(unit(&Throughput::Bytes(10)), "us/byte");
+            {
+                let _label = label_elements("packet");
+                assert_eq!(unit(&Throughput::Elements(10)), "us/packet");
+            }
+            assert_eq!(unit(&Throughput::Elements(10)), "us/row");
+        }
+        assert_eq!(unit(&Throughput::Elements(10)), "us/elem");
+    }
+
+    struct Transactions;
+
+    impl DenomFormatter for Transactions {
+        fn format(&self, time_unit: &str, throughput: &Throughput) -> (String, f64) {
+            match throughput {
+                // 4 transactions per element
+                Throughput::Elements(_) => (format!("{}/tx", time_unit), 0.25),
+                _ => (format!("{}/byte", time_unit), 1.0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_denom_formatter() {
+        let measure = InvertedThroughput::new().denom_formatter(Transactions);
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 2e4),
+            "0.5000 µs/tx"
+        );
+        assert_eq!(
+            measure.format_throughput(&Throughput::Bytes(10), 2e4),
+            "2.0000 µs/byte"
+        );
+
+        let measure = InvertedThroughput::new().ascii_units(true).denom_formatter(
+            |time_unit: &str, _: &Throughput| (format!("{}/voxel", time_unit), 1.0),
+        );
+        let _label = label_elements("row");
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 2e4),
+            "2.0000 us/voxel"
+        );
+    }
+
+    #[test_case(InvertedThroughput::per_frame(), Throughput::Elements(2), 33.2e6, "16.600 ms/frame" ; "frames")]
+    #[test_case(InvertedThroughput::per_frame(), Throughput::Elements(1), 2e3, "0.0020 ms/frame" ; "fast frames")]
+    #[test_case(InvertedThroughput::per_pixel(), Throughput::Elements(1920 * 1080), 16.6e6, "8.0054 ns/pixel" ; "pixels")]
+    #[test_case(InvertedThroughput::per_sample(), Throughput::Elements(48_000), 1e6, "20.833 ns/sample" ; "samples")]
+    #[test_case(InvertedThroughput::per_sample(), Throughput::Bytes(10), 2e4, "2.0000 µs/byte" ; "bytes")]
+    fn test_presets(
+        measure: InvertedThroughput,
+        throughput: Throughput,
+        value: f64,
+        expected: &str,
+    ) {
+        assert_eq!(measure.format_throughput(&throughput, value), expected);
+    }
+
+    #[test]
+    fn test_inverted_spec() {
+        let spec = InvertedSpec::new([("row", 1080), ("col", 1920)]);
+        assert_eq!(spec.label(), "(row·col)");
+        assert_eq!(spec.throughput(), Throughput::Elements(1080 * 1920));
+        assert_eq!(InvertedSpec::new([("row", 3)]).label(), "row");
+
+        // the throughput of the group is replaced by the product
+        let measure = InvertedThroughput::new().per(spec.clone());
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(1), 16.6e6),
+            "8.0054 ns/(row·col)"
+        );
+
+        let measure = InvertedThroughput::new();
+        let _label = spec.label_elements();
+        assert_eq!(
+            measure.format_throughput(&spec.throughput(), 16.6e6),
+            "8.0054 ns/(row·col)"
+        );
+    }
+
+    #[test_case(&[] ; "empty")]
+    #[test_case(&[("row", 0)] ; "zero")]
+    #[test_case(&[("row", u64::MAX), ("col", 2)] ; "overflow")]
+    #[should_panic]
+    fn test_invalid_spec(factors: &[(&str, u64)]) {
+        InvertedSpec::new(factors.iter().copied());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unknown_fixed_unit() {
+        FixedUnit::new("min", "frame");
+    }
+
+    #[test]
+    fn test_intern() {
+        let a = intern("ns/row".to_string());
+        let b = intern("ns/row".to_string());
+        assert_eq!(a, "ns/row");
+        assert!(std::ptr::eq(a, b));
+    }
+}
@@ -0,0 +1,330 @@
+//! Pinning benchmarks to a core and recording the environment
+//!
+//! Times per element of two runs are comparable only if they ran on the same kind of core
+//! at the same frequency. [`Setup`] pins the benchmark thread to a core, optionally sets
+//! its scheduling priority, and saves the [`Environment`] (the CPU model, the frequency
+//! governor and the frequency) to [`ENVIRONMENT_FILE`] in the directory criterion stores
+//! results in. Apply it at the start of the benchmarks, e.g. in the `main` of a benchmark
+//! target (criterion's `criterion_main!` is replaced):
+//!
+//! ```no_run
+//! use criterion::{criterion_group, Criterion};
+//! use criterion_inverted_throughput::environment::Setup;
+//!
+//! # fn bench_parse(_: &mut Criterion) {}
+//! criterion_group!(benches, bench_parse);
+//!
+//! # #[cfg(feature = "serde")]
+//! fn main() {
+//!     let environment = Setup::new().core(2).nice(-10).apply().unwrap();
+//!     eprintln!("{}", environment);
+//!     benches();
+//!     Criterion::default().configure_from_args().final_summary();
+//! }
+//! # #[cfg(not(feature = "serde"))]
+//! # fn main() {}
+//! ```
+//!
+//! `criterion-invert export` adds the saved environment to the Markdown table and to
+//! the Prometheus metrics. Pinning is supported on Linux, and the priority on Unix.
+//! Criterion runs benchmarks in the thread calling them, so pin that thread.
+
+use crate::clock::cpu_frequency_ghz;
+#[cfg(feature = "serde")]
+use crate::results::criterion_home;
+use std::fmt;
+#[cfg(any(feature = "serde", target_os = "linux"))]
+use std::fs;
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+/// The file storing the environment in the directory criterion stores results in
+pub const ENVIRONMENT_FILE: &str = "inverted_environment.json";
+
+/// The environment benchmarks run in
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Environment {
+    /// The model name of the CPU, if detected
+    pub cpu_model: Option<String>,
+    /// The frequency governor of the core like `performance`, if detected
+    pub governor: Option<String>,
+    /// The frequency of the CPU in GHz, if detected (see [`cpu_frequency_ghz`])
+    pub frequency_ghz: Option<f64>,
+    /// The core the benchmark thread is pinned to, if any
+    pub core: Option<usize>,
+    /// The nice value of the benchmark thread, if set
+    pub nice: Option<i32>,
+}
+
+impl Environment {
+    /// Returns the environment of the current thread pinned to `core` with `nice`, if any
+    pub fn capture(core: Option<usize>, nice: Option<i32>) -> Self {
+        Environment {
+            cpu_model: cpu_model(),
+            governor: governor(core.unwrap_or(0)),
+            frequency_ghz: cpu_frequency_ghz(),
+            core,
+            nice,
+        }
+    }
+
+    /// Saves the environment to [`ENVIRONMENT_FILE`] in `criterion_home`
+    #[cfg(feature = "serde")]
+    pub fn save(&self, criterion_home: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::create_dir_all(criterion_home)?;
+        fs::write(criterion_home.join(ENVIRONMENT_FILE), json)
+    }
+
+    /// Loads the environment saved in `criterion_home`
+    #[cfg(feature = "serde")]
+    pub fn load(criterion_home: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(criterion_home.join(ENVIRONMENT_FILE))?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        parts.push(
+            self.cpu_model
+                .as_deref()
+                .unwrap_or("unknown CPU")
+                .to_string(),
+        );
+        if let Some(ghz) = self.frequency_ghz {
+            parts.push(format!("{:.2} GHz", ghz));
+        }
+        if let Some(governor) = &self.governor {
+            parts.push(format!("governor {}", governor));
+        }
+        if let Some(core) = self.core {
+            parts.push(format!("pinned to core {}", core));
+        }
+        if let Some(nice) = self.nice {
+            parts.push(format!("nice {}", nice));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// The setup of the benchmark thread
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Setup {
+    core: Option<usize>,
+    nice: Option<i32>,
+}
+
+impl Setup {
+    /// Returns a setup changing nothing but saving the environment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pins the thread to `core`
+    pub fn core(mut self, core: usize) -> Self {
+        self.core = Some(core);
+        self
+    }
+
+    /// Sets the nice value of the thread to `nice`, from -20 (the highest priority) to 19
+    ///
+    /// Values below the current one usually require privileges.
+    pub fn nice(mut self, nice: i32) -> Self {
+        self.nice = Some(nice);
+        self
+    }
+
+    /// Applies the setup to the current thread and saves the environment in the directory
+    /// criterion stores results in
+    #[cfg(feature = "serde")]
+    pub fn apply(&self) -> io::Result<Environment> {
+        let environment = self.apply_only()?;
+        environment.save(&criterion_home())?;
+        Ok(environment)
+    }
+
+    /// Applies the setup to the current thread without saving the environment
+    pub fn apply_only(&self) -> io::Result<Environment> {
+        if let Some(core) = self.core {
+            pin_to_core(core)?;
+        }
+        if let Some(nice) = self.nice {
+            set_nice(nice)?;
+        }
+        Ok(Environment::capture(self.core, self.nice))
+    }
+}
+
+/// Pins the current thread to `core`
+///
+/// It fails on platforms other than Linux.
+pub fn pin_to_core(core: usize) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: `cpu_set_t` is a plain C struct, valid when zeroed
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        if core >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("core {} is out of range", core),
+            ));
+        }
+        // SAFETY: `core` is in the range of `set`
+        unsafe { libc::CPU_SET(core, &mut set) };
+        // SAFETY: `set` is a valid pointer to a `cpu_set_t` of the given size
+        if unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = core;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "pinning threads is not supported on this platform",
+        ))
+    }
+}
+
+/// Sets the nice value of the current thread on Linux, or of the process on other Unix
+///
+/// It fails on platforms other than Unix.
+pub fn set_nice(nice: i32) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        // SAFETY: `setpriority` has no memory safety requirements
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = nice;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "setting priorities is not supported on this platform",
+        ))
+    }
+}
+
+fn cpu_model() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        cpuinfo_model(&fs::read_to_string("/proc/cpuinfo").ok()?)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut buffer = [0u8; 256];
+        let mut size = buffer.len();
+        // SAFETY: the name is NUL-terminated, and `buffer` is valid for `size` bytes
+        let result = unsafe {
+            libc::sysctlbyname(
+                b"machdep.cpu.brand_string\0".as_ptr() as *const libc::c_char,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if result != 0 {
+            return None;
+        }
+        let model = String::from_utf8_lossy(&buffer[..size]);
+        Some(model.trim_end_matches('\0').trim().to_string())
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+// returns the model name of the first CPU in `/proc/cpuinfo`
+#[cfg(target_os = "linux")]
+fn cpuinfo_model(cpuinfo: &str) -> Option<String> {
+    cpuinfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "model name")
+        .map(|(_, model)| model.trim().to_string())
+}
+
+fn governor(core: usize) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let path = format!(
+            "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor",
+            core
+        );
+        Some(fs::read_to_string(path).ok()?.trim().to_string())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = core;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use std::env;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_environment() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-environment");
+        let _ = fs::remove_dir_all(&home);
+        let environment = Environment {
+            cpu_model: Some("Example CPU".to_string()),
+            governor: Some("performance".to_string()),
+            frequency_ghz: Some(3.0),
+            core: Some(2),
+            nice: Some(-10),
+        };
+        environment.save(&home).unwrap();
+        assert_eq!(Environment::load(&home).unwrap(), environment);
+        assert_eq!(
+            environment.to_string(),
+            "Example CPU, 3.00 GHz, governor performance, pinned to core 2, nice -10"
+        );
+        assert_eq!(Environment::default().to_string(), "unknown CPU");
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pin_to_core() {
+        std::thread::spawn(|| {
+            // the core the thread runs on is allowed
+            // SAFETY: `sched_getcpu` has no memory safety requirements
+            let core = unsafe { libc::sched_getcpu() } as usize;
+            let environment = Setup::new().core(core).apply_only().unwrap();
+            assert_eq!(environment.core, Some(core));
+            // SAFETY: `sched_getcpu` has no memory safety requirements
+            assert_eq!(unsafe { libc::sched_getcpu() } as usize, core);
+            assert!(pin_to_core(1 << 20).is_err());
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cpuinfo_model() {
+        let cpuinfo = "processor\t: 0\nmodel name\t: Example CPU @ 3.00GHz\n";
+        assert_eq!(
+            cpuinfo_model(cpuinfo).as_deref(),
+            Some("Example CPU @ 3.00GHz")
+        );
+        assert_eq!(cpuinfo_model("processor\t: 0\n"), None);
+    }
+}
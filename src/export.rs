@@ -0,0 +1,1027 @@
+//! Exporting inverted throughputs of benchmark results as tables
+//!
+//! The tables have a row per benchmark like
+//! `benchmark id | throughput | lower | point | upper` and are suitable for pasting
+//! into PR descriptions. If any benchmark has a secondary throughput (`secondary`),
+//! the point estimates per its element or byte are added. Elements are labeled by
+//! [`denominator::label_elements`](crate::denominator::label_elements) in the current thread,
+//! like `ns/(row·col)` with an [`InvertedSpec`](crate::denominator::InvertedSpec).
+//! Numbers of Markdown and CSV tables are formatted by the
+//! [`NumberFormat`](crate::numbers::NumberFormat) applied in the current thread.
+//! [`ExportOptions`] add columns like the realtime multiples of media workloads and
+//! the approximate cycles.
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde")] {
+//! use criterion_inverted_throughput::export::{export, Format};
+//! use criterion_inverted_throughput::results::{criterion_home, load_records, LATEST_BASELINE};
+//!
+//! let records = load_records(&criterion_home(), LATEST_BASELINE).unwrap();
+//! let parse: Vec<_> = records.into_iter().filter(|r| r.group_id == "parse").collect();
+//! println!("{}", export(&parse, Format::Markdown));
+//! # }
+//! ```
+
+use crate::environment::Environment;
+use crate::numbers::NumberFormat;
+use crate::results::{BenchRecord, Dispersion};
+use crate::units::{format_cycles, unit_per};
+use crate::InvertedThroughput;
+use criterion::measurement::ValueFormatter;
+use criterion::Throughput;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The format of exported tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Markdown table with values scaled like the console output
+    Markdown,
+    /// CSV with values in nanoseconds per element or byte, with the standard deviations,
+    /// the median absolute deviations and the coefficients of variation of the samples
+    Csv,
+    /// The text format of `cargo bench` like `test foo ... bench: 68.85 ns/iter (+/- 2.1)`,
+    /// with the medians and the standard deviations in nanoseconds per element or byte
+    ///
+    /// The values are per element or byte but labeled `ns/iter`, since tools parsing the format
+    /// like [github-action-benchmark](https://github.com/benchmark-action/github-action-benchmark)
+    /// with `tool: cargo` only match `ns/iter`.
+    Bencher,
+    /// The Prometheus text format with gauges like `bench_ns_per_elem{bench="parse/1k"} 68.4`
+    /// of the point estimates in nanoseconds per element or byte
+    ///
+    /// Write it with [`write_textfile`] for the textfile collector of node_exporter.
+    Prometheus,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md" | "markdown" => Ok(Format::Markdown),
+            "csv" => Ok(Format::Csv),
+            "bencher" => Ok(Format::Bencher),
+            "prom" | "prometheus" => Ok(Format::Prometheus),
+            _ => Err(format!("unknown format `{}`", s)),
+        }
+    }
+}
+
+/// The summary of the inverted throughput of a benchmark
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    /// The full id of the benchmark like `group/function/value`
+    pub id: String,
+    /// The unit of the throughput, `elem` or `byte`
+    pub unit: &'static str,
+    /// The lower bound of the confidence interval in nanoseconds per element or byte
+    pub lower_bound: f64,
+    /// The point estimate in nanoseconds per element or byte
+    pub point_estimate: f64,
+    /// The upper bound of the confidence interval in nanoseconds per element or byte
+    pub upper_bound: f64,
+    /// The dispersion of the sample in nanoseconds per element or byte
+    pub dispersion: Dispersion,
+    /// The point estimate of the CPU time in nanoseconds per element or byte, measured by
+    /// [`WallAndCpuTime`](crate::cpu_time::WallAndCpuTime)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub cpu_time: Option<f64>,
+    /// The CPU time divided by the wall time, measured by
+    /// [`WallAndCpuTime`](crate::cpu_time::WallAndCpuTime)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub utilization: Option<f64>,
+}
+
+// `Summary` with its unit not static, to be deserialized
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawSummary {
+    id: String,
+    unit: String,
+    lower_bound: f64,
+    point_estimate: f64,
+    upper_bound: f64,
+    #[serde(default)]
+    dispersion: Dispersion,
+    cpu_time: Option<f64>,
+    utilization: Option<f64>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Summary {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = RawSummary::deserialize(deserializer)?;
+        Ok(Summary {
+            id: raw.id,
+            unit: crate::static_unit(&raw.unit).map_err(serde::de::Error::custom)?,
+            lower_bound: raw.lower_bound,
+            point_estimate: raw.point_estimate,
+            upper_bound: raw.upper_bound,
+            dispersion: raw.dispersion,
+            cpu_time: raw.cpu_time,
+            utilization: raw.utilization,
+        })
+    }
+}
+
+impl Summary {
+    /// Returns how many times faster than real time the benchmark processes media,
+    /// where an element or byte is `media` long, like `312.5` (xRT) for 3.2 ms per second
+    pub fn realtime_multiple(&self, media: Duration) -> f64 {
+        realtime_multiple(media, self.point_estimate)
+    }
+
+    /// Returns the approximate cycles per element or byte of the point estimate at `ghz` GHz
+    pub fn cycles(&self, ghz: f64) -> f64 {
+        self.point_estimate * ghz
+    }
+}
+
+/// The options adding columns and metadata to exported tables
+///
+/// ```
+/// use criterion_inverted_throughput::export::ExportOptions;
+/// use std::time::Duration;
+///
+/// // adds the realtime multiples of elements of 10 ms of audio
+/// let options = ExportOptions::new().per_media_seconds(Duration::from_millis(10));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportOptions {
+    environment: Option<Environment>,
+    media: Option<Duration>,
+    frequency_ghz: Option<f64>,
+}
+
+impl ExportOptions {
+    /// Returns the options adding nothing, as [`export`] exports
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the [`Environment`] the benchmarks were measured in, like
+    /// [`export_with_environment`]
+    ///
+    /// Its frequency, if detected, adds the cycles like [`ExportOptions::cycles_at`].
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Adds the realtime multiples (xRT), where an element or byte is `media` long,
+    /// like [`Summary::realtime_multiple`]
+    ///
+    /// Markdown and CSV get the column `realtime`, and Prometheus metrics the gauge
+    /// `bench_realtime_multiple`. The bencher format has no place for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `media` is zero.
+    pub fn per_media_seconds(mut self, media: Duration) -> Self {
+        assert!(!media.is_zero(), "the duration of media must not be zero");
+        self.media = Some(media);
+        self
+    }
+
+    /// Adds the approximate cycles per element or byte at `ghz` GHz, like [`Summary::cycles`],
+    /// instead of the frequency of the environment
+    ///
+    /// Markdown and CSV get the column `cycles`, and Prometheus metrics gauges like
+    /// `bench_cycles_per_elem`. The bencher format has no place for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ghz` is not a positive finite number.
+    pub fn cycles_at(mut self, ghz: f64) -> Self {
+        assert!(
+            ghz.is_finite() && ghz > 0.0,
+            "the frequency must be a positive finite number: {}",
+            ghz
+        );
+        self.frequency_ghz = Some(ghz);
+        self
+    }
+
+    // the frequency to add the cycles at, if any
+    fn frequency(&self) -> Option<f64> {
+        self.frequency_ghz.or_else(|| {
+            self.environment
+                .as_ref()
+                .and_then(|environment| environment.frequency_ghz)
+        })
+    }
+}
+
+/// Returns the summaries of the inverted throughputs of `records`
+///
+/// Benchmarks without throughput are skipped.
+pub fn summaries(records: &[BenchRecord]) -> Vec<Summary> {
+    records
+        .iter()
+        .filter_map(|record| {
+            let estimate = record.time_per_unit()?;
+            Some(Summary {
+                id: record.id.clone(),
+                unit: record.unit()?,
+                lower_bound: estimate.lower_bound,
+                point_estimate: estimate.point_estimate,
+                upper_bound: estimate.upper_bound,
+                dispersion: record.dispersion_per_unit()?,
+                cpu_time: record.cpu_time_per_unit(),
+                utilization: record.utilization,
+            })
+        })
+        .collect()
+}
+
+/// Returns the table of the inverted throughputs of `records` in `format`
+///
+/// Benchmarks without throughput are skipped.
+pub fn export(records: &[BenchRecord], format: Format) -> String {
+    export_with(records, format, &ExportOptions::new())
+}
+
+/// Returns the table of the inverted throughputs of `records` in `format` with the columns
+/// and the metadata of `options`
+///
+/// Benchmarks without throughput are skipped.
+pub fn export_with(records: &[BenchRecord], format: Format, options: &ExportOptions) -> String {
+    let mut out = match format {
+        Format::Markdown => markdown(records, options),
+        Format::Csv => csv(records, options),
+        Format::Bencher => bencher(records),
+        Format::Prometheus => prometheus(records, options),
+    };
+    if let Some(environment) = &options.environment {
+        match format {
+            Format::Markdown => {
+                let _ = writeln!(out, "\nEnvironment: {}", environment);
+            }
+            Format::Prometheus => out.push_str(&environment_info(environment)),
+            Format::Csv | Format::Bencher => {}
+        }
+    }
+    out
+}
+
+/// Returns the table of the inverted throughputs of `records` in `format` with
+/// the [`Environment`] they were measured in
+///
+/// Markdown tables end with a line of the environment, and Prometheus metrics have
+/// the gauge `bench_environment_info` labeled with it. CSV and the bencher format have
+/// no place for metadata. If the frequency was detected, the cycles are added
+/// like [`ExportOptions::cycles_at`].
+pub fn export_with_environment(
+    records: &[BenchRecord],
+    format: Format,
+    environment: &Environment,
+) -> String {
+    export_with(
+        records,
+        format,
+        &ExportOptions::new().environment(environment.clone()),
+    )
+}
+
+/// Writes the inverted throughputs of `records` in [`Format::Prometheus`] to `path`
+///
+/// The file is written next to `path` and renamed, so that a collector never reads
+/// a partial file.
+pub fn write_textfile(path: &Path, records: &[BenchRecord]) -> io::Result<()> {
+    write_textfile_with(path, records, &ExportOptions::new())
+}
+
+/// Writes the inverted throughputs of `records` in [`Format::Prometheus`] with the gauges
+/// and the metadata of `options` to `path`, like [`write_textfile`]
+pub fn write_textfile_with(
+    path: &Path,
+    records: &[BenchRecord],
+    options: &ExportOptions,
+) -> io::Result<()> {
+    write_atomically(path, &export_with(records, Format::Prometheus, options))
+}
+
+/// Writes the inverted throughputs of `records` in [`Format::Prometheus`] with
+/// the [`Environment`] they were measured in to `path`, like [`write_textfile`]
+pub fn write_textfile_with_environment(
+    path: &Path,
+    records: &[BenchRecord],
+    environment: &Environment,
+) -> io::Result<()> {
+    write_textfile_with(
+        path,
+        records,
+        &ExportOptions::new().environment(environment.clone()),
+    )
+}
+
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+/// Returns the amount of `throughput` with its unit, like `10 elem`
+pub fn throughput_label(throughput: &Throughput) -> String {
+    let (amount, unit) = crate::units::throughput_amount(throughput);
+    format!("{} {}", amount, unit)
+}
+
+fn markdown(records: &[BenchRecord], options: &ExportOptions) -> String {
+    let formatter = InvertedThroughput::new();
+    let numbers = NumberFormat::current();
+    let cell =
+        |value: f64, unit: &str| numbers.pad(&format!("{} {}", numbers.format_short(value), unit));
+    let amount = |throughput: &Throughput| {
+        let (amount, unit) = crate::units::throughput_amount(throughput);
+        format!("{} {}", numbers.format_amount(amount), unit)
+    };
+    let secondary = has_secondary(records);
+    let cpu = has_utilization(records);
+    let mut out = String::from("| benchmark | throughput | lower | point | upper |");
+    if secondary {
+        out.push_str(" secondary throughput | secondary point |");
+    }
+    if cpu {
+        out.push_str(" cpu | utilization |");
+    }
+    if options.media.is_some() {
+        out.push_str(" realtime |");
+    }
+    if options.frequency().is_some() {
+        out.push_str(" cycles |");
+    }
+    out.push_str("\n|:--|--:|--:|--:|--:|");
+    if secondary {
+        out.push_str("--:|--:|");
+    }
+    if cpu {
+        out.push_str("--:|--:|");
+    }
+    if options.media.is_some() {
+        out.push_str("--:|");
+    }
+    if options.frequency().is_some() {
+        out.push_str("--:|");
+    }
+    out.push('\n');
+    for record in records {
+        let Some(throughput) = &record.throughput else {
+            continue;
+        };
+        let estimate = record.estimates.typical();
+        let mut values = [
+            estimate.lower_bound,
+            estimate.point_estimate,
+            estimate.upper_bound,
+        ];
+        // scale all values with the point estimate to show them in the same unit
+        let unit = formatter.scale_throughputs(estimate.point_estimate, throughput, &mut values);
+        let _ = write!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            record.id,
+            amount(throughput),
+            cell(values[0], unit),
+            cell(values[1], unit),
+            cell(values[2], unit),
+        );
+        if secondary {
+            match &record.secondary_throughput {
+                Some(secondary_throughput) => {
+                    let mut values = [estimate.point_estimate];
+                    let unit = formatter.scale_throughputs(
+                        estimate.point_estimate,
+                        secondary_throughput,
+                        &mut values,
+                    );
+                    let _ = write!(
+                        out,
+                        " {} | {} |",
+                        amount(secondary_throughput),
+                        cell(values[0], unit),
+                    );
+                }
+                None => out.push_str(" - | - |"),
+            }
+        }
+        if cpu {
+            match record.utilization {
+                Some(utilization) => {
+                    let mut values = [estimate.point_estimate * utilization];
+                    let unit = formatter.scale_throughputs(values[0], throughput, &mut values);
+                    let _ = write!(
+                        out,
+                        " {} | {} |",
+                        cell(values[0], unit),
+                        numbers.pad(&numbers.localize(&format!("{:.1}%", utilization * 100.0))),
+                    );
+                }
+                None => out.push_str(" - | - |"),
+            }
+        }
+        if let Some(media) = options.media {
+            match record.time_per_unit() {
+                Some(estimate) => {
+                    let multiple = realtime_multiple(media, estimate.point_estimate);
+                    let _ = write!(
+                        out,
+                        " {} |",
+                        numbers.pad(&numbers.localize(&format!("{:.1}x", multiple)))
+                    );
+                }
+                None => out.push_str(" - |"),
+            }
+        }
+        if let Some(ghz) = options.frequency() {
+            match record.time_per_unit() {
+                Some(estimate) => {
+                    let cycles = format_cycles(estimate.point_estimate * ghz);
+                    let _ = write!(out, " {} |", numbers.pad(&numbers.localize(&cycles)));
+                }
+                None => out.push_str(" - |"),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn csv(records: &[BenchRecord], options: &ExportOptions) -> String {
+    let numbers = NumberFormat::current();
+    let number = |value: f64| csv_field(&numbers.format(value));
+    let amount = |throughput: &Throughput| {
+        csv_field(&numbers.format_amount(crate::units::throughput_amount(throughput).0))
+    };
+    let secondary = has_secondary(records);
+    let cpu = has_utilization(records);
+    let mut out = String::from("benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv");
+    if secondary {
+        out.push_str(",secondary_throughput,secondary_unit,secondary_point");
+    }
+    if cpu {
+        out.push_str(",cpu,utilization");
+    }
+    if options.media.is_some() {
+        out.push_str(",realtime");
+    }
+    if options.frequency().is_some() {
+        out.push_str(",cycles");
+    }
+    out.push('\n');
+    for record in records {
+        let (Some(throughput), Some(unit), Some(estimate), Some(dispersion)) = (
+            &record.throughput,
+            record.unit(),
+            record.time_per_unit(),
+            record.dispersion_per_unit(),
+        ) else {
+            continue;
+        };
+        let _ = write!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            csv_field(&record.id),
+            amount(throughput),
+            csv_field(unit_per("ns", unit)),
+            number(estimate.lower_bound),
+            number(estimate.point_estimate),
+            number(estimate.upper_bound),
+            number(dispersion.std_dev),
+            number(dispersion.median_abs_dev),
+            number(dispersion.coefficient_of_variation),
+        );
+        if secondary {
+            match (
+                &record.secondary_throughput,
+                record.secondary_unit(),
+                record.secondary_time_per_unit(),
+            ) {
+                (Some(throughput), Some(unit), Some(estimate)) => {
+                    let _ = write!(
+                        out,
+                        ",{},{},{}",
+                        amount(throughput),
+                        csv_field(unit_per("ns", unit)),
+                        number(estimate.point_estimate),
+                    );
+                }
+                _ => out.push_str(",,,"),
+            }
+        }
+        if cpu {
+            match (record.cpu_time_per_unit(), record.utilization) {
+                (Some(cpu_time), Some(utilization)) => {
+                    let _ = write!(out, ",{},{}", number(cpu_time), number(utilization));
+                }
+                _ => out.push_str(",,"),
+            }
+        }
+        if let Some(media) = options.media {
+            let multiple = realtime_multiple(media, estimate.point_estimate);
+            let _ = write!(out, ",{}", number(multiple));
+        }
+        if let Some(ghz) = options.frequency() {
+            let _ = write!(out, ",{}", number(estimate.point_estimate * ghz));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn bencher(records: &[BenchRecord]) -> String {
+    let mut out = String::new();
+    for record in records {
+        let Some(throughput) = &record.throughput else {
+            continue;
+        };
+        let Ok((amount, _)) = crate::units::checked_amount(throughput) else {
+            continue;
+        };
+        // values per element or byte as per iteration, the only unit parsers match
+        let _ = writeln!(
+            out,
+            "test {} ... bench: {:>11} ns/iter (+/- {})",
+            record.id,
+            short(record.estimates.median.point_estimate / amount),
+            short(record.estimates.std_dev.point_estimate / amount),
+        );
+    }
+    out
+}
+
+fn prometheus(records: &[BenchRecord], options: &ExportOptions) -> String {
+    // the samples of a metric must be grouped, and the metrics have their help
+    let mut metrics: BTreeMap<String, (String, String)> = BTreeMap::new();
+    let mut sample = |metric: String, help: String, id: &str, value: f64| {
+        let (_, samples) = metrics
+            .entry(metric.clone())
+            .or_insert_with(|| (help, String::new()));
+        let _ = writeln!(
+            samples,
+            "{}{{bench=\"{}\"}} {}",
+            metric,
+            label_value(id),
+            value
+        );
+    };
+    for summary in summaries(records) {
+        let unit = unit_per("ns", summary.unit);
+        let denominator = unit.strip_prefix("ns/").unwrap_or(unit);
+        sample(
+            metric_name(unit),
+            format!(
+                "The point estimate of the benchmark in nanoseconds per {}",
+                denominator
+            ),
+            &summary.id,
+            summary.point_estimate,
+        );
+        if let Some(media) = options.media {
+            sample(
+                "bench_realtime_multiple".to_string(),
+                "How many times faster than real time the benchmark processes media".to_string(),
+                &summary.id,
+                summary.realtime_multiple(media),
+            );
+        }
+        if let Some(ghz) = options.frequency() {
+            sample(
+                metric_name(unit_per("cycles", summary.unit)),
+                format!(
+                    "The approximate cycles of the benchmark per {} at {} GHz",
+                    denominator, ghz
+                ),
+                &summary.id,
+                summary.cycles(ghz),
+            );
+        }
+    }
+    let mut out = String::new();
+    for (metric, (help, samples)) in metrics {
+        let _ = writeln!(out, "# HELP {metric} {help}\n# TYPE {metric} gauge");
+        out.push_str(&samples);
+    }
+    out
+}
+
+fn environment_info(environment: &Environment) -> String {
+    let mut labels = Vec::new();
+    if let Some(cpu_model) = &environment.cpu_model {
+        labels.push(format!("cpu=\"{}\"", label_value(cpu_model)));
+    }
+    if let Some(governor) = &environment.governor {
+        labels.push(format!("governor=\"{}\"", label_value(governor)));
+    }
+    if let Some(ghz) = environment.frequency_ghz {
+        labels.push(format!("frequency_ghz=\"{}\"", ghz));
+    }
+    if let Some(core) = environment.core {
+        labels.push(format!("core=\"{}\"", core));
+    }
+    if let Some(nice) = environment.nice {
+        labels.push(format!("nice=\"{}\"", nice));
+    }
+    format!(
+        "# HELP bench_environment_info The environment the benchmarks ran in\n\
+         # TYPE bench_environment_info gauge\n\
+         bench_environment_info{{{}}} 1\n",
+        labels.join(",")
+    )
+}
+
+// the name of the metric of values in `unit` like `bench_ns_per_row_col` for `ns/(row·col)`
+fn metric_name(unit: &str) -> String {
+    let mut name = String::from("bench");
+    for word in unit
+        .replace('/', " per ")
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        name.push('_');
+        name.push_str(word);
+    }
+    name
+}
+
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// how many times faster than real time `ns` nanoseconds per element or byte of `media` are
+fn realtime_multiple(media: Duration, ns: f64) -> f64 {
+    media.as_nanos() as f64 / ns
+}
+
+fn has_secondary(records: &[BenchRecord]) -> bool {
+    records
+        .iter()
+        .any(|record| record.throughput.is_some() && record.secondary_throughput.is_some())
+}
+
+fn has_utilization(records: &[BenchRecord]) -> bool {
+    records
+        .iter()
+        .any(|record| record.throughput.is_some() && record.utilization.is_some())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// formats a value with 5 significant digits like criterion does
+pub(crate) fn short(n: f64) -> String {
+    if n < 10.0 {
+        format!("{:.4}", n)
+    } else if n < 100.0 {
+        format!("{:.3}", n)
+    } else if n < 1000.0 {
+        format!("{:.2}", n)
+    } else if n < 10000.0 {
+        format!("{:.1}", n)
+    } else {
+        format!("{:.0}", n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RecordBuilder;
+    use std::env;
+
+    fn record(id: &str, throughput: Option<Throughput>, point_estimate: f64) -> BenchRecord {
+        RecordBuilder::new(id, point_estimate)
+            .throughput(throughput)
+            .margin(100.0)
+            .build()
+    }
+
+    fn records() -> Vec<BenchRecord> {
+        vec![
+            record("parse/10", Some(Throughput::Elements(10)), 1000.0),
+            record("parse,none", None, 1000.0),
+            record("parse,\"bytes\"", Some(Throughput::Bytes(1000)), 2000.0),
+        ]
+    }
+
+    #[test]
+    fn test_summaries() {
+        let summaries = summaries(&records());
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(
+            summaries[0],
+            Summary {
+                id: "parse/10".to_string(),
+                unit: "elem",
+                lower_bound: 90.0,
+                point_estimate: 100.0,
+                upper_bound: 110.0,
+                dispersion: Dispersion {
+                    std_dev: 100.0,
+                    median_abs_dev: 100.0,
+                    coefficient_of_variation: 1.0,
+                },
+                cpu_time: None,
+                utilization: None,
+            }
+        );
+        assert_eq!(summaries[1].unit, "byte");
+        // 100 ns per element of 10 µs
+        assert_eq!(
+            summaries[0].realtime_multiple(Duration::from_micros(10)),
+            100.0
+        );
+        assert_eq!(summaries[0].cycles(3.0), 300.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_summaries() {
+        let summaries = summaries(&records()[..1]);
+        let json = serde_json::to_string(&summaries).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"id":"parse/10","unit":"elem","lower_bound":90.0,"point_estimate":100.0,"upper_bound":110.0,"dispersion":{"std_dev":100.0,"median_abs_dev":100.0,"coefficient_of_variation":1.0}}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<Summary>>(&json).unwrap(),
+            summaries
+        );
+    }
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(
+            export(&records(), Format::Markdown),
+            "| benchmark | throughput | lower | point | upper |\n\
+             |:--|--:|--:|--:|--:|\n\
+             | parse/10 | 10 elem | 90.000 ns/elem | 100.00 ns/elem | 110.00 ns/elem |\n\
+             | parse,\"bytes\" | 1000 byte | 1.9000 ns/byte | 2.0000 ns/byte | 2.1000 ns/byte |\n"
+        );
+    }
+
+    #[test]
+    fn test_bencher() {
+        assert_eq!(
+            export(&records(), Format::Bencher),
+            "test parse/10 ... bench:      100.00 ns/iter (+/- 100.00)\n\
+             test parse,\"bytes\" ... bench:      2.0000 ns/iter (+/- 2.0000)\n"
+        );
+    }
+
+    fn records_with_secondary() -> Vec<BenchRecord> {
+        let mut records = records();
+        records[0].secondary_throughput = Some(Throughput::Bytes(50));
+        records
+    }
+
+    #[test]
+    fn test_markdown_with_secondary() {
+        assert_eq!(
+            export(&records_with_secondary(), Format::Markdown),
+            "| benchmark | throughput | lower | point | upper | secondary throughput | secondary point |\n\
+             |:--|--:|--:|--:|--:|--:|--:|\n\
+             | parse/10 | 10 elem | 90.000 ns/elem | 100.00 ns/elem | 110.00 ns/elem | 50 byte | 20.000 ns/byte |\n\
+             | parse,\"bytes\" | 1000 byte | 1.9000 ns/byte | 2.0000 ns/byte | 2.1000 ns/byte | - | - |\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_with_secondary() {
+        assert_eq!(
+            export(&records_with_secondary(), Format::Csv),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv,secondary_throughput,secondary_unit,secondary_point\n\
+             parse/10,10,ns/elem,90,100,110,100,100,1,50,ns/byte,20\n\
+             \"parse,\"\"bytes\"\"\",1000,ns/byte,1.9,2,2.1,2,2,1,,,\n"
+        );
+    }
+
+    fn records_with_utilization() -> Vec<BenchRecord> {
+        let mut records = records();
+        records[0].utilization = Some(0.5);
+        records
+    }
+
+    #[test]
+    fn test_markdown_with_utilization() {
+        assert_eq!(
+            export(&records_with_utilization(), Format::Markdown),
+            "| benchmark | throughput | lower | point | upper | cpu | utilization |\n\
+             |:--|--:|--:|--:|--:|--:|--:|\n\
+             | parse/10 | 10 elem | 90.000 ns/elem | 100.00 ns/elem | 110.00 ns/elem | 50.000 ns/elem | 50.0% |\n\
+             | parse,\"bytes\" | 1000 byte | 1.9000 ns/byte | 2.0000 ns/byte | 2.1000 ns/byte | - | - |\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_with_utilization() {
+        assert_eq!(
+            export(&records_with_utilization(), Format::Csv),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv,cpu,utilization\n\
+             parse/10,10,ns/elem,90,100,110,100,100,1,50,0.5\n\
+             \"parse,\"\"bytes\"\"\",1000,ns/byte,1.9,2,2.1,2,2,1,,\n"
+        );
+        let summaries = summaries(&records_with_utilization());
+        assert_eq!(summaries[0].cpu_time, Some(50.0));
+        assert_eq!(summaries[0].utilization, Some(0.5));
+        assert_eq!(summaries[1].cpu_time, None);
+    }
+
+    #[test]
+    fn test_export_realtime() {
+        let options = ExportOptions::new().per_media_seconds(Duration::from_micros(10));
+        assert_eq!(
+            export_with(&records(), Format::Markdown, &options),
+            "| benchmark | throughput | lower | point | upper | realtime |\n\
+             |:--|--:|--:|--:|--:|--:|\n\
+             | parse/10 | 10 elem | 90.000 ns/elem | 100.00 ns/elem | 110.00 ns/elem | 100.0x |\n\
+             | parse,\"bytes\" | 1000 byte | 1.9000 ns/byte | 2.0000 ns/byte | 2.1000 ns/byte | 5000.0x |\n"
+        );
+        assert_eq!(
+            export_with(&records(), Format::Csv, &options),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv,realtime\n\
+             parse/10,10,ns/elem,90,100,110,100,100,1,100\n\
+             \"parse,\"\"bytes\"\"\",1000,ns/byte,1.9,2,2.1,2,2,1,5000\n"
+        );
+        assert!(export_with(&records(), Format::Prometheus, &options).ends_with(
+            "# HELP bench_realtime_multiple How many times faster than real time the benchmark processes media\n\
+             # TYPE bench_realtime_multiple gauge\n\
+             bench_realtime_multiple{bench=\"parse/10\"} 100\n\
+             bench_realtime_multiple{bench=\"parse,\\\"bytes\\\"\"} 5000\n"
+        ));
+        assert_eq!(
+            export_with(&records(), Format::Bencher, &options),
+            export(&records(), Format::Bencher)
+        );
+    }
+
+    #[test]
+    fn test_export_cycles() {
+        let options = ExportOptions::new().cycles_at(3.0);
+        assert_eq!(
+            export_with(&records(), Format::Markdown, &options),
+            "| benchmark | throughput | lower | point | upper | cycles |\n\
+             |:--|--:|--:|--:|--:|--:|\n\
+             | parse/10 | 10 elem | 90.000 ns/elem | 100.00 ns/elem | 110.00 ns/elem | 300 |\n\
+             | parse,\"bytes\" | 1000 byte | 1.9000 ns/byte | 2.0000 ns/byte | 2.1000 ns/byte | 6.0 |\n"
+        );
+        assert_eq!(
+            export_with(&records(), Format::Csv, &options),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv,cycles\n\
+             parse/10,10,ns/elem,90,100,110,100,100,1,300\n\
+             \"parse,\"\"bytes\"\"\",1000,ns/byte,1.9,2,2.1,2,2,1,6\n"
+        );
+        assert_eq!(
+            export_with(&records(), Format::Prometheus, &options),
+            "# HELP bench_cycles_per_byte The approximate cycles of the benchmark per byte at 3 GHz\n\
+             # TYPE bench_cycles_per_byte gauge\n\
+             bench_cycles_per_byte{bench=\"parse,\\\"bytes\\\"\"} 6\n\
+             # HELP bench_cycles_per_elem The approximate cycles of the benchmark per elem at 3 GHz\n\
+             # TYPE bench_cycles_per_elem gauge\n\
+             bench_cycles_per_elem{bench=\"parse/10\"} 300\n\
+             # HELP bench_ns_per_byte The point estimate of the benchmark in nanoseconds per byte\n\
+             # TYPE bench_ns_per_byte gauge\n\
+             bench_ns_per_byte{bench=\"parse,\\\"bytes\\\"\"} 2\n\
+             # HELP bench_ns_per_elem The point estimate of the benchmark in nanoseconds per elem\n\
+             # TYPE bench_ns_per_elem gauge\n\
+             bench_ns_per_elem{bench=\"parse/10\"} 100\n"
+        );
+
+        // the frequency of the environment
+        let environment = Environment {
+            frequency_ghz: Some(2.0),
+            ..Environment::default()
+        };
+        assert!(
+            export_with_environment(&records()[..1], Format::Csv, &environment)
+                .ends_with(",cycles\nparse/10,10,ns/elem,90,100,110,100,100,1,200\n")
+        );
+        let options = ExportOptions::new().environment(environment).cycles_at(3.0);
+        assert!(export_with(&records()[..1], Format::Csv, &options).ends_with(",1,300\n"));
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let mut records = records();
+        records.push(record("parse/20", Some(Throughput::Elements(20)), 1000.0));
+        assert_eq!(
+            export(&records, Format::Prometheus),
+            "# HELP bench_ns_per_byte The point estimate of the benchmark in nanoseconds per byte\n\
+             # TYPE bench_ns_per_byte gauge\n\
+             bench_ns_per_byte{bench=\"parse,\\\"bytes\\\"\"} 2\n\
+             # HELP bench_ns_per_elem The point estimate of the benchmark in nanoseconds per elem\n\
+             # TYPE bench_ns_per_elem gauge\n\
+             bench_ns_per_elem{bench=\"parse/10\"} 100\n\
+             bench_ns_per_elem{bench=\"parse/20\"} 50\n"
+        );
+    }
+
+    #[test]
+    fn test_write_textfile() {
+        let path = env::temp_dir().join("criterion-inverted-throughput-test-export.prom");
+        write_textfile(&path, &records()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            export(&records(), Format::Prometheus)
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_export_with_environment() {
+        let environment = Environment {
+            cpu_model: Some("Example \"CPU\"".to_string()),
+            core: Some(2),
+            ..Environment::default()
+        };
+        let records = &records()[..1];
+        assert!(
+            export_with_environment(records, Format::Markdown, &environment)
+                .ends_with("|\n\nEnvironment: Example \"CPU\", pinned to core 2\n")
+        );
+        assert!(
+            export_with_environment(records, Format::Prometheus, &environment).ends_with(
+                "# TYPE bench_environment_info gauge\n\
+                 bench_environment_info{cpu=\"Example \\\"CPU\\\"\",core=\"2\"} 1\n"
+            )
+        );
+        assert_eq!(
+            export_with_environment(records, Format::Csv, &environment),
+            export(records, Format::Csv)
+        );
+    }
+
+    #[test]
+    fn test_export_per_spec() {
+        let records = &records()[..1];
+        let _label =
+            crate::denominator::InvertedSpec::new([("row", 2), ("col", 5)]).label_elements();
+        assert_eq!(
+            export(records, Format::Prometheus),
+            "# HELP bench_ns_per_row_col The point estimate of the benchmark in nanoseconds per (row·col)\n\
+             # TYPE bench_ns_per_row_col gauge\n\
+             bench_ns_per_row_col{bench=\"parse/10\"} 100\n"
+        );
+        assert_eq!(
+            export(records, Format::Csv),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv\n\
+             parse/10,10,ns/(row·col),90,100,110,100,100,1\n"
+        );
+        assert!(export(records, Format::Markdown).contains("| 100.00 ns/(row·col) |"));
+    }
+
+    #[test]
+    fn test_export_numbers() {
+        let records = &[record(
+            "parse/1k",
+            Some(Throughput::Elements(1000)),
+            12345678.0,
+        )];
+        let _numbers = NumberFormat::european().width(16).apply();
+        assert!(export(records, Format::Markdown).contains(
+            "| parse/1k | 1.000 elem |   12,346 µs/elem |   12,346 µs/elem |   12,346 µs/elem |\n"
+        ));
+        let _numbers = NumberFormat::european().precision(1).apply();
+        assert_eq!(
+            export(records, Format::Csv),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv\n\
+             parse/1k,1.000,ns/elem,\"12.345,6\",\"12.345,7\",\"12.345,8\",\"12.345,7\",\"12.345,7\",\"1,0\"\n"
+        );
+        assert_eq!(
+            export(records, Format::Bencher),
+            "test parse/1k ... bench:       12346 ns/iter (+/- 12346)\n"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut records = records();
+        records[0].estimates.std_dev.point_estimate = 50.0;
+        records[0].estimates.median_abs_dev.point_estimate = 30.0;
+        assert_eq!(
+            export(&records, Format::Csv),
+            "benchmark,throughput,unit,lower,point,upper,std_dev,mad,cv\n\
+             parse/10,10,ns/elem,90,100,110,5,3,0.05\n\
+             \"parse,\"\"bytes\"\"\",1000,ns/byte,1.9,2,2.1,2,2,1\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Time measurement with external timers
+//!
+//! [`ExternalTimer`] measures times with user-supplied callbacks reading an arbitrary timer,
+//! e.g. a cycle counter of a microcontroller read over a debug probe, and prints throughputs
+//! in the format `[time]/[element or byte]` like [`InvertedThroughput`](crate::InvertedThroughput).
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::external::ExternalTimer;
+//!
+//! # fn read_cycle_counter() -> u64 { 0 }
+//! let timer = ExternalTimer::new(
+//!     read_cycle_counter,
+//!     |start| read_cycle_counter().wrapping_sub(start) & 0xffff_ffff,
+//!     // the counter runs at 64 MHz
+//!     1.0 / 64e6,
+//! );
+//! let c = Criterion::default().with_measurement(timer);
+//! ```
+//!
+//! It takes the options of [`InvertedThroughput`](crate::InvertedThroughput) like
+//! [`ExternalTimer::precision`].
+
+use crate::{inversion_options, Inversion, TimeFormatter};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+
+/// The custom measurement of times with an external timer
+///
+/// `start` returns the current value of the timer, and `end` returns the ticks elapsed since
+/// the value `start` returned, so that wrapping counters can be handled by the callbacks.
+pub struct ExternalTimer<S, E> {
+    start: S,
+    end: E,
+    seconds_per_tick: f64,
+    inversion: Inversion,
+}
+
+impl<S, E> ExternalTimer<S, E>
+where
+    S: Fn() -> u64,
+    E: Fn(u64) -> u64,
+{
+    /// Returns an `ExternalTimer` with the callbacks and the length of a tick in seconds
+    pub fn new(start: S, end: E, seconds_per_tick: f64) -> Self {
+        ExternalTimer {
+            start,
+            end,
+            seconds_per_tick,
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl<S, E> Measurement for ExternalTimer<S, E>
+where
+    S: Fn() -> u64,
+    E: Fn(u64) -> u64,
+{
+    type Intermediate = u64;
+    // ticks
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        (self.start)()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        (self.end)(i)
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        // in nanoseconds to be formatted as times
+        *val as f64 * self.seconds_per_tick * 1e9
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl<S, E> ValueFormatter for ExternalTimer<S, E> {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&TimeFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&TimeFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&TimeFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&TimeFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&TimeFormatter, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::thrpt_line;
+    use crate::{MachineOutput, Precision};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_external_timer() {
+        let counter = Cell::new(0u64);
+        let timer = ExternalTimer::new(
+            || counter.get(),
+            |start| {
+                counter.set(counter.get() + 640);
+                counter.get() - start
+            },
+            1.0 / 64e6,
+        );
+        let ticks = timer.end(timer.start());
+        assert_eq!(ticks, 640);
+        assert_eq!(timer.to_f64(&timer.add(&ticks, &ticks)), 20_000.0);
+
+        let mut values = [timer.to_f64(&ticks)];
+        let unit =
+            timer
+                .formatter()
+                .scale_throughputs(values[0], &Throughput::Elements(10), &mut values);
+        assert_eq!(unit, "µs/elem");
+        assert!((values[0] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_options() {
+        let timer = ExternalTimer::new(|| 0, |start| start, 1e-9)
+            .machine_output(MachineOutput::PerUnit)
+            .precision(Precision::Decimals(1));
+        assert_eq!(
+            thrpt_line(&timer, &Throughput::Elements(10), [1000.0, 1000.0, 1200.0]),
+            [" 100.0 ns/elem", " 100.0 ns/elem", " 120.0 ns/elem"]
+        );
+        let mut values = [1000.0];
+        assert_eq!(timer.formatter().scale_for_machines(&mut values), "ns/elem");
+        assert_eq!(values, [100.0]);
+    }
+}
@@ -0,0 +1,243 @@
+//! Time measurement with GPU timestamps
+//!
+//! [`GpuTime`] measures times with timestamps of a GPU (e.g. timestamp queries of
+//! wgpu or Vulkan) instead of CPU wall time, and prints throughputs in the format
+//! `[time]/[element or byte]` like [`InvertedThroughput`](crate::InvertedThroughput).
+//!
+//! The GPU API is not bound: give the timestamps as ticks and the period of a tick.
+//! Since GPU work runs asynchronously, timestamps are usually resolved after submitting
+//! a batch of iterations, with [`Bencher::iter_custom`](criterion::Bencher::iter_custom):
+//!
+//! ```no_run
+//! use criterion::{Bencher, Criterion, Throughput};
+//! use criterion_inverted_throughput::gpu::{ExternalTimestamps, GpuTime};
+//!
+//! # fn dispatch_and_resolve_timestamps(_iters: u64) -> (u64, u64) { (0, 0) }
+//! // e.g. `queue.get_timestamp_period()` with wgpu
+//! let period_ns = 1.0;
+//! let gpu = GpuTime::external(period_ns);
+//! let mut c = Criterion::default().with_measurement(gpu.clone());
+//!
+//! let mut g = c.benchmark_group("shader");
+//! g.throughput(Throughput::Elements(1920 * 1080));
+//! g.bench_function("blur", |b: &mut Bencher<GpuTime<ExternalTimestamps>>| {
+//!     b.iter_custom(|iters| {
+//!         // dispatch the shader `iters` times between two timestamp writes
+//!         let (start, end) = dispatch_and_resolve_timestamps(iters);
+//!         gpu.elapsed(start, end)
+//!     })
+//! });
+//! g.finish();
+//! ```
+//!
+//! If the GPU timestamp can be read synchronously, implement [`GpuTimestamps`]
+//! (or give a closure returning it) to use `Bencher::iter` and others.
+//!
+//! It takes the options of [`InvertedThroughput`](crate::InvertedThroughput) like
+//! [`GpuTime::precision`].
+
+use crate::{inversion_options, Inversion, TimeFormatter};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+use std::fmt;
+
+/// The source of the current GPU timestamp in ticks
+pub trait GpuTimestamps {
+    /// Returns the current timestamp in ticks, after the previously submitted work is done
+    fn timestamp(&self) -> u64;
+}
+
+impl<F: Fn() -> u64> GpuTimestamps for F {
+    fn timestamp(&self) -> u64 {
+        self()
+    }
+}
+
+/// The source of timestamps resolved outside of the measurement, for `iter_custom`
+///
+/// Reading the timestamp from it panics, so only `Bencher::iter_custom` is available.
+#[derive(Debug, Clone, Copy)]
+pub struct ExternalTimestamps;
+
+impl GpuTimestamps for ExternalTimestamps {
+    fn timestamp(&self) -> u64 {
+        panic!("timestamps of `GpuTime::external` are given externally, use `iter_custom`")
+    }
+}
+
+/// The custom measurement of times with GPU timestamps, in nanoseconds
+///
+/// Clones share the source, the period and the options.
+#[derive(Clone)]
+pub struct GpuTime<S> {
+    source: S,
+    period_ns: f64,
+    mask: u64,
+    inversion: Inversion,
+}
+
+impl<S: fmt::Debug> fmt::Debug for GpuTime<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpuTime")
+            .field("source", &self.source)
+            .field("period_ns", &self.period_ns)
+            .field("mask", &self.mask)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GpuTime<ExternalTimestamps> {
+    /// Returns a `GpuTime` whose timestamps are given externally with
+    /// [`GpuTime::elapsed`] in `iter_custom`
+    pub fn external(period_ns: f64) -> Self {
+        Self::new(ExternalTimestamps, period_ns)
+    }
+}
+
+impl<S> GpuTime<S> {
+    /// Returns a `GpuTime` reading timestamps from `source`, whose tick is `period_ns` nanoseconds
+    pub fn new(source: S, period_ns: f64) -> Self {
+        GpuTime {
+            source,
+            period_ns,
+            mask: u64::MAX,
+            inversion: Inversion::new(),
+        }
+    }
+
+    /// Sets the number of valid bits of timestamps, so that wrapped timestamps are handled
+    ///
+    /// The default is 64.
+    pub fn valid_bits(mut self, bits: u32) -> Self {
+        self.mask = u64::MAX.checked_shr(64 - bits.min(64)).unwrap_or(0);
+        self
+    }
+
+    /// Returns the time in nanoseconds from the timestamp `start` to `end`
+    pub fn elapsed(&self, start: u64, end: u64) -> f64 {
+        let ticks = end.wrapping_sub(start) & self.mask;
+        ticks as f64 * self.period_ns
+    }
+
+    inversion_options!();
+}
+
+impl<S: GpuTimestamps> Measurement for GpuTime<S> {
+    type Intermediate = u64;
+    type Value = f64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        self.source.timestamp()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        self.elapsed(i, self.source.timestamp())
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0.0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl<S> ValueFormatter for GpuTime<S> {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&TimeFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&TimeFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&TimeFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&TimeFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&TimeFormatter, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::thrpt_line;
+    use crate::{MachineOutput, Precision};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_elapsed() {
+        let gpu = GpuTime::external(2.5);
+        assert_eq!(gpu.elapsed(100, 140), 100.0);
+
+        let gpu = GpuTime::external(1.0).valid_bits(8);
+        assert_eq!(gpu.elapsed(250, 4), 10.0);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let ticks = Cell::new(0);
+        let gpu = GpuTime::new(
+            || {
+                ticks.set(ticks.get() + 10);
+                ticks.get()
+            },
+            0.5,
+        );
+        let value = gpu.end(gpu.start());
+        assert_eq!(value, 5.0);
+        assert_eq!(gpu.add(&value, &value), 10.0);
+    }
+
+    #[test]
+    fn test_scale_throughputs() {
+        let gpu = GpuTime::external(1.0);
+        let mut values = [2e6];
+        let unit = gpu
+            .formatter()
+            .scale_throughputs(2e6, &Throughput::Elements(1000), &mut values);
+        assert_eq!(unit, "µs/elem");
+        assert_eq!(values, [2.0]);
+    }
+
+    #[test]
+    fn test_options() {
+        let gpu = GpuTime::external(1.0)
+            .machine_output(MachineOutput::PerUnit)
+            .precision(Precision::Decimals(1));
+        assert_eq!(
+            thrpt_line(&gpu, &Throughput::Elements(10), [1000.0, 1000.0, 1200.0]),
+            [" 100.0 ns/elem", " 100.0 ns/elem", " 120.0 ns/elem"]
+        );
+        let mut values = [1000.0];
+        assert_eq!(gpu.formatter().scale_for_machines(&mut values), "ns/elem");
+        assert_eq!(values, [100.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_external_timestamps() {
+        GpuTime::external(1.0).start();
+    }
+}
@@ -0,0 +1,282 @@
+//! History of time per element or byte over runs
+//!
+//! Appends the typical estimates per element or byte of each run to
+//! `target/criterion-inverted/history/history.jsonl` with the git commit and the time,
+//! then compares the oldest and the latest of the last runs to catch slow drifts
+//! which no single comparison between two baselines shows:
+//!
+//! ```no_run
+//! use criterion_inverted_throughput::compare::table;
+//! use criterion_inverted_throughput::history::{self, drifted, trends};
+//! use criterion_inverted_throughput::results::{criterion_home, load_records, LATEST_BASELINE};
+//!
+//! // after `cargo bench`
+//! let records = load_records(&criterion_home(), LATEST_BASELINE).unwrap();
+//! history::record(&records).unwrap();
+//!
+//! let trends = trends(&history::load(&history::history_dir()).unwrap(), 10);
+//! print!("{}", table(&trends));
+//! for trend in drifted(&trends, 5.0) {
+//!     println!("{} drifted by {:.2}%", trend.id, trend.change());
+//! }
+//! ```
+
+use crate::compare::Comparison;
+use crate::results::{criterion_home, BenchRecord, Estimate};
+use crate::static_unit;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The file storing the history in the history directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// The estimate per element or byte of a benchmark in a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The full id of the benchmark like `group/function/value`
+    pub id: String,
+    /// The unit of the throughput, `elem` or `byte`
+    pub unit: String,
+    /// The lower bound of the confidence interval in nanoseconds per element or byte
+    pub lower_bound: f64,
+    /// The point estimate in nanoseconds per element or byte
+    pub point_estimate: f64,
+    /// The upper bound of the confidence interval in nanoseconds per element or byte
+    pub upper_bound: f64,
+    /// The standard error of the point estimate in nanoseconds per element or byte
+    pub standard_error: f64,
+    /// The git commit checked out in the run, if any
+    pub git_sha: Option<String>,
+    /// The time of the run in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl Entry {
+    /// Returns the entry of `record` with its estimate per element or byte,
+    /// or `None` if it has no throughput
+    pub fn new(record: &BenchRecord, git_sha: Option<String>, timestamp: u64) -> Option<Self> {
+        let estimate = record.time_per_unit()?;
+        Some(Entry {
+            id: record.id.clone(),
+            unit: record.unit()?.to_string(),
+            lower_bound: estimate.lower_bound,
+            point_estimate: estimate.point_estimate,
+            upper_bound: estimate.upper_bound,
+            standard_error: estimate.standard_error,
+            git_sha,
+            timestamp,
+        })
+    }
+
+    fn estimate(&self) -> Estimate {
+        Estimate {
+            lower_bound: self.lower_bound,
+            point_estimate: self.point_estimate,
+            upper_bound: self.upper_bound,
+            standard_error: self.standard_error,
+        }
+    }
+}
+
+/// Returns the directory the history is stored in,
+/// `criterion-inverted/history` next to the directory criterion stores results in
+pub fn history_dir() -> PathBuf {
+    let home = criterion_home();
+    home.parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("criterion-inverted")
+        .join("history")
+}
+
+/// Appends the estimates of `records` to the history with the current git commit and time
+///
+/// Returns the number of appended entries. Benchmarks without throughput are skipped.
+pub fn record(records: &[BenchRecord]) -> io::Result<usize> {
+    record_in(&history_dir(), records, git_sha(), now())
+}
+
+/// Appends the estimates of `records` to the history in `dir`
+///
+/// Returns the number of appended entries. Benchmarks without throughput are skipped.
+pub fn record_in(
+    dir: &Path,
+    records: &[BenchRecord],
+    git_sha: Option<String>,
+    timestamp: u64,
+) -> io::Result<usize> {
+    let mut lines = String::new();
+    let mut count = 0;
+    for entry in records
+        .iter()
+        .filter_map(|record| Entry::new(record, git_sha.clone(), timestamp))
+    {
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        lines.push_str(&json);
+        lines.push('\n');
+        count += 1;
+    }
+    fs::create_dir_all(dir)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(HISTORY_FILE))?
+        .write_all(lines.as_bytes())?;
+    Ok(count)
+}
+
+/// Loads the entries of the history in `dir` in the order they were recorded
+pub fn load(dir: &Path) -> io::Result<Vec<Entry>> {
+    let path = dir.join(HISTORY_FILE);
+    let jsonl = match fs::read_to_string(&path) {
+        Ok(jsonl) => jsonl,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path.display(), i + 1, e),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Compares the oldest and the latest of the last `runs` entries of each benchmark,
+/// sorted by delta with the largest regression first
+///
+/// Benchmarks with a single entry, or whose unit changed in the runs, are skipped.
+pub fn trends(entries: &[Entry], runs: usize) -> Vec<Comparison> {
+    let mut by_id: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        by_id.entry(&entry.id).or_default().push(entry);
+    }
+    let mut trends: Vec<Comparison> = by_id
+        .into_values()
+        .filter_map(|entries| {
+            let last = &entries[entries.len().saturating_sub(runs.max(2))..];
+            let (oldest, latest) = (last.first()?, last.last()?);
+            if last.len() < 2 || last.iter().any(|entry| entry.unit != latest.unit) {
+                return None;
+            }
+            Some(Comparison {
+                id: latest.id.clone(),
+                unit: static_unit(&latest.unit).ok()?,
+                old: oldest.estimate(),
+                new: latest.estimate(),
+            })
+        })
+        .collect();
+    trends.sort_by(|a, b| b.delta().total_cmp(&a.delta()));
+    trends
+}
+
+/// Returns the trends whose change exceeds `threshold` percent in either direction
+pub fn drifted(trends: &[Comparison], threshold: f64) -> Vec<&Comparison> {
+    trends
+        .iter()
+        .filter(|trend| trend.change().abs() > threshold)
+        .collect()
+}
+
+/// Returns the current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Returns the git commit checked out in the current directory, if any
+pub fn git_sha() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RecordBuilder;
+    use criterion::Throughput;
+    use std::env;
+
+    fn record(id: &str, throughput: Option<Throughput>, point_estimate: f64) -> BenchRecord {
+        RecordBuilder::new(id, point_estimate)
+            .throughput(throughput)
+            .build()
+    }
+
+    #[test]
+    fn test_record() {
+        let dir = env::temp_dir().join("criterion-inverted-throughput-test-history");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(load(&dir).unwrap().is_empty());
+
+        let records = [
+            record("parse/10", Some(Throughput::Elements(10)), 1000.0),
+            record("parse/none", None, 1000.0),
+        ];
+        assert_eq!(record_in(&dir, &records, None, 1).unwrap(), 1);
+        assert_eq!(
+            record_in(&dir, &records, Some("abc".to_string()), 2).unwrap(),
+            1
+        );
+        let entries = load(&dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].point_estimate, 100.0);
+        assert_eq!(entries[0].git_sha, None);
+        assert_eq!(entries[1].git_sha.as_deref(), Some("abc"));
+        assert_eq!(entries[1].timestamp, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trends() {
+        let entries: Vec<Entry> = [
+            ("parse/10", 100.0),
+            ("parse/20", 100.0),
+            ("parse/10", 50.0),
+            ("parse/single", 100.0),
+            ("parse/10", 104.0),
+            ("parse/20", 90.0),
+            ("parse/10", 110.0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (id, point_estimate))| {
+            let record = record(id, Some(Throughput::Elements(1)), *point_estimate);
+            Entry::new(&record, None, i as u64).unwrap()
+        })
+        .collect();
+
+        let trends = trends(&entries, 3);
+        let ids: Vec<&str> = trends.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["parse/10", "parse/20"]);
+        // the last 3 runs of `parse/10` are from 50 to 110
+        assert_eq!(trends[0].old.point_estimate, 50.0);
+        assert_eq!(trends[0].new.point_estimate, 110.0);
+        assert_eq!(trends[1].delta(), -10.0);
+
+        let drifted: Vec<&str> = drifted(&trends, 10.0)
+            .iter()
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(drifted, vec!["parse/10"]);
+    }
+}
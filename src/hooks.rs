@@ -0,0 +1,252 @@
+//! Callbacks run with the results of benchmarks
+//!
+//! Register hooks to stream results to databases, notifiers or test frameworks without
+//! an exporter for each backend. A hook gets the [`BenchRecord`] of a benchmark with its id,
+//! its throughput and its estimates, and the estimates per element or byte by
+//! [`BenchRecord::time_per_unit`] or [`InvertedEstimates::new`](crate::results::InvertedEstimates::new):
+//!
+//! Criterion stores a result after printing it, so the hooks run with the results stored
+//! in the output directory of criterion. [`InvertedThroughput::run_hooks`] runs them with the
+//! results of all benchmarks measured with it, when it is dropped with the `Criterion` of
+//! the group, so `criterion_group!` and `criterion_main!` run them without changes:
+//!
+//! ```no_run
+//! use criterion::{criterion_group, criterion_main, Criterion};
+//! use criterion_inverted_throughput::{hooks, results, InvertedThroughput};
+//!
+//! fn bench(c: &mut Criterion<InvertedThroughput>) {
+//!     hooks::register(|record| {
+//!         if let Some(estimate) = record.time_per_unit() {
+//!             println!("{}: {} ns per unit", record.id, estimate.point_estimate);
+//!         }
+//!     });
+//!     c.bench_function("sum", |b| b.iter(|| (0..100u64).sum::<u64>()));
+//! }
+//!
+//! criterion_group!(
+//!     name = benches;
+//!     config = Criterion::default()
+//!         .with_measurement(InvertedThroughput::new().run_hooks(&results::criterion_home()));
+//!     targets = bench
+//! );
+//! criterion_main!(benches);
+//! ```
+//!
+//! Pass the directory passed to `Criterion::output_directory` instead of
+//! [`criterion_home`](crate::results::criterion_home) if any. [`bench_function`] runs the
+//! hooks right after a benchmark, and a [`Snapshot`] after the benchmarks run since it was
+//! captured, with other measurements too.
+//!
+//! The results stored by a run are told apart by the contents of their estimates, so
+//! benchmarks skipped by a filter, which keep their previous results, are not updated.
+//!
+//! [`InvertedThroughput::run_hooks`]: crate::InvertedThroughput::run_hooks
+
+use crate::results::{load_records, BenchRecord, LATEST_BASELINE};
+use criterion::measurement::Measurement;
+use criterion::{Bencher, Criterion};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+type Hook = Arc<dyn Fn(&BenchRecord) + Send + Sync>;
+
+static HOOKS: Mutex<Vec<Hook>> = Mutex::new(Vec::new());
+
+/// Registers `hook` to run with the results of benchmarks
+///
+/// Hooks run in the order they are registered.
+pub fn register(hook: impl Fn(&BenchRecord) + Send + Sync + 'static) {
+    HOOKS.lock().unwrap().push(Arc::new(hook));
+}
+
+/// Unregisters all hooks
+pub fn clear() {
+    HOOKS.lock().unwrap().clear();
+}
+
+/// Runs the hooks with each of `records`
+///
+/// Returns the number of records the hooks ran with.
+pub fn run(records: &[BenchRecord]) -> usize {
+    // hooks may register hooks, so they run without the lock
+    let hooks = HOOKS.lock().unwrap().clone();
+    for record in records {
+        for hook in &hooks {
+            hook(record);
+        }
+    }
+    records.len()
+}
+
+/// The latest results under an output directory of criterion, to run the hooks with the
+/// results stored after it was captured
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    output_directory: PathBuf,
+    // the contents of `estimates.json` by the directory names of the results
+    estimates: HashMap<String, Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Captures the latest results under `output_directory`, the one passed to
+    /// `Criterion::output_directory` or [`criterion_home`](crate::results::criterion_home)
+    ///
+    /// The directory may not exist yet.
+    pub fn capture(output_directory: &Path) -> io::Result<Self> {
+        let mut estimates = HashMap::new();
+        if output_directory.is_dir() {
+            for record in load_records(output_directory, LATEST_BASELINE)? {
+                let contents = fs::read(estimates_path(output_directory, &record))?;
+                estimates.insert(record.directory_name, contents);
+            }
+        }
+        Ok(Snapshot {
+            output_directory: output_directory.to_path_buf(),
+            estimates,
+        })
+    }
+
+    /// Returns the latest results stored since the capture, new or with other estimates
+    pub fn updated_records(&self) -> io::Result<Vec<BenchRecord>> {
+        if !self.output_directory.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut records = load_records(&self.output_directory, LATEST_BASELINE)?;
+        records.retain(|record| {
+            fs::read(estimates_path(&self.output_directory, record))
+                .is_ok_and(|contents| self.estimates.get(&record.directory_name) != Some(&contents))
+        });
+        Ok(records)
+    }
+
+    /// Runs the hooks with the latest results stored since the capture
+    ///
+    /// Returns the number of results the hooks ran with.
+    pub fn run_updated(&self) -> io::Result<usize> {
+        let records = self.updated_records()?;
+        Ok(run(&records))
+    }
+}
+
+/// Runs `routine` as the benchmark `id` of `c` like [`Criterion::bench_function`],
+/// then runs the hooks with its result stored under `output_directory`
+///
+/// `output_directory` is the one passed to `Criterion::output_directory` or
+/// [`criterion_home`](crate::results::criterion_home).
+/// Returns whether the hooks ran, `false` if the benchmark was skipped by a filter.
+pub fn bench_function<M, F>(
+    c: &mut Criterion<M>,
+    output_directory: &Path,
+    id: &str,
+    routine: F,
+) -> io::Result<bool>
+where
+    M: Measurement + 'static,
+    F: FnMut(&mut Bencher<'_, M>),
+{
+    let snapshot = Snapshot::capture(output_directory)?;
+    c.bench_function(id, routine);
+    let mut records = snapshot.updated_records()?;
+    records.retain(|record| record.id == id);
+    Ok(run(&records) > 0)
+}
+
+fn estimates_path(output_directory: &Path, record: &BenchRecord) -> PathBuf {
+    output_directory
+        .join(&record.directory_name)
+        .join(LATEST_BASELINE)
+        .join("estimates.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InvertedThroughput;
+    use criterion::Throughput;
+    use std::env;
+    use std::time::Duration;
+
+    fn criterion<M: Measurement>(c: Criterion<M>, home: &Path) -> Criterion<M> {
+        c.output_directory(home)
+            .sample_size(10)
+            .warm_up_time(Duration::from_millis(1))
+            .measurement_time(Duration::from_millis(10))
+            .without_plots()
+    }
+
+    fn bench<M: Measurement + 'static>(c: &mut Criterion<M>, group: &str, id: &str) {
+        let mut g = c.benchmark_group(group);
+        g.throughput(Throughput::Elements(100));
+        g.bench_function(id, |b| b.iter(|| (0..100u64).sum::<u64>()));
+        g.finish();
+    }
+
+    // the ids and the times per element of the results a hook ran with
+    type Seen = Arc<Mutex<Vec<(String, Option<f64>)>>>;
+
+    // registers a hook remembering the results in `group`
+    fn register_seen(group: &'static str) -> Seen {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let hook_seen = seen.clone();
+        register(move |record| {
+            // other tests may run hooks
+            if record.group_id == group {
+                let per_unit = record.time_per_unit().map(|e| e.point_estimate);
+                hook_seen
+                    .lock()
+                    .unwrap()
+                    .push((record.id.clone(), per_unit));
+            }
+        });
+        seen
+    }
+
+    #[test]
+    fn test_run_updated() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-hooks");
+        let _ = fs::remove_dir_all(&home);
+        let mut c = criterion(Criterion::default(), &home);
+        bench(&mut c, "hooks", "before");
+        bench(&mut c, "hooks", "again");
+
+        let seen = register_seen("hooks");
+        let snapshot = Snapshot::capture(&home).unwrap();
+        bench(&mut c, "hooks", "after");
+        bench(&mut c, "hooks", "again");
+        assert_eq!(snapshot.run_updated().unwrap(), 2);
+
+        let seen = seen.lock().unwrap();
+        let ids: Vec<_> = seen.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["hooks/after", "hooks/again"]);
+        assert!(seen.iter().all(|(_, per_unit)| per_unit.unwrap() > 0.0));
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-run-hooks");
+        let _ = fs::remove_dir_all(&home);
+        let seen = register_seen("run_hooks");
+        let measurement = InvertedThroughput::new().run_hooks(&home);
+        let mut c = criterion(Criterion::default().with_measurement(measurement), &home);
+        bench(&mut c, "run_hooks", "sum");
+        assert!(seen.lock().unwrap().is_empty());
+        drop(c);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, "run_hooks/sum");
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_capture_missing_directory() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-hooks-missing");
+        let _ = fs::remove_dir_all(&home);
+        let snapshot = Snapshot::capture(&home).unwrap();
+        assert!(snapshot.updated_records().unwrap().is_empty());
+    }
+}
@@ -18,7 +18,7 @@
 //!
 //! ```text
 //! time:   [2.8581 µs 2.8720 µs 2.8917 µs]
-//! thrpt:  [68.849 ns/elem 68.381 ns/elem 68.049 ns/elem]
+//! thrpt:  [68.049 ns/elem 68.381 ns/elem 68.849 ns/elem]
 //! ```
 //!
 //! ## Usage
@@ -48,20 +48,609 @@
 //! );
 //! criterion_main!(Foo);
 //! ```
+//!
+//! Or use [`inverted_criterion_group!`] to omit the config:
+//!
+//! ```
+//! # use criterion::{criterion_main, Criterion, measurement::Measurement};
+//! # use criterion_inverted_throughput::inverted_criterion_group;
+//! # fn bench_foo<M: Measurement>(c: &mut Criterion<M>) {}
+//! inverted_criterion_group!(name = Foo; targets = bench_foo);
+//! criterion_main!(Foo);
+//! ```
+
+#[cfg(feature = "alloc-tracking")]
+pub mod allocation;
+pub mod batches;
+pub mod budget;
+pub mod clock;
+pub mod cold_cache;
+pub mod compare;
+mod compat;
+pub mod complexity;
+pub mod counted;
+pub mod cpu_time;
+#[cfg(feature = "cycles-per-byte")]
+pub mod cycles;
+pub mod denominator;
+pub mod environment;
+pub mod export;
+pub mod external;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "serde")]
+pub mod history;
+#[cfg(feature = "serde")]
+pub mod hooks;
+mod macros;
+pub mod numbers;
+pub mod pausable;
+pub mod polls;
+#[cfg(all(feature = "rapl", target_os = "linux"))]
+pub mod rapl;
+pub mod results;
+#[cfg(unix)]
+pub mod rusage;
+#[cfg(feature = "serde")]
+pub mod secondary;
+pub mod settings;
+pub mod sized;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+#[cfg(any(windows, target_os = "macos"))]
+pub mod thread_time;
+#[cfg(feature = "tracing")]
+pub mod trace;
+pub mod units;
+#[cfg(feature = "valgrind")]
+pub mod valgrind;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
 
+// the selected version of criterion is `criterion` in this crate, see `compat`
+// (public to be re-exported)
+#[cfg(feature = "criterion_0_4")]
+#[doc(hidden)]
+pub extern crate criterion_0_4 as criterion;
+#[cfg(feature = "criterion_0_6")]
+#[doc(hidden)]
+pub extern crate criterion_0_6 as criterion;
+#[cfg(feature = "criterion_0_7")]
+#[doc(hidden)]
+pub extern crate criterion_0_7 as criterion;
+#[cfg(feature = "criterion_0_8")]
+#[doc(hidden)]
+pub extern crate criterion_0_8 as criterion;
+#[cfg(not(any(
+    feature = "criterion_0_4",
+    feature = "criterion_0_5",
+    feature = "criterion_0_6",
+    feature = "criterion_0_7",
+    feature = "criterion_0_8"
+)))]
+compile_error!("enable one of the features `criterion_0_4` to `criterion_0_8`");
+#[cfg(any(
+    all(
+        feature = "criterion_0_4",
+        any(
+            feature = "criterion_0_5",
+            feature = "criterion_0_6",
+            feature = "criterion_0_7",
+            feature = "criterion_0_8"
+        )
+    ),
+    all(
+        feature = "criterion_0_5",
+        any(
+            feature = "criterion_0_6",
+            feature = "criterion_0_7",
+            feature = "criterion_0_8"
+        )
+    ),
+    all(
+        feature = "criterion_0_6",
+        any(feature = "criterion_0_7", feature = "criterion_0_8")
+    ),
+    all(feature = "criterion_0_7", feature = "criterion_0_8")
+))]
+compile_error!(
+    "enable only one of the features `criterion_0_4` to `criterion_0_8`; \
+     disable the default features to enable another than `criterion_0_5`"
+);
+
+#[doc(hidden)]
+pub use criterion as __criterion;
+
+use clock::{ClockSource, ClockStart, Start};
+use criterion::async_executor::AsyncExecutor;
 use criterion::measurement::{Measurement, ValueFormatter, WallTime};
 use criterion::Throughput;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use units::{
+    ascii_unit, checked_amount, denominator_prefix, format_cycles, prefixed_unit, scale_per_unit,
+    throughput_amount, unit_per,
+};
+
+// the measurement of wall times `InvertedThroughput` wraps
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+type Inner = criterion::measurement::WallTime;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+type Inner = wasm::PerformanceNow;
 
 /// The custom measurement printing inverted throughputs instead of the throughputs
 ///
 /// Specify it as custom measurement in your benchmarks like
 /// `Criterion::default().with_measurement(InvertedThroughput::new())`
-pub struct InvertedThroughput(WallTime);
+pub struct InvertedThroughput {
+    inner: Inner,
+    clock: ClockSource,
+    inversion: Inversion,
+    // the results before the benchmarks to run the hooks with the updated ones when dropped
+    #[cfg(feature = "serde")]
+    hooks: Option<hooks::Snapshot>,
+}
+
+// the options and the state to invert throughputs, shared by measurements of this crate
+// wrapping another measurement
+pub(crate) struct Inversion {
+    machine_output: MachineOutput,
+    // applied to the amounts of throughputs
+    throughput_multiplier: f64,
+    ascii_units: bool,
+    precision: Option<Precision>,
+    missing_throughput: MissingThroughput,
+    plots_per_unit: bool,
+    prefixed_denominator: bool,
+    denom_formatter: Option<Arc<dyn denominator::DenomFormatter>>,
+    // the duration of media in an element or byte
+    media: Option<Duration>,
+    // the number of threads running the routine, multiplying values per element or byte
+    threads: u32,
+    // the fixed overhead of an iteration subtracted from values before inversion
+    overhead: f64,
+    // whether to annotate the point estimates with the values without the overhead
+    report_raw: bool,
+    // the frequency of the CPU in GHz to annotate the point estimates with cycles
+    frequency: Option<f64>,
+    // throughput of the benchmark being reported, used by `scale_for_machines` and plots
+    reported_throughput: Mutex<Option<Throughput>>,
+    // what criterion printed for the benchmark being reported, used to warn missing throughputs
+    reported: Mutex<Reported>,
+    // the `time` line and the throughputs formatted since, to order the bounds
+    bounds: Mutex<Bounds>,
+    // whether an invalid throughput of the benchmark being reported was warned
+    warned_throughput: Mutex<bool>,
+}
+
+// the indices in the `time` line of the bounds criterion formats as throughputs, in the
+// order it formats them: the console prints `[upper point lower]` of times, then the HTML
+// report `[upper lower point]`
+const THROUGHPUT_CALLS: [usize; 6] = [2, 1, 0, 2, 0, 1];
+
+// the index of the point estimate in the `time` line
+const POINT: usize = 1;
+
+#[derive(Debug, Default)]
+struct Bounds {
+    // the lower bound, the point estimate and the upper bound of the `time` line
+    time_line: Vec<f64>,
+    // the number of throughputs formatted since the `time` line
+    throughputs: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reported {
+    Nothing,
+    Time,
+    Throughput,
+}
+
+/// The options of [`InvertedThroughput`] set by its builder methods,
+/// except [`InvertedThroughput::denom_formatter`], [`InvertedThroughput::subtract_overhead`] and
+/// [`InvertedThroughput::subtract_async_overhead`]
+///
+/// With the `serde` feature, it can be stored and loaded, missing fields defaulting
+/// to the defaults of `InvertedThroughput`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Config {
+    /// See [`InvertedThroughput::with_clock`]
+    pub clock: ClockSource,
+    /// See [`InvertedThroughput::machine_output`]
+    pub machine_output: MachineOutput,
+    /// See [`InvertedThroughput::throughput_multiplier`]
+    pub throughput_multiplier: f64,
+    /// See [`InvertedThroughput::ascii_units`]
+    pub ascii_units: bool,
+    /// See [`InvertedThroughput::precision`]
+    pub precision: Option<Precision>,
+    /// See [`InvertedThroughput::missing_throughput`]
+    pub missing_throughput: MissingThroughput,
+    /// See [`InvertedThroughput::plots_per_unit`]
+    pub plots_per_unit: bool,
+    /// See [`InvertedThroughput::prefixed_denominator`]
+    pub prefixed_denominator: bool,
+    /// See [`InvertedThroughput::per_media_seconds`]
+    pub per_media_seconds: Option<Duration>,
+    /// See [`InvertedThroughput::normalize_threads`]
+    pub normalize_threads: u32,
+    /// See [`InvertedThroughput::annotate_cycles`]
+    pub annotate_cycles: Option<f64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        InvertedThroughput::new().config()
+    }
+}
+
+/// The format of values for machines (e.g. CSV output or `--output-format bencher`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MachineOutput {
+    /// Raw measured times in nanoseconds, same as `WallTime`
+    #[default]
+    Raw,
+    /// Times per element or byte in nanoseconds, with units like `ns/elem`
+    ///
+    /// The throughput is taken from the one criterion formatted last for the running benchmark.
+    /// If the benchmark has no throughput, or the console output does not format it
+    /// (e.g. with `--output-format bencher`), raw times are emitted instead.
+    PerUnit,
+    /// Approximate cycles per element or byte at the frequency of
+    /// [`InvertedThroughput::annotate_cycles`], with units like `cycles/elem`
+    ///
+    /// Without the frequency, for times per second of media, or for values other than times,
+    /// values are emitted as with [`MachineOutput::PerUnit`].
+    CyclesPerUnit,
+}
+
+/// The behavior for benchmarks without throughput
+///
+/// Criterion prints no `thrpt` line if a benchmark group has no throughput,
+/// so a forgotten `g.throughput(...)` goes unnoticed by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MissingThroughput {
+    /// Same as criterion, print only times
+    #[default]
+    Silent,
+    /// Print times per call of the routine like `ns/call`, as if `Throughput::Elements(1)`
+    /// were set
+    ///
+    /// Criterion prints no `thrpt` line without throughput, so the `time` line is printed
+    /// per call. Machine output with [`MachineOutput::PerUnit`] is also per call.
+    PerCall,
+    /// Same as [`MissingThroughput::PerCall`], and warn benchmarks without throughput
+    /// on the standard error
+    PerCallWarn,
+}
+
+/// The precision of printed throughputs
+///
+/// By default, criterion prints values with 5 significant digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Precision {
+    /// The number of significant digits, e.g. `68.8 ns/elem` with 3
+    SignificantDigits(usize),
+    /// The number of digits after the decimal point, e.g. `68.85 ns/elem` with 2
+    Decimals(usize),
+}
+
+impl Precision {
+    /// Formats `value` with the precision
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Precision::SignificantDigits(digits) => {
+                let digits = digits.max(1) as i32;
+                let magnitude = |value: f64| {
+                    if value == 0.0 || !value.is_finite() {
+                        0
+                    } else {
+                        value.abs().log10().floor() as i32
+                    }
+                };
+                let round = |magnitude: i32| {
+                    let factor = 10f64.powi(magnitude + 1 - digits);
+                    (value / factor).round() * factor
+                };
+                // rounding may carry to the next magnitude, e.g. 99.96 to 100.0
+                let magnitude = magnitude(round(magnitude(value)));
+                let decimals = (digits - 1 - magnitude).max(0) as usize;
+                format!("{:.*}", decimals, round(magnitude))
+            }
+            Precision::Decimals(decimals) => format!("{:.*}", decimals, value),
+        }
+    }
+}
+
+// defines the builder methods setting the options of `Inversion` in the field `inversion`
+macro_rules! inversion_options {
+    () => {
+        /// Sets the format of values for machines
+        pub fn machine_output(mut self, machine_output: $crate::MachineOutput) -> Self {
+            self.inversion.machine_output = machine_output;
+            self
+        }
+
+        /// Sets the multiplier applied to the amounts of throughputs
+        ///
+        /// It is useful for fractional amounts which `Throughput` cannot express, e.g.
+        /// `Throughput::Elements(100)` with `throughput_multiplier(3.5)` for 100 inputs of
+        /// 3.5 tokens in average prints values per token.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `multiplier` is not a positive finite number.
+        pub fn throughput_multiplier(mut self, multiplier: f64) -> Self {
+            assert!(
+                multiplier.is_finite() && multiplier > 0.0,
+                "the throughput multiplier must be a positive finite number: {}",
+                multiplier
+            );
+            self.inversion.throughput_multiplier = multiplier;
+            self
+        }
+
+        /// Sets whether to print units in ASCII only, like `us/elem` instead of `µs/elem`
+        pub fn ascii_units(mut self, ascii_units: bool) -> Self {
+            self.inversion.ascii_units = ascii_units;
+            self
+        }
+
+        /// Sets the precision of printed throughputs
+        pub fn precision(mut self, precision: $crate::Precision) -> Self {
+            self.inversion.precision = Some(precision);
+            self
+        }
+
+        /// Sets the behavior for benchmarks without throughput
+        pub fn missing_throughput(mut self, missing_throughput: $crate::MissingThroughput) -> Self {
+            self.inversion.missing_throughput = missing_throughput;
+            self
+        }
+
+        /// Sets whether to scale values per element or byte in plots and HTML reports,
+        /// like `Average Time (ns/elem)`
+        ///
+        /// The throughput is taken from the one criterion formatted last for the running
+        /// benchmark, so that plots are per iteration with `--output-format bencher`.
+        /// The summary plots of a group are scaled with the throughput of its last benchmark,
+        /// so they are correct only if the benchmarks of the group share a throughput.
+        pub fn plots_per_unit(mut self, plots_per_unit: bool) -> Self {
+            self.inversion.plots_per_unit = plots_per_unit;
+            self
+        }
+
+        /// Sets whether to prefix the element or byte with the magnitude of the throughput,
+        /// like `2.1 µs/Kelem` instead of `2.1 ns/elem` for `Throughput::Elements(10_000)`
+        ///
+        /// Bytes are prefixed in binary like `KiB`, and decimal bytes like `KB`, as criterion does.
+        pub fn prefixed_denominator(mut self, prefixed_denominator: bool) -> Self {
+            self.inversion.prefixed_denominator = prefixed_denominator;
+            self
+        }
+
+        /// Sets the formatter of the denominators for domain-specific units like `ns/voxel`
+        ///
+        /// It overrides the labels of elements, the prefixes of denominators and
+        /// the seconds of media.
+        pub fn denom_formatter(
+            mut self,
+            formatter: impl $crate::denominator::DenomFormatter + 'static,
+        ) -> Self {
+            self.inversion.denom_formatter = Some(std::sync::Arc::new(formatter));
+            self
+        }
+
+        /// Prints times per the product of the factors of `spec`, like `ns/(row·col)`
+        ///
+        /// The amounts of throughputs are replaced by the product (see
+        /// [`InvertedSpec`]($crate::denominator::InvertedSpec)).
+        pub fn per(self, spec: $crate::denominator::InvertedSpec) -> Self {
+            self.denom_formatter(spec)
+        }
+
+        /// Prints values per second of media, like `3.2 ms/s-audio` for audio processing
+        /// 1 s of audio in 3.2 ms, where an element or byte of throughputs is `media` long
+        ///
+        /// The media is labeled by [`denominator::label_elements`]($crate::denominator::label_elements),
+        /// `media` by default. Values for machines with [`MachineOutput::PerUnit`]($crate::MachineOutput::PerUnit)
+        /// are also per second of media.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `media` is zero.
+        pub fn per_media_seconds(mut self, media: std::time::Duration) -> Self {
+            assert!(!media.is_zero(), "the duration of media must not be zero");
+            self.inversion.media = Some(media);
+            self
+        }
+
+        /// Sets the number of threads the routine keeps busy, multiplying values per element
+        /// or byte by it like `core·ns/elem` (`core*ns/elem` with ASCII units)
+        ///
+        /// It makes the costs of parallel and serial implementations comparable. To measure
+        /// the CPU time instead of assuming it, use [`WallAndCpuTime`]($crate::cpu_time::WallAndCpuTime).
+        ///
+        /// # Panics
+        ///
+        /// Panics if `threads` is zero.
+        pub fn normalize_threads(mut self, threads: u32) -> Self {
+            assert!(threads > 0, "the number of threads must be positive");
+            self.inversion.threads = threads;
+            self
+        }
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use inversion_options;
 
 impl InvertedThroughput {
     /// Returns a new `InvertedThroughput`
     pub fn new() -> Self {
-        InvertedThroughput(WallTime)
+        InvertedThroughput {
+            inner: Inner {},
+            clock: ClockSource::default(),
+            inversion: Inversion::new(),
+            #[cfg(feature = "serde")]
+            hooks: None,
+        }
+    }
+
+    /// Returns an `InvertedThroughput` printing times per element as milliseconds per frame,
+    /// like `16.600 ms/frame`
+    pub fn per_frame() -> Self {
+        Self::new().denom_formatter(denominator::FixedUnit::new("ms", "frame"))
+    }
+
+    /// Returns an `InvertedThroughput` printing times per element as nanoseconds per pixel,
+    /// like `8.0054 ns/pixel`
+    pub fn per_pixel() -> Self {
+        Self::new().denom_formatter(denominator::FixedUnit::new("ns", "pixel"))
+    }
+
+    /// Returns an `InvertedThroughput` printing times per element as nanoseconds per sample,
+    /// like `20.833 ns/sample`
+    pub fn per_sample() -> Self {
+        Self::new().denom_formatter(denominator::FixedUnit::new("ns", "sample"))
+    }
+
+    /// Sets the clock to measure times with
+    ///
+    /// # Panics
+    ///
+    /// Panics if the clock is not supported on this platform
+    /// (see [`ClockSource::is_supported`]).
+    pub fn with_clock(mut self, clock: ClockSource) -> Self {
+        assert!(
+            clock.is_supported(),
+            "{:?} is not supported on this platform",
+            clock
+        );
+        self.clock = clock;
+        self
+    }
+
+    /// Runs the [`hooks`] with the results of the benchmarks measured with it when it is
+    /// dropped with the `Criterion` of the group
+    ///
+    /// `output_directory` is the one passed to `Criterion::output_directory` or
+    /// [`results::criterion_home`]. The results there are read here, to tell them apart
+    /// from the ones stored by the benchmarks. The hooks do not run if they cannot be read.
+    #[cfg(feature = "serde")]
+    pub fn run_hooks(mut self, output_directory: &std::path::Path) -> Self {
+        match hooks::Snapshot::capture(output_directory) {
+            Ok(snapshot) => self.hooks = Some(snapshot),
+            Err(e) => eprintln!("failed to read the results to run hooks with: {}", e),
+        }
+        self
+    }
+
+    /// Measures the time of an iteration of `routine`, e.g. an empty closure, and subtracts
+    /// it from times per iteration before inverting them
+    ///
+    /// It removes the constant overhead of calling the routine which dominates times per
+    /// element of small inputs. Times per element or byte are marked like `ns/elem net`,
+    /// and clamped to zero. The `time` line is not adjusted.
+    pub fn subtract_overhead<O>(mut self, routine: impl FnMut() -> O) -> Self {
+        self.inversion.overhead = measure_overhead(&self.inner, routine);
+        self
+    }
+
+    /// Measures the time of an iteration awaiting `routine()` in `executor`, as
+    /// `Bencher::to_async` runs routines, and subtracts it like
+    /// [`InvertedThroughput::subtract_overhead`]
+    ///
+    /// Calibrate with the empty task of the routines, e.g.
+    /// `|| async { tokio::spawn(async {}).await }` for routines spawning a task per iteration,
+    /// to remove the scheduling overhead dominating times per element of small inputs.
+    /// The point estimates are annotated with the times before subtracting it, like
+    /// `12.340 ns/elem net (raw 52.340 ns/elem)`.
+    pub fn subtract_async_overhead<E, R, F>(mut self, executor: E, routine: R) -> Self
+    where
+        E: AsyncExecutor,
+        R: FnMut() -> F,
+        F: Future,
+    {
+        self.inversion.overhead = measure_async_overhead(&self.inner, executor, routine);
+        self.inversion.report_raw = true;
+        self
+    }
+
+    /// Annotates the point estimates of times per element or byte with the approximate cycles
+    /// at `ghz` GHz, like `68.381 ns/elem (~205 cycles @ 3.0 GHz)`
+    ///
+    /// Use [`clock::cpu_frequency_ghz`] to detect the frequency. The cycles are approximate,
+    /// as the frequency changes with the load and the temperature. Times per second of media
+    /// and custom denominators are not annotated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ghz` is not a positive finite number.
+    pub fn annotate_cycles(mut self, ghz: f64) -> Self {
+        assert!(
+            ghz.is_finite() && ghz > 0.0,
+            "the frequency must be a positive finite number: {}",
+            ghz
+        );
+        self.inversion.frequency = Some(ghz);
+        self
+    }
+
+    inversion_options!();
+
+    /// Returns an `InvertedThroughput` with the options in `config`
+    ///
+    /// # Panics
+    ///
+    /// Panics if an option is invalid, like the builder methods.
+    pub fn from_config(config: Config) -> Self {
+        let measurement = InvertedThroughput::new()
+            .with_clock(config.clock)
+            .machine_output(config.machine_output)
+            .throughput_multiplier(config.throughput_multiplier)
+            .ascii_units(config.ascii_units)
+            .missing_throughput(config.missing_throughput)
+            .plots_per_unit(config.plots_per_unit)
+            .prefixed_denominator(config.prefixed_denominator)
+            .normalize_threads(config.normalize_threads);
+        let measurement = match config.per_media_seconds {
+            Some(media) => measurement.per_media_seconds(media),
+            None => measurement,
+        };
+        let measurement = match config.annotate_cycles {
+            Some(ghz) => measurement.annotate_cycles(ghz),
+            None => measurement,
+        };
+        match config.precision {
+            Some(precision) => measurement.precision(precision),
+            None => measurement,
+        }
+    }
+
+    /// Returns the options of the `InvertedThroughput`
+    pub fn config(&self) -> Config {
+        Config {
+            clock: self.clock,
+            machine_output: self.inversion.machine_output,
+            throughput_multiplier: self.inversion.throughput_multiplier,
+            ascii_units: self.inversion.ascii_units,
+            precision: self.inversion.precision,
+            missing_throughput: self.inversion.missing_throughput,
+            plots_per_unit: self.inversion.plots_per_unit,
+            prefixed_denominator: self.inversion.prefixed_denominator,
+            per_media_seconds: self.inversion.media,
+            normalize_threads: self.inversion.threads,
+            annotate_cycles: self.inversion.frequency,
+        }
     }
 }
 
@@ -71,24 +660,43 @@ impl Default for InvertedThroughput {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Drop for InvertedThroughput {
+    fn drop(&mut self) {
+        let Some(snapshot) = &self.hooks else {
+            return;
+        };
+        if let Err(e) = snapshot.run_updated() {
+            eprintln!("failed to run hooks: {}", e);
+        }
+    }
+}
+
 impl Measurement for InvertedThroughput {
-    type Intermediate = <WallTime as Measurement>::Intermediate;
-    type Value = <WallTime as Measurement>::Value;
+    type Intermediate = ClockStart<<Inner as Measurement>::Intermediate>;
+    type Value = Duration;
     fn start(&self) -> Self::Intermediate {
-        self.0.start()
+        self.inversion.start();
+        match self.clock.now() {
+            Some(nanos) => ClockStart(Start::Nanos(nanos)),
+            None => ClockStart(Start::Inner(self.inner.start())),
+        }
     }
     fn end(&self, i: Self::Intermediate) -> Self::Value {
-        self.0.end(i)
+        match i.0 {
+            Start::Nanos(nanos) => clock::elapsed(self.clock, nanos),
+            Start::Inner(i) => self.inner.end(i),
+        }
     }
 
     fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
-        self.0.add(v1, v2)
+        self.inner.add(v1, v2)
     }
     fn zero(&self) -> Self::Value {
-        self.0.zero()
+        self.inner.zero()
     }
     fn to_f64(&self, val: &Self::Value) -> f64 {
-        self.0.to_f64(val)
+        self.inner.to_f64(val)
     }
 
     fn formatter(&self) -> &dyn ValueFormatter {
@@ -96,36 +704,54 @@ impl Measurement for InvertedThroughput {
     }
 }
 
-impl InvertedThroughput {
-    fn time_per_unit(&self, units: f64, typical_value: f64, values: &mut [f64]) -> &'static str {
-        let typical_time = typical_value / units;
-        for val in &mut *values {
-            let val_per_unit = *val / units;
-            *val = val_per_unit;
+// the mean time of an iteration of `routine` measured by `inner` in nanoseconds
+fn measure_overhead<O>(inner: &Inner, mut routine: impl FnMut() -> O) -> f64 {
+    // double the iterations until the clock is negligible
+    let mut iters: u64 = 1;
+    loop {
+        let start = inner.start();
+        for _ in 0..iters {
+            std::hint::black_box(routine());
+        }
+        let elapsed = inner.end(start);
+        if elapsed >= Duration::from_millis(10) || iters >= 1 << 30 {
+            return elapsed.as_nanos() as f64 / iters as f64;
         }
-        self.0.formatter().scale_values(typical_time, values)
+        iters *= 2;
     }
+}
 
-    fn static_denom(&self, time_denom: &str, unit_denom: &str) -> &'static str {
-        match (unit_denom, time_denom) {
-            ("byte", "ps") => "ps/byte",
-            ("byte", "ns") => "ns/byte",
-            ("byte", "µs") => "µs/byte",
-            ("byte", "ms") => "ms/byte",
-            ("byte", "s") => "s/byte",
-            ("elem", "ps") => "ps/elem",
-            ("elem", "ns") => "ns/elem",
-            ("elem", "µs") => "µs/elem",
-            ("elem", "ms") => "ms/elem",
-            ("elem", "s") => "s/elem",
-            _ => "UNEXPECTED",
+// the mean time of an iteration awaiting `routine()` in `executor` measured by `inner`
+// in nanoseconds
+fn measure_async_overhead<F: Future>(
+    inner: &Inner,
+    executor: impl AsyncExecutor,
+    mut routine: impl FnMut() -> F,
+) -> f64 {
+    // double the iterations until the clock is negligible
+    let mut iters: u64 = 1;
+    loop {
+        let elapsed = executor.block_on(async {
+            let start = inner.start();
+            for _ in 0..iters {
+                std::hint::black_box(routine().await);
+            }
+            inner.end(start)
+        });
+        if elapsed >= Duration::from_millis(10) || iters >= 1 << 30 {
+            return elapsed.as_nanos() as f64 / iters as f64;
         }
+        iters *= 2;
     }
 }
 
-impl ValueFormatter for InvertedThroughput {
+/// The formatter of times in nanoseconds, printing throughputs in the format
+/// `[time]/[element or byte]`
+pub(crate) struct TimeFormatter;
+
+impl ValueFormatter for TimeFormatter {
     fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
-        self.0.formatter().scale_values(typical_value, values)
+        WallTime.formatter().scale_values(typical_value, values)
     }
 
     fn scale_throughputs(
@@ -134,118 +760,469 @@ impl ValueFormatter for InvertedThroughput {
         throughput: &Throughput,
         values: &mut [f64],
     ) -> &'static str {
-        let (t_val, t_unit) = match *throughput {
-            Throughput::Bytes(v) => (v as f64, "byte"),
-            Throughput::BytesDecimal(v) => (v as f64, "byte"),
-            Throughput::Elements(v) => (v as f64, "elem"),
-        };
-        self.static_denom(self.time_per_unit(t_val, typical_value, values), t_unit)
+        scale_per_unit(WallTime.formatter(), typical_value, throughput, values)
     }
 
     fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
-        self.0.formatter().scale_for_machines(values)
+        WallTime.formatter().scale_for_machines(values)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use test_case::test_case;
+// returns `value_denom` per second of media like `ms/s-audio`
+fn media_unit(value_denom: &str) -> &'static str {
+    let media = denominator::elements_label().unwrap_or("media");
+    denominator::intern(format!("{}/s-{}", value_denom, media))
+}
 
-    #[derive(Clone)]
-    struct Data {
-        typical_value: f64,
-        values: Vec<f64>,
-        throughput: Throughput,
+// returns the static string of the unit of a throughput deserialized, `elem` or `byte`
+#[cfg(feature = "serde")]
+pub(crate) fn static_unit(unit: &str) -> Result<&'static str, String> {
+    match unit {
+        "elem" => Ok("elem"),
+        "byte" => Ok("byte"),
+        "bit" => Ok("bit"),
+        _ => Err(format!("unknown unit `{}`", unit)),
     }
+}
 
-    impl Data {
-        fn new(typical_value: f64, throughput: Throughput) -> Self {
-            let mut values: Vec<f64> = vec![];
-            for x in -5..5 {
-                // generate values in 90%-110% times of typical value
-                values.push(typical_value * (1f64 - (x as f64 * 0.02)))
-            }
-            Self {
-                typical_value,
-                values,
-                throughput,
-            }
+impl Inversion {
+    pub(crate) fn new() -> Self {
+        Inversion {
+            machine_output: MachineOutput::default(),
+            throughput_multiplier: 1.0,
+            ascii_units: false,
+            precision: None,
+            missing_throughput: MissingThroughput::default(),
+            plots_per_unit: false,
+            prefixed_denominator: false,
+            denom_formatter: None,
+            media: None,
+            threads: 1,
+            overhead: 0.0,
+            report_raw: false,
+            frequency: None,
+            reported_throughput: Mutex::new(None),
+            reported: Mutex::new(Reported::Nothing),
+            bounds: Mutex::new(Bounds::default()),
+            warned_throughput: Mutex::new(false),
         }
     }
 
-    enum Unit {
-        Element,
-        Byte,
-        ByteDecimal,
+    // called when a new measurement begins
+    pub(crate) fn start(&self) {
+        if self.tracks_throughput() {
+            // forget the throughput of the previous benchmark
+            *self.reported_throughput.lock().unwrap() = None;
+        }
+        self.warn_missing_throughput();
+        *self.bounds.lock().unwrap() = Bounds::default();
+        *self.warned_throughput.lock().unwrap() = false;
+    }
+
+    // whether to record the throughput of the benchmark being reported
+    fn tracks_throughput(&self) -> bool {
+        self.machine_output != MachineOutput::Raw || self.plots_per_unit
+    }
+
+    fn per_call(&self) -> bool {
+        self.missing_throughput != MissingThroughput::Silent
+    }
+
+    // returns whether the last reported benchmark had no throughput, and forgets it
+    fn missed_throughput(&self) -> bool {
+        let mut reported = self.reported.lock().unwrap();
+        let missed = *reported == Reported::Time;
+        *reported = Reported::Nothing;
+        missed
+    }
+
+    fn warn_missing_throughput(&self) {
+        if self.missing_throughput == MissingThroughput::PerCallWarn && self.missed_throughput() {
+            eprintln!(
+                "warning: the last benchmark has no throughput, so its times are per call; \
+                 set it with `BenchmarkGroup::throughput`"
+            );
+        }
     }
 
-    fn normalize_time(denom: &str, value: f64) -> f64 {
-        if denom.to_string().starts_with("ps") {
-            value / 1e12
-        } else if denom.to_string().starts_with("ns") {
-            value / 1e9
-        } else if denom.to_string().starts_with("µs") {
-            value / 1e6
-        } else if denom.to_string().starts_with("ms") {
-            value / 1e3
-        } else if denom.to_string().starts_with("s") {
-            value
+    fn unit(&self, unit: &'static str) -> &'static str {
+        if self.ascii_units {
+            ascii_unit(unit)
         } else {
-            panic!("Unexpected denom for time: {}", denom)
+            unit
+        }
+    }
+
+    pub(crate) fn format_throughput(
+        &self,
+        base: &dyn ValueFormatter,
+        throughput: &Throughput,
+        value: f64,
+    ) -> String {
+        *self.reported.lock().unwrap() = Reported::Throughput;
+        let (bound, index) = self.ordered_bound(value);
+        let mut values = [bound];
+        let unit = self.scale_throughputs(base, bound, throughput, &mut values);
+        let mut formatted = format!("{:>6} {}", self.format_number(values[0]), unit);
+        if index != Some(POINT) {
+            return formatted;
+        }
+        if let Some(raw) = self.raw(base, throughput, bound) {
+            formatted = format!("{} {}", formatted, raw);
+        }
+        match self.cycles(throughput, bound) {
+            Some(cycles) => format!("{} {}", formatted, cycles),
+            None => formatted,
         }
     }
 
-    fn normalize_amount(denom: &str, value: f64) -> f64 {
-        if denom.to_string().starts_with("G") {
-            value * 1e9
-        } else if denom.to_string().starts_with("M") {
-            value * 1e6
-        } else if denom.to_string().starts_with("K") {
-            value * 1e3
+    fn format_number(&self, value: f64) -> String {
+        match self.precision {
+            Some(precision) => precision.format(value),
+            None => export::short(value),
+        }
+    }
+
+    // the annotation of the point estimate with the value without subtracting the overhead,
+    // like `(raw 52.340 ns/elem)`
+    fn raw(
+        &self,
+        base: &dyn ValueFormatter,
+        throughput: &Throughput,
+        value: f64,
+    ) -> Option<String> {
+        if !self.report_raw || self.overhead == 0.0 {
+            return None;
+        }
+        checked_amount(throughput).ok()?;
+        let mut values = [value];
+        let typical_value = self.normalize_threads(value, &mut values);
+        let unit = self.invert(base, typical_value, throughput, &mut values);
+        Some(format!(
+            "(raw {} {})",
+            self.format_number(values[0]),
+            self.mark_with(unit, false)
+        ))
+    }
+
+    // the annotation of the point estimate with cycles, like `(~205 cycles @ 3.0 GHz)`
+    fn cycles(&self, throughput: &Throughput, value: f64) -> Option<String> {
+        let ghz = self.frequency?;
+        if self.media.is_some() || self.denom_formatter.is_some() {
+            return None;
+        }
+        let (amount, _) = checked_amount(throughput).ok()?;
+        let mut values = [value];
+        self.subtract_overhead(value, &mut values);
+        self.normalize_threads(value, &mut values);
+        let cycles = values[0] / (amount * self.throughput_multiplier) * ghz;
+        Some(format!(
+            "(~{} cycles @ {:.1} GHz)",
+            format_cycles(cycles),
+            ghz
+        ))
+    }
+
+    // criterion formats the upper bound of times as the lower bound of throughputs, which is
+    // the upper bound of inverted throughputs, so the bounds are swapped back to print
+    // `[lower point upper]` in ascending order
+    //
+    // Returns the value to print, and its index in the `time` line if it is a bound of it.
+    // The bounds are told apart by the position of the call, so that equal bounds are swapped
+    // correctly.
+    fn ordered_bound(&self, value: f64) -> (f64, Option<usize>) {
+        let mut bounds = self.bounds.lock().unwrap();
+        let call = bounds.throughputs;
+        bounds.throughputs += 1;
+        let time_line = match bounds.time_line[..] {
+            [lower, point, upper] => [lower, point, upper],
+            _ => return (value, None),
+        };
+        let formatted = match THROUGHPUT_CALLS.get(call) {
+            Some(&index) if time_line[index] == value => Some(index),
+            // called in another order, e.g. by cargo-criterion
+            _ => [POINT, 0, 2]
+                .into_iter()
+                .find(|&index| time_line[index] == value),
+        };
+        match formatted {
+            Some(index) => (time_line[2 - index], Some(2 - index)),
+            None => (value, None),
+        }
+    }
+
+    // criterion formats the time of a benchmark with it, before its throughput if any
+    pub(crate) fn format_value(&self, base: &dyn ValueFormatter, value: f64) -> String {
+        {
+            let mut reported = self.reported.lock().unwrap();
+            if *reported == Reported::Nothing {
+                *reported = Reported::Time;
+            }
+        }
+        {
+            let mut bounds = self.bounds.lock().unwrap();
+            if bounds.time_line.len() < 3 {
+                bounds.time_line.push(value);
+            }
+        }
+        let mut values = [value];
+        let unit = self.scale_values(base, value, &mut values);
+        format!("{:>6} {}", export::short(values[0]), unit)
+    }
+
+    pub(crate) fn scale_values(
+        &self,
+        base: &dyn ValueFormatter,
+        typical_value: f64,
+        values: &mut [f64],
+    ) -> &'static str {
+        if self.plots_per_unit {
+            // plots are drawn after the throughput is formatted for the console
+            let reported_throughput = self.reported_throughput.lock().unwrap().clone();
+            if let Some(throughput) = reported_throughput {
+                return self.scale_throughputs(base, typical_value, &throughput, values);
+            }
+        }
+        let unit = base.scale_values(typical_value, values);
+        if self.per_call() {
+            self.unit(unit_per(unit, "call"))
         } else {
-            value
+            self.unit(unit)
         }
     }
 
-    fn assert_nearly_eq(a: Vec<f64>, b: Vec<f64>) {
-        assert_eq!(a.len(), b.len(), "left: {:?} !~= right: {:?}", a, b);
-        for i in 0..a.len() {
-            assert_ne!(a[i].abs(), 0.0, "left: {:?} !~= right: {:?}", a, b);
-            assert!(
-                (a[i] - b[i]).abs() < a[i].abs() * 1e-12,
-                "left: {:?} !~= right: {:?}",
-                a,
-                b
-            )
+    pub(crate) fn scale_throughputs(
+        &self,
+        base: &dyn ValueFormatter,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        if let Err(e) = checked_amount(throughput) {
+            let mut warned = self.warned_throughput.lock().unwrap();
+            if !*warned {
+                eprintln!("warning: {}, so times are printed per call", e);
+                *warned = true;
+            }
+            let unit = base.scale_values(typical_value, values);
+            return self.unit(unit_per(unit, "call"));
+        }
+        if self.tracks_throughput() {
+            *self.reported_throughput.lock().unwrap() = Some(throughput.clone());
         }
+        let typical_value = self.subtract_overhead(typical_value, values);
+        let typical_value = self.normalize_threads(typical_value, values);
+        let unit = self.invert(base, typical_value, throughput, values);
+        self.mark(unit)
     }
 
-    fn assert_nearly_inversion(a: Vec<f64>, b: Vec<f64>) {
-        assert_eq!(
-            a.len(),
-            b.len(),
-            "left: {:?} <not inversion> right: {:?}",
-            a,
-            b
-        );
-        for i in 0..a.len() {
-            assert_ne!(
-                a[i].abs(),
-                0.0,
-                "left: {:?} <not inversion> right: {:?}",
-                a,
-                b
+    // subtracts the overhead from `values` and returns the typical value without it
+    fn subtract_overhead(&self, typical_value: f64, values: &mut [f64]) -> f64 {
+        if self.overhead == 0.0 {
+            return typical_value;
+        }
+        for val in &mut *values {
+            *val = (*val - self.overhead).max(0.0);
+        }
+        (typical_value - self.overhead).max(0.0)
+    }
+
+    // multiplies `values` by the threads and returns the typical value multiplied
+    fn normalize_threads(&self, typical_value: f64, values: &mut [f64]) -> f64 {
+        let threads = self.threads as f64;
+        for val in &mut *values {
+            *val *= threads;
+        }
+        typical_value * threads
+    }
+
+    // marks `unit` adjusted by the options, like `core·ns/elem net`
+    fn mark(&self, unit: &'static str) -> &'static str {
+        self.mark_with(unit, self.overhead != 0.0)
+    }
+
+    // marks `unit` adjusted by the threads, and as `net` of the overhead if `net`
+    fn mark_with(&self, unit: &'static str, net: bool) -> &'static str {
+        let cores = match (self.threads, self.ascii_units) {
+            (1, _) => "",
+            (_, false) => "core·",
+            (_, true) => "core*",
+        };
+        let net = if net { " net" } else { "" };
+        if cores.is_empty() && net.is_empty() {
+            unit
+        } else {
+            denominator::intern(format!("{}{}{}", cores, unit, net))
+        }
+    }
+
+    fn invert(
+        &self,
+        base: &dyn ValueFormatter,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        // the time unit of `settings` applied in the current thread
+        let fixed = settings::fixed_time_unit().map(|time_unit| {
+            denominator::FixedUnit::new(time_unit, denominator::elements_label().unwrap_or("elem"))
+        });
+        let formatter = match &self.denom_formatter {
+            Some(formatter) => Some(&**formatter),
+            None => fixed
+                .as_ref()
+                .map(|fixed| fixed as &dyn denominator::DenomFormatter),
+        };
+        if let Some(formatter) = formatter {
+            let amount = formatter
+                .amount(throughput)
+                .unwrap_or_else(|| throughput_amount(throughput).0);
+            for val in &mut *values {
+                *val /= amount * self.throughput_multiplier;
+            }
+            let time_unit = base.scale_values(
+                typical_value / (amount * self.throughput_multiplier),
+                values,
             );
-            assert!(
-                (a[i] * b[i] - 1f64).abs() < 0.075,
-                "left: {:?} <not inversion> right: {:?} (index: {}, abs(sub(1.0)): {})",
-                a,
-                b,
-                i,
-                (a[i] * b[i] - 1f64).abs(),
-            )
+            let (unit, factor) = formatter.format(time_unit, throughput);
+            for val in &mut *values {
+                *val *= factor;
+            }
+            return self.unit(denominator::intern(unit));
+        }
+        if let Some(media) = self.media {
+            let (amount, _) = throughput_amount(throughput);
+            let seconds = amount * media.as_secs_f64() * self.throughput_multiplier;
+            for val in &mut *values {
+                *val /= seconds;
+            }
+            let time_unit = base.scale_values(typical_value / seconds, values);
+            return self.unit(media_unit(time_unit));
+        }
+        let (factor, prefix) = if self.prefixed_denominator {
+            denominator_prefix(throughput)
+        } else {
+            (1.0, "")
+        };
+        for val in &mut *values {
+            *val *= factor / self.throughput_multiplier;
+        }
+        let unit = scale_per_unit(
+            base,
+            typical_value * factor / self.throughput_multiplier,
+            throughput,
+            values,
+        );
+        self.unit(prefixed_unit(unit, throughput, prefix))
+    }
+
+    pub(crate) fn scale_for_machines(
+        &self,
+        base: &dyn ValueFormatter,
+        values: &mut [f64],
+    ) -> &'static str {
+        let unit = self.unit(base.scale_for_machines(values));
+        if self.machine_output == MachineOutput::Raw {
+            return unit;
         }
+        match &*self.reported_throughput.lock().unwrap() {
+            Some(throughput) => {
+                let (t_val, t_unit) = throughput_amount(throughput);
+                self.subtract_overhead(0.0, values);
+                self.normalize_threads(0.0, values);
+                let seconds = self.media.map_or(1.0, |media| media.as_secs_f64());
+                for val in &mut *values {
+                    *val /= t_val * seconds * self.throughput_multiplier;
+                }
+                let unit = match (self.machine_output, self.frequency) {
+                    _ if self.media.is_some() => media_unit(unit),
+                    (MachineOutput::CyclesPerUnit, Some(ghz)) if unit == "ns" => {
+                        for val in &mut *values {
+                            *val *= ghz;
+                        }
+                        unit_per("cycles", t_unit)
+                    }
+                    _ => unit_per(unit, t_unit),
+                };
+                self.mark(self.unit(unit))
+            }
+            None if self.per_call() => self.unit(unit_per(unit, "call")),
+            None => unit,
+        }
+    }
+}
+
+// clones the options, with the state of a new measurement
+impl Clone for Inversion {
+    fn clone(&self) -> Self {
+        let mut inversion = Inversion::new();
+        inversion.machine_output = self.machine_output;
+        inversion.throughput_multiplier = self.throughput_multiplier;
+        inversion.ascii_units = self.ascii_units;
+        inversion.precision = self.precision;
+        inversion.missing_throughput = self.missing_throughput;
+        inversion.plots_per_unit = self.plots_per_unit;
+        inversion.prefixed_denominator = self.prefixed_denominator;
+        inversion.denom_formatter = self.denom_formatter.clone();
+        inversion.media = self.media;
+        inversion.threads = self.threads;
+        inversion.overhead = self.overhead;
+        inversion.report_raw = self.report_raw;
+        inversion.frequency = self.frequency;
+        inversion
+    }
+}
+
+impl Drop for Inversion {
+    fn drop(&mut self) {
+        self.warn_missing_throughput();
+    }
+}
+
+impl ValueFormatter for InvertedThroughput {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(self.inner.formatter(), value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(self.inner.formatter(), throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(self.inner.formatter(), typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(self.inner.formatter(), typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_for_machines(self.inner.formatter(), values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{
+        assert_nearly_eq, assert_nearly_inversion, normalize_amount, normalize_time, thrpt_line,
+        Data,
+    };
+    use test_case::test_case;
+
+    enum Unit {
+        Element,
+        Byte,
+        ByteDecimal,
     }
 
     #[test_case(Unit::Element, 1, 1e3 ; "test 1 elements")]
@@ -254,6 +1231,7 @@ mod tests {
     #[test_case(Unit::ByteDecimal, 1000, 1e12 ; "test 1000 bytesdecimal")]
     #[test_case(Unit::Element, 123, 1.234e15 ; "test 123 elements")]
     #[test_case(Unit::Byte, 123_456_789, 1.234e6 ; "test big bytes")]
+    #[test_case(Unit::Element, 1_000_000_000, 123.4e3 ; "test femtoseconds")]
     fn test_invert_throughput(unit: Unit, amount: u64, typical_value: f64) {
         // generate test case
         let throughput = match unit {
@@ -265,7 +1243,7 @@ mod tests {
 
         // measurements
         let default_measure = WallTime;
-        let our_measure = InvertedThroughput(WallTime);
+        let our_measure = InvertedThroughput::new();
 
         // compare value with intert throughput
         let mut values_by_default = data.values.clone();
@@ -299,10 +1277,455 @@ mod tests {
             .map(|x| normalize_time(unit_inverted_throughputs, *x))
             .collect();
 
-        assert_nearly_eq(
-            expected_inverted_throuputs,
-            normalized_inverted_throuputs.clone(),
+        assert_nearly_eq(&expected_inverted_throuputs, &normalized_inverted_throuputs);
+        assert_nearly_inversion(
+            &normalized_inverted_throuputs,
+            &normalized_default_throuputs,
+        );
+    }
+
+    #[test]
+    fn test_machine_output_raw() {
+        let measure = InvertedThroughput::new();
+        let mut values = vec![1234.0, 2345.0];
+        measure.scale_throughputs(1234.0, &Throughput::Elements(10), &mut values.clone());
+        let unit = measure.scale_for_machines(&mut values);
+        assert_eq!(unit, "ns");
+        assert_eq!(values, vec![1234.0, 2345.0]);
+    }
+
+    #[test_case(Throughput::Elements(10), "ns/elem" ; "test elements")]
+    #[test_case(Throughput::Bytes(10), "ns/byte" ; "test bytes")]
+    #[test_case(Throughput::BytesDecimal(10), "ns/byte" ; "test bytesdecimal")]
+    fn test_machine_output_per_unit(throughput: Throughput, expected_unit: &str) {
+        let measure = InvertedThroughput::new().machine_output(MachineOutput::PerUnit);
+        let mut values = vec![1234.0, 2345.0];
+        measure.scale_throughputs(1234.0, &throughput, &mut values.clone());
+        let unit = measure.scale_for_machines(&mut values);
+        assert_eq!(unit, expected_unit);
+        assert_nearly_eq(&values, &[123.4, 234.5]);
+    }
+
+    #[test]
+    fn test_machine_output_cycles_per_unit() {
+        let measure = InvertedThroughput::new()
+            .machine_output(MachineOutput::CyclesPerUnit)
+            .annotate_cycles(3.0);
+        let mut values = vec![1234.0, 2345.0];
+        measure.scale_throughputs(1234.0, &Throughput::Elements(10), &mut values.clone());
+        let unit = measure.scale_for_machines(&mut values);
+        assert_eq!(unit, "cycles/elem");
+        assert_nearly_eq(&values, &[370.2, 703.5]);
+
+        // without the frequency, times per element are emitted
+        let measure = InvertedThroughput::new().machine_output(MachineOutput::CyclesPerUnit);
+        let mut values = vec![1234.0, 2345.0];
+        measure.scale_throughputs(1234.0, &Throughput::Elements(10), &mut values.clone());
+        assert_eq!(measure.scale_for_machines(&mut values), "ns/elem");
+        assert_nearly_eq(&values, &[123.4, 234.5]);
+    }
+
+    #[test]
+    fn test_machine_output_per_unit_without_throughput() {
+        let measure = InvertedThroughput::new().machine_output(MachineOutput::PerUnit);
+        let mut values = vec![1234.0, 2345.0];
+        measure.scale_throughputs(1234.0, &Throughput::Elements(10), &mut values.clone());
+        // the next benchmark without throughput starts
+        measure.end(measure.start());
+        let unit = measure.scale_for_machines(&mut values);
+        assert_eq!(unit, "ns");
+        assert_eq!(values, vec![1234.0, 2345.0]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_with_clock() {
+        let measure = InvertedThroughput::new().with_clock(ClockSource::MonotonicRaw);
+        let start = measure.start();
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(measure.end(start) >= Duration::from_millis(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_unsupported_clock() {
+        #[cfg(windows)]
+        let clock = ClockSource::Boottime;
+        #[cfg(not(windows))]
+        let clock = ClockSource::PerformanceCounter;
+        let _ = InvertedThroughput::new().with_clock(clock);
+    }
+
+    #[test]
+    fn test_throughput_multiplier() {
+        let measure = InvertedThroughput::new()
+            .machine_output(MachineOutput::PerUnit)
+            .throughput_multiplier(2.5);
+        let mut values = vec![1000.0, 2000.0];
+        let unit = measure.scale_throughputs(1000.0, &Throughput::Elements(10), &mut values);
+        assert_eq!(unit, "ns/elem");
+        assert_nearly_eq(&values, &[40.0, 80.0]);
+
+        let mut values = vec![1000.0, 2000.0];
+        let unit = measure.scale_for_machines(&mut values);
+        assert_eq!(unit, "ns/elem");
+        assert_nearly_eq(&values, &[40.0, 80.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_throughput_multiplier() {
+        let _ = InvertedThroughput::new().throughput_multiplier(0.0);
+    }
+
+    #[test_case(Throughput::Elements(0) ; "test zero elements")]
+    #[test_case(Throughput::Bytes(0) ; "test zero bytes")]
+    #[test_case(Throughput::Elements(u64::MAX) ; "test inexact elements")]
+    fn test_invalid_throughput(throughput: Throughput) {
+        assert!(checked_amount(&throughput).is_err());
+        let measure = InvertedThroughput::new().machine_output(MachineOutput::PerUnit);
+        assert_eq!(
+            measure.format_throughput(&throughput, 1234.0),
+            "1.2340 µs/call"
+        );
+        let mut values = [1234.0];
+        assert_eq!(measure.scale_for_machines(&mut values), "ns");
+        assert_eq!(values, [1234.0]);
+        assert!(checked_amount(&Throughput::Elements(1 << 53)).is_ok());
+    }
+
+    #[test]
+    fn test_ascii_units() {
+        let measure = InvertedThroughput::new().ascii_units(true);
+        let mut values = vec![2e4];
+        assert_eq!(measure.scale_values(2e4, &mut values), "us");
+        let mut values = vec![2e4];
+        assert_eq!(
+            measure.scale_throughputs(2e4, &Throughput::Elements(10), &mut values),
+            "us/elem"
         );
-        assert_nearly_inversion(normalized_inverted_throuputs, normalized_default_throuputs);
+        let mut values = vec![2e4];
+        assert_eq!(
+            measure.scale_throughputs(2e4, &Throughput::Bytes(10), &mut values),
+            "us/byte"
+        );
+    }
+
+    #[test_case(Precision::SignificantDigits(3), 688.49, "  68.8 ns/elem" ; "test significant digits")]
+    #[test_case(Precision::SignificantDigits(3), 6884.9, "   688 ns/elem" ; "test significant digits of integer")]
+    #[test_case(Precision::SignificantDigits(2), 1.2345, "   120 ps/elem" ; "test significant digits of picoseconds")]
+    #[test_case(Precision::SignificantDigits(3), 999.6, "   100 ns/elem" ; "test significant digits with carry")]
+    #[test_case(Precision::Decimals(1), 688.49, "  68.8 ns/elem" ; "test decimals")]
+    fn test_precision(precision: Precision, value: f64, expected: &str) {
+        let measure = InvertedThroughput::new().precision(precision);
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), value),
+            expected
+        );
+    }
+
+    #[test_case(Throughput::Elements(1_000_000_000), 123.4e3, "123.40 fs/elem" ; "test femtoseconds")]
+    #[test_case(Throughput::Bytes(1_000_000), 123.4e3, "123.40 ps/byte" ; "test picoseconds")]
+    fn test_sub_picosecond(throughput: Throughput, value: f64, expected: &str) {
+        let measure = InvertedThroughput::new();
+        assert_eq!(measure.format_throughput(&throughput, value), expected);
+    }
+
+    #[test_case(MissingThroughput::Silent, "1.2340 µs", "ns" ; "test silent")]
+    #[test_case(MissingThroughput::PerCall, "1.2340 µs/call", "ns/call" ; "test per call")]
+    #[test_case(MissingThroughput::PerCallWarn, "1.2340 µs/call", "ns/call" ; "test per call with warning")]
+    fn test_missing_throughput(
+        missing_throughput: MissingThroughput,
+        expected_value: &str,
+        expected_unit: &str,
+    ) {
+        let measure = InvertedThroughput::new()
+            .missing_throughput(missing_throughput)
+            .machine_output(MachineOutput::PerUnit);
+        assert_eq!(measure.format_value(1234.0), expected_value);
+        let mut values = vec![1234.0];
+        assert_eq!(measure.scale_for_machines(&mut values), expected_unit);
+        assert_eq!(values, vec![1234.0]);
+    }
+
+    #[test]
+    fn test_plots_per_unit() {
+        let measure = InvertedThroughput::new().plots_per_unit(true);
+        // the time is formatted before the throughput
+        assert_eq!(measure.format_value(1234.0), "1.2340 µs");
+        measure.format_throughput(&Throughput::Elements(10), 1234.0);
+        let mut values = vec![1234.0, 2345.0];
+        assert_eq!(measure.scale_values(1234.0, &mut values), "ns/elem");
+        assert_nearly_eq(&values, &[123.4, 234.5]);
+
+        // the next benchmark
+        measure.end(measure.start());
+        let mut values = vec![1234.0];
+        assert_eq!(measure.scale_values(1234.0, &mut values), "µs");
+    }
+
+    #[test_case(Throughput::Elements(999), 2097.9, "2.1000 ns/elem" ; "test no prefix")]
+    #[test_case(Throughput::Elements(10_000), 2.1e4, "2.1000 µs/Kelem" ; "test kilo elements")]
+    #[test_case(Throughput::Elements(3_000_000), 6.3e6, "2.1000 ms/Melem" ; "test mega elements")]
+    #[test_case(Throughput::Bytes(2048), 2.1e3, "1.0500 µs/KiB" ; "test kibibytes")]
+    #[test_case(Throughput::BytesDecimal(2000), 2.1e3, "1.0500 µs/KB" ; "test kilobytes")]
+    fn test_prefixed_denominator(throughput: Throughput, value: f64, expected: &str) {
+        let measure = InvertedThroughput::new().prefixed_denominator(true);
+        assert_eq!(measure.format_throughput(&throughput, value), expected);
+    }
+
+    #[test]
+    fn test_per_media_seconds() {
+        // an element is 10 ms of media
+        let measure = InvertedThroughput::new()
+            .machine_output(MachineOutput::PerUnit)
+            .per_media_seconds(Duration::from_millis(10));
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(2), 64e3),
+            "3.2000 ms/s-media"
+        );
+        let mut values = [64e3];
+        assert_eq!(measure.scale_for_machines(&mut values), "ns/s-media");
+        assert_nearly_eq(&values, &[3.2e6]);
+
+        let _label = denominator::label_elements("audio");
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(2), 64e3),
+            "3.2000 ms/s-audio"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_media_seconds() {
+        let _ = InvertedThroughput::new().per_media_seconds(Duration::ZERO);
+    }
+
+    #[test]
+    fn test_normalize_threads() {
+        let measure = InvertedThroughput::new()
+            .machine_output(MachineOutput::PerUnit)
+            .normalize_threads(8);
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 1234.0),
+            "987.20 core·ns/elem"
+        );
+        let mut values = [1234.0];
+        assert_eq!(measure.scale_for_machines(&mut values), "core·ns/elem");
+        assert_nearly_eq(&values, &[987.2]);
+
+        let measure = measure.ascii_units(true);
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 1234.0),
+            "987.20 core*ns/elem"
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let config = Config {
+            machine_output: MachineOutput::PerUnit,
+            throughput_multiplier: 2.0,
+            precision: Some(Precision::Decimals(1)),
+            per_media_seconds: Some(Duration::from_millis(10)),
+            normalize_threads: 4,
+            annotate_cycles: Some(3.0),
+            ..Config::default()
+        };
+        assert_eq!(
+            InvertedThroughput::from_config(config.clone()).config(),
+            config
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_config() {
+        let config: Config = serde_json::from_str(
+            r#"{"machine_output": "PerUnit", "precision": {"SignificantDigits": 3}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                machine_output: MachineOutput::PerUnit,
+                precision: Some(Precision::SignificantDigits(3)),
+                ..Config::default()
+            }
+        );
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn test_subtract_overhead() {
+        let measure = InvertedThroughput::new()
+            .machine_output(MachineOutput::PerUnit)
+            .subtract_overhead(|| ());
+        assert!(measure.inversion.overhead.is_finite() && measure.inversion.overhead >= 0.0);
+
+        let mut measure = measure;
+        measure.inversion.overhead = 200.0;
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 1234.0),
+            "103.40 ns/elem net"
+        );
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 100.0),
+            "0.0000 ps/elem net"
+        );
+        // the time line is not adjusted
+        assert_eq!(measure.format_value(1234.0), "1.2340 µs");
+        let mut values = [1234.0];
+        assert_eq!(measure.scale_for_machines(&mut values), "ns/elem net");
+        assert_nearly_eq(&values, &[103.4]);
+    }
+
+    #[test]
+    fn test_bound_order() {
+        let measure = InvertedThroughput::new();
+        let (lower, point, upper) = (2858.1, 2872.0, 2891.7);
+        let throughput = Throughput::Elements(42);
+        let time_line: Vec<String> = [lower, point, upper]
+            .iter()
+            .map(|&value| measure.format_value(value))
+            .collect();
+        assert_eq!(time_line, ["2.8581 µs", "2.8720 µs", "2.8917 µs"]);
+
+        // the console formats the throughputs in the order of the bounds of throughputs
+        let thrpt_line: Vec<String> = [upper, point, lower]
+            .iter()
+            .map(|&value| measure.format_throughput(&throughput, value))
+            .collect();
+        let inverted: Vec<String> = [lower, point, upper]
+            .iter()
+            .map(|&value| format!("{:>6} ns/elem", export::short(value / 42.0)))
+            .collect();
+        assert_eq!(thrpt_line, inverted);
+        assert_eq!(
+            thrpt_line,
+            ["68.050 ns/elem", "68.381 ns/elem", "68.850 ns/elem"]
+        );
+
+        // the HTML report formats the upper bound, the lower bound and the point estimate
+        assert_eq!(measure.format_throughput(&throughput, upper), inverted[0]);
+        assert_eq!(measure.format_throughput(&throughput, lower), inverted[2]);
+        assert_eq!(measure.format_throughput(&throughput, point), inverted[1]);
+
+        // the next benchmark
+        measure.end(measure.start());
+        assert_eq!(measure.format_throughput(&throughput, upper), inverted[2]);
+    }
+
+    #[test_case([2858.1, 2858.1, 2891.7], ["68.050 ns/elem", "68.050 ns/elem", "68.850 ns/elem"] ; "equal lower bound")]
+    #[test_case([2858.1, 2891.7, 2891.7], ["68.050 ns/elem", "68.850 ns/elem", "68.850 ns/elem"] ; "equal upper bound")]
+    #[test_case([2872.0, 2872.0, 2872.0], ["68.381 ns/elem", "68.381 ns/elem", "68.381 ns/elem"] ; "equal bounds")]
+    fn test_equal_bounds(bounds: [f64; 3], expected: [&str; 3]) {
+        let measure = InvertedThroughput::new().annotate_cycles(3.0);
+        let throughput = Throughput::Elements(42);
+        let thrpt_line = thrpt_line(&measure, &throughput, bounds);
+        // only the point estimate is annotated
+        assert!(thrpt_line[1].ends_with("cycles @ 3.0 GHz)"));
+        assert!(!thrpt_line[0].contains("cycles") && !thrpt_line[2].contains("cycles"));
+        assert_eq!(
+            thrpt_line.map(|value| value.split(" (").next().unwrap().to_string()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_annotate_cycles() {
+        let measure = InvertedThroughput::new().annotate_cycles(3.0);
+        let (lower, point, upper) = (2858.1, 2872.0, 2891.7);
+        let throughput = Throughput::Elements(42);
+        for value in [lower, point, upper] {
+            measure.format_value(value);
+        }
+        let thrpt_line: Vec<String> = [upper, point, lower]
+            .iter()
+            .map(|&value| measure.format_throughput(&throughput, value))
+            .collect();
+        assert_eq!(
+            thrpt_line,
+            [
+                "68.050 ns/elem",
+                "68.381 ns/elem (~205 cycles @ 3.0 GHz)",
+                "68.850 ns/elem"
+            ]
+        );
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(1000), point),
+            "2.8720 ns/elem (~8.6 cycles @ 3.0 GHz)"
+        );
+    }
+
+    #[test]
+    fn test_subtract_async_overhead() {
+        struct NoopWaker;
+
+        impl std::task::Wake for NoopWaker {
+            fn wake(self: std::sync::Arc<Self>) {}
+        }
+
+        // polls the future until it is ready
+        struct Executor;
+
+        impl AsyncExecutor for Executor {
+            fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+                let mut future = std::pin::pin!(future);
+                let waker = std::task::Waker::from(std::sync::Arc::new(NoopWaker));
+                let mut cx = std::task::Context::from_waker(&waker);
+                loop {
+                    if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                        return output;
+                    }
+                }
+            }
+        }
+
+        let measure = InvertedThroughput::new().subtract_async_overhead(Executor, || async {});
+        assert!(measure.inversion.overhead.is_finite() && measure.inversion.overhead >= 0.0);
+
+        let mut measure = measure;
+        measure.inversion.overhead = 200.0;
+        let (lower, point, upper) = (1134.0, 1234.0, 1334.0);
+        let throughput = Throughput::Elements(10);
+        for value in [lower, point, upper] {
+            measure.format_value(value);
+        }
+        let thrpt_line: Vec<String> = [upper, point, lower]
+            .iter()
+            .map(|&value| measure.format_throughput(&throughput, value))
+            .collect();
+        assert_eq!(
+            thrpt_line,
+            [
+                "93.400 ns/elem net",
+                "103.40 ns/elem net (raw 123.40 ns/elem)",
+                "113.40 ns/elem net"
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_frequency() {
+        InvertedThroughput::new().annotate_cycles(0.0);
+    }
+
+    #[test]
+    fn test_missed_throughput() {
+        let measure = InvertedThroughput::new().missing_throughput(MissingThroughput::PerCall);
+        assert!(!measure.inversion.missed_throughput());
+
+        measure.format_value(1234.0);
+        assert!(measure.inversion.missed_throughput());
+        assert!(!measure.inversion.missed_throughput());
+
+        measure.format_value(1234.0);
+        measure.format_throughput(&Throughput::Elements(10), 1234.0);
+        // criterion formats times again in verbose mode
+        measure.format_value(1234.0);
+        assert!(!measure.inversion.missed_throughput());
     }
 }
@@ -0,0 +1,78 @@
+/// Macro used to define a benchmark group measured by [`InvertedThroughput`](crate::InvertedThroughput)
+///
+/// It is a wrapper of [`criterion::criterion_group!`] which injects
+/// `Criterion::default().with_measurement(InvertedThroughput::new())` as the config,
+/// so the boilerplate in your benchmarks can be written like:
+///
+/// ```
+/// use criterion::{criterion_main, measurement::Measurement, Criterion};
+/// use criterion_inverted_throughput::inverted_criterion_group;
+///
+/// fn bench_foo<M: Measurement>(c: &mut Criterion<M>) {
+///     // add benchmarks here
+/// }
+///
+/// inverted_criterion_group!(name = Foo; targets = bench_foo);
+/// criterion_main!(Foo);
+/// ```
+///
+/// The measurement and the base `Criterion` config can be given optionally (in this order):
+///
+/// ```
+/// use criterion::{criterion_main, measurement::Measurement, Criterion};
+/// use criterion_inverted_throughput::{inverted_criterion_group, InvertedThroughput};
+///
+/// fn bench_foo<M: Measurement>(c: &mut Criterion<M>) {
+///     // add benchmarks here
+/// }
+///
+/// inverted_criterion_group!(
+///     name = Foo;
+///     measurement = InvertedThroughput::new();
+///     config = Criterion::default().sample_size(10);
+///     targets = bench_foo
+/// );
+/// criterion_main!(Foo);
+/// ```
+///
+/// The short form `inverted_criterion_group!(Foo, bench_foo, bench_bar)` is also supported.
+#[macro_export]
+macro_rules! inverted_criterion_group {
+    (name = $name:ident; measurement = $measurement:expr; config = $config:expr; targets = $( $target:path ),+ $(,)*) => {
+        $crate::__criterion::criterion_group!{
+            name = $name;
+            config = $config.with_measurement($measurement);
+            targets = $( $target ),+
+        }
+    };
+    (name = $name:ident; measurement = $measurement:expr; targets = $( $target:path ),+ $(,)*) => {
+        $crate::inverted_criterion_group!{
+            name = $name;
+            measurement = $measurement;
+            config = $crate::__criterion::Criterion::default();
+            targets = $( $target ),+
+        }
+    };
+    (name = $name:ident; config = $config:expr; targets = $( $target:path ),+ $(,)*) => {
+        $crate::inverted_criterion_group!{
+            name = $name;
+            measurement = $crate::InvertedThroughput::new();
+            config = $config;
+            targets = $( $target ),+
+        }
+    };
+    (name = $name:ident; targets = $( $target:path ),+ $(,)*) => {
+        $crate::inverted_criterion_group!{
+            name = $name;
+            measurement = $crate::InvertedThroughput::new();
+            config = $crate::__criterion::Criterion::default();
+            targets = $( $target ),+
+        }
+    };
+    ($name:ident, $( $target:path ),+ $(,)*) => {
+        $crate::inverted_criterion_group!{
+            name = $name;
+            targets = $( $target ),+
+        }
+    };
+}
@@ -0,0 +1,216 @@
+//! Formatting numbers of exported tables
+//!
+//! Markdown and CSV tables of [`export`](crate::export) and the table of
+//! `criterion-invert show` print numbers like criterion does, e.g. `12345.678`. A
+//! [`NumberFormat`] adds thousands separators, fixes the number of decimals, changes
+//! the decimal mark, and pads the values of Markdown tables to a fixed width, so that
+//! the tables are readable and their diffs across runs line up. Like
+//! [`label_elements`](crate::denominator::label_elements), it applies to the tables
+//! exported in the current thread until the returned guard is dropped:
+//!
+//! ```
+//! use criterion_inverted_throughput::numbers::NumberFormat;
+//!
+//! let _numbers = NumberFormat::new().thousands_separator(',').precision(2).apply();
+//! assert_eq!(NumberFormat::current().format(12345.678), "12,345.68");
+//! ```
+//!
+//! The bencher format and the Prometheus format are parsed by other tools, so they always
+//! print plain numbers.
+
+use crate::export::short;
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+thread_local! {
+    static NUMBER_FORMAT: Cell<NumberFormat> = const { Cell::new(NumberFormat::new()) };
+}
+
+/// The format of the numbers of exported tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    thousands_separator: Option<char>,
+    decimal_mark: char,
+    precision: Option<usize>,
+    width: Option<usize>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NumberFormat {
+    /// Returns the format printing numbers as criterion does
+    pub const fn new() -> Self {
+        NumberFormat {
+            thousands_separator: None,
+            decimal_mark: '.',
+            precision: None,
+            width: None,
+        }
+    }
+
+    /// Returns the format of continental Europe like `12.345,68`
+    pub const fn european() -> Self {
+        Self::new().thousands_separator('.').decimal_mark(',')
+    }
+
+    /// Separates thousands of the integer parts by `separator` like `12,345.678`
+    pub const fn thousands_separator(mut self, separator: char) -> Self {
+        self.thousands_separator = Some(separator);
+        self
+    }
+
+    /// Prints `mark` instead of `.` before the decimals
+    pub const fn decimal_mark(mut self, mark: char) -> Self {
+        self.decimal_mark = mark;
+        self
+    }
+
+    /// Prints `digits` decimals, instead of 5 significant digits in Markdown tables and
+    /// the shortest exact decimals in CSV
+    pub const fn precision(mut self, digits: usize) -> Self {
+        self.precision = Some(digits);
+        self
+    }
+
+    /// Pads the values of Markdown tables and `criterion-invert show`, with their units,
+    /// to at least `width` characters aligned to the right
+    pub const fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Applies the format to the tables exported in the current thread until the returned
+    /// guard is dropped
+    pub fn apply(self) -> NumberFormatGuard {
+        NumberFormatGuard {
+            previous: NUMBER_FORMAT.with(|current| current.replace(self)),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns the format applied in the current thread
+    pub fn current() -> Self {
+        NUMBER_FORMAT.with(Cell::get)
+    }
+
+    /// Formats `value` with the shortest exact decimals, or the fixed precision
+    pub fn format(&self, value: f64) -> String {
+        match self.precision {
+            Some(precision) => self.localize(&format!("{:.*}", precision, value)),
+            None => self.localize(&value.to_string()),
+        }
+    }
+
+    /// Formats `value` with 5 significant digits like criterion, or the fixed precision
+    pub fn format_short(&self, value: f64) -> String {
+        match self.precision {
+            Some(precision) => self.localize(&format!("{:.*}", precision, value)),
+            None => self.localize(&short(value)),
+        }
+    }
+
+    /// Formats the amount of a throughput, an integer, ignoring the precision
+    pub fn format_amount(&self, amount: f64) -> String {
+        self.localize(&amount.to_string())
+    }
+
+    /// Replaces the number at the start of `formatted`, like `12345.678` of
+    /// `12345.678 ns/elem`, with the separators and the decimal mark of the format
+    pub fn localize(&self, formatted: &str) -> String {
+        let sign = if formatted.starts_with('-') { 1 } else { 0 };
+        let (sign, rest) = formatted.split_at(sign);
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (integer, rest) = rest.split_at(digits);
+
+        let mut out = String::from(sign);
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                if let Some(separator) = self.thousands_separator {
+                    out.push(separator);
+                }
+            }
+            out.push(digit);
+        }
+        match rest.strip_prefix('.') {
+            Some(decimals) if !integer.is_empty() => {
+                out.push(self.decimal_mark);
+                out.push_str(decimals);
+            }
+            _ => out.push_str(rest),
+        }
+        out
+    }
+
+    /// Pads `cell` to the width of the format, aligned to the right
+    pub fn pad(&self, cell: &str) -> String {
+        format!("{:>1$}", cell, self.width.unwrap_or(0))
+    }
+}
+
+/// The guard applying a [`NumberFormat`] until it is dropped
+///
+/// The previous format is restored on drop, so guards can be nested.
+#[must_use = "the format is reset when the guard is dropped"]
+pub struct NumberFormatGuard {
+    previous: NumberFormat,
+    // the format is thread-local
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for NumberFormatGuard {
+    fn drop(&mut self) {
+        NUMBER_FORMAT.with(|format| format.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(NumberFormat::new(), 12345.678, "12345.678" ; "default")]
+    #[test_case(NumberFormat::new().thousands_separator(','), 1234567.5, "1,234,567.5" ; "thousands")]
+    #[test_case(NumberFormat::new().thousands_separator(','), 123.5, "123.5" ; "below thousand")]
+    #[test_case(NumberFormat::new().thousands_separator(' '), -1234.0, "-1 234" ; "negative")]
+    #[test_case(NumberFormat::european(), 12345.678, "12.345,678" ; "european")]
+    #[test_case(NumberFormat::new().precision(2), 0.125, "0.12" ; "precision")]
+    #[test_case(NumberFormat::new().thousands_separator(','), f64::NAN, "NaN" ; "nan")]
+    fn test_format(format: NumberFormat, value: f64, expected: &str) {
+        assert_eq!(format.format(value), expected);
+    }
+
+    #[test]
+    fn test_format_short() {
+        let format = NumberFormat::new().thousands_separator(',');
+        assert_eq!(format.format_short(12345.678), "12,346");
+        assert_eq!(format.format_short(1.5), "1.5000");
+        assert_eq!(format.precision(1).format_short(1.25), "1.2");
+        assert_eq!(format.precision(1).format_amount(1048576.0), "1,048,576");
+        assert_eq!(
+            NumberFormat::european().localize("1234.5 ns/elem"),
+            "1.234,5 ns/elem"
+        );
+        assert_eq!(format.width(8).pad("1.5 ns"), "  1.5 ns");
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(NumberFormat::current(), NumberFormat::new());
+        {
+            let _numbers = NumberFormat::european().apply();
+            assert_eq!(NumberFormat::current(), NumberFormat::european());
+            {
+                let _numbers = NumberFormat::new().width(10).apply();
+                assert_eq!(NumberFormat::current().pad("1"), "         1");
+            }
+            assert_eq!(NumberFormat::current(), NumberFormat::european());
+        }
+        assert_eq!(NumberFormat::current(), NumberFormat::new());
+    }
+}
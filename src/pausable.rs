@@ -0,0 +1,238 @@
+//! Times excluding paused regions
+//!
+//! Per-iteration work like cloning an input buffer inflates times per element, and
+//! `Bencher::iter_batched` cannot exclude work in the middle of the routine.
+//! [`pause`] returns a guard pausing the [`Stopwatch`]es of the current thread until it is
+//! dropped. [`PausableTime`] measures with a `Stopwatch`, so that paused regions in
+//! `Bencher::iter` are excluded:
+//!
+//! ```no_run
+//! use criterion::{Criterion, Throughput};
+//! use criterion_inverted_throughput::pausable::{pause, PausableTime};
+//!
+//! # let input = vec![3u32, 1, 2];
+//! let mut c = Criterion::default().with_measurement(PausableTime::new());
+//! let mut g = c.benchmark_group("sort");
+//! g.throughput(Throughput::Elements(input.len() as u64));
+//! g.bench_function("unstable", |b| {
+//!     b.iter(|| {
+//!         let mut input = {
+//!             let _paused = pause();
+//!             input.clone()
+//!         };
+//!         input.sort_unstable();
+//!         input
+//!     })
+//! });
+//! g.finish();
+//! ```
+//!
+//! With other measurements of times, use `Bencher::iter_custom` with [`Stopwatch`], or
+//! [`iter_excluding_setup`].
+//!
+//! Pausing and resuming read the clock, which costs some nanoseconds per pause not excluded.
+//! Pauses are per thread, so routines measured in a thread and paused in another
+//! (e.g. with async executors running tasks on a thread pool) are not paused.
+
+use crate::{inversion_options, Inversion, TimeFormatter};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{Bencher, Throughput};
+use std::cell::Cell;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // the total nanoseconds paused in the current thread
+    static PAUSED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The guard pausing the [`Stopwatch`]es of the current thread until it is dropped
+#[must_use = "the stopwatches are resumed when the guard is dropped"]
+pub struct Paused {
+    since: Instant,
+}
+
+impl Drop for Paused {
+    fn drop(&mut self) {
+        let nanos = self.since.elapsed().as_nanos() as u64;
+        PAUSED.with(|paused| paused.set(paused.get() + nanos));
+    }
+}
+
+/// Pauses the [`Stopwatch`]es of the current thread until the returned guard is dropped
+pub fn pause() -> Paused {
+    Paused {
+        since: Instant::now(),
+    }
+}
+
+fn paused_nanos() -> u64 {
+    PAUSED.with(Cell::get)
+}
+
+/// The wall time since it started, excluding the regions paused by [`pause`] in the
+/// current thread
+pub struct Stopwatch {
+    start: Instant,
+    paused: u64,
+}
+
+impl Stopwatch {
+    /// Returns a `Stopwatch` started now
+    pub fn start() -> Self {
+        Stopwatch {
+            paused: paused_nanos(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the elapsed time excluding the paused regions
+    pub fn elapsed(&self) -> Duration {
+        let elapsed = self.start.elapsed();
+        let paused = Duration::from_nanos(paused_nanos() - self.paused);
+        elapsed.saturating_sub(paused)
+    }
+}
+
+/// Runs `routine` on inputs returned by `setup` in [`Bencher::iter_custom`], excluding
+/// `setup` and dropping the outputs from the times
+///
+/// Unlike `Bencher::iter_batched`, the inputs are not stored in batches, so large inputs
+/// do not pressure the memory.
+pub fn iter_excluding_setup<M, I, O, S, R>(
+    bencher: &mut Bencher<'_, M>,
+    mut setup: S,
+    mut routine: R,
+) where
+    M: Measurement<Value = Duration>,
+    S: FnMut() -> I,
+    R: FnMut(I) -> O,
+{
+    bencher.iter_custom(|iters| {
+        let stopwatch = Stopwatch::start();
+        for _ in 0..iters {
+            let input = {
+                let _paused = pause();
+                setup()
+            };
+            let output = black_box(routine(black_box(input)));
+            let _paused = pause();
+            drop(output);
+        }
+        stopwatch.elapsed()
+    });
+}
+
+/// The custom measurement of wall times excluding the regions paused by [`pause`],
+/// printing throughputs like [`InvertedThroughput`](crate::InvertedThroughput)
+pub struct PausableTime {
+    inversion: Inversion,
+}
+
+impl PausableTime {
+    /// Returns a new `PausableTime`
+    pub fn new() -> Self {
+        PausableTime {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for PausableTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement for PausableTime {
+    type Intermediate = Stopwatch;
+    type Value = Duration;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        Stopwatch::start()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        i.elapsed()
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        *v1 + *v2
+    }
+    fn zero(&self) -> Self::Value {
+        Duration::ZERO
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        val.as_nanos() as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for PausableTime {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&TimeFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&TimeFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&TimeFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&TimeFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&TimeFormatter, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_pause() {
+        let measure = PausableTime::new();
+        let stopwatch = measure.start();
+        {
+            let _paused = pause();
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(measure.end(stopwatch) < Duration::from_millis(50));
+
+        let stopwatch = Stopwatch::start();
+        thread::sleep(Duration::from_millis(10));
+        assert!(stopwatch.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_nested_stopwatches() {
+        let outer = Stopwatch::start();
+        let inner = Stopwatch::start();
+        {
+            let _paused = pause();
+            thread::sleep(Duration::from_millis(20));
+        }
+        let (inner, outer) = (inner.elapsed(), outer.elapsed());
+        assert!(inner < Duration::from_millis(20));
+        assert!(outer < Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,386 @@
+//! Poll and wakeup counts of futures
+//!
+//! [`Polls`] and [`Wakeups`] measure how many times futures wrapped by [`instrument`] are
+//! polled and woken up, and print throughputs like `polls/elem` and `wakeups/elem`.
+//! They are useful to spot async pipelines yielding more than expected per element.
+//!
+//! Pass [`InstrumentedExecutor`] to `Bencher::to_async` to count the polls and the wakeups
+//! of the benchmark task (criterion's `async` feature is required):
+//!
+//! ```no_run
+//! use criterion::async_executor::FuturesExecutor;
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::polls::{InstrumentedExecutor, Polls};
+//!
+//! let mut c = Criterion::default().with_measurement(Polls::new());
+//! c.bench_function("channel", |b| {
+//!     b.to_async(InstrumentedExecutor(FuturesExecutor))
+//!         .iter(|| async { std::future::ready(42).await })
+//! });
+//! ```
+//!
+//! They take the options of [`InvertedThroughput`](crate::InvertedThroughput) like
+//! [`Polls::precision`].
+//!
+//! Criterion measures in the task, so the first poll of each sample is not counted.
+//! The counters are shared by all threads, so other instrumented futures polled while
+//! measuring are counted too.
+
+use crate::units::scale_per_unit;
+use crate::{inversion_options, Inversion};
+use criterion::async_executor::AsyncExecutor;
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+static POLLS: AtomicU64 = AtomicU64::new(0);
+static WAKEUPS: AtomicU64 = AtomicU64::new(0);
+
+/// The future counting the polls and the wakeups of the inner future
+pub struct Instrumented<F> {
+    inner: F,
+}
+
+/// Wraps `future` to count its polls and wakeups
+pub fn instrument<F: Future>(future: F) -> Instrumented<F> {
+    Instrumented { inner: future }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        POLLS.fetch_add(1, Ordering::Relaxed);
+        let waker = Waker::from(Arc::new(CountingWaker(cx.waker().clone())));
+        // SAFETY: `inner` is never moved out of the pinned `Instrumented`
+        let inner = unsafe { self.map_unchecked_mut(|s| &mut s.inner) };
+        inner.poll(&mut Context::from_waker(&waker))
+    }
+}
+
+// the waker counting wakeups of the inner waker
+struct CountingWaker(Waker);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        WAKEUPS.fetch_add(1, Ordering::Relaxed);
+        self.0.wake_by_ref();
+    }
+}
+
+/// The executor wrapping `E` to [`instrument`] the futures it runs
+pub struct InstrumentedExecutor<E>(pub E);
+
+impl<E: AsyncExecutor> AsyncExecutor for InstrumentedExecutor<E> {
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        self.0.block_on(instrument(future))
+    }
+}
+
+impl<E: AsyncExecutor> AsyncExecutor for &InstrumentedExecutor<E> {
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        self.0.block_on(instrument(future))
+    }
+}
+
+/// The custom measurement of the polls of [`instrument`]ed futures
+pub struct Polls {
+    inversion: Inversion,
+}
+
+impl Polls {
+    /// Returns a new `Polls`
+    pub fn new() -> Self {
+        Polls {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for Polls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement for Polls {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        POLLS.load(Ordering::SeqCst)
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        POLLS.load(Ordering::SeqCst) - i
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Polls {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&PollsFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&PollsFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&PollsFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&PollsFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&PollsFormatter, values)
+    }
+}
+
+/// The custom measurement of the wakeups of [`instrument`]ed futures
+pub struct Wakeups {
+    inversion: Inversion,
+}
+
+impl Wakeups {
+    /// Returns a new `Wakeups`
+    pub fn new() -> Self {
+        Wakeups {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for Wakeups {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement for Wakeups {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        WAKEUPS.load(Ordering::SeqCst)
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        WAKEUPS.load(Ordering::SeqCst) - i
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Wakeups {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&WakeupsFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&WakeupsFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&WakeupsFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&WakeupsFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&WakeupsFormatter, values)
+    }
+}
+
+/// The formatter of numbers of polls, printing throughputs in the format
+/// `polls/[element or byte]`
+pub struct PollsFormatter;
+
+impl ValueFormatter for PollsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "polls"
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "polls"
+    }
+}
+
+/// The formatter of numbers of wakeups, printing throughputs in the format
+/// `wakeups/[element or byte]`
+pub struct WakeupsFormatter;
+
+impl ValueFormatter for WakeupsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "wakeups"
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "wakeups"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::thrpt_line;
+
+    // yields `n` times, waking itself up
+    struct Yield(u32);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                return Poll::Ready(());
+            }
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // polls `future` until it is ready
+    struct Executor;
+
+    impl AsyncExecutor for Executor {
+        fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+            let waker = Waker::from(Arc::new(NoopWaker));
+            let mut future = Box::pin(future);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker))
+                {
+                    return output;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_instrument() {
+        let (polls, wakeups) = (Polls::new(), Wakeups::new());
+        let polls_start = polls.start();
+        let wakeups_start = wakeups.start();
+        InstrumentedExecutor(Executor).block_on(Yield(3));
+        assert_eq!(polls.end(polls_start), 4);
+        assert_eq!(wakeups.end(wakeups_start), 3);
+
+        // not instrumented
+        let polls_start = polls.start();
+        Executor.block_on(Yield(3));
+        assert_eq!(polls.end(polls_start), 0);
+    }
+
+    #[test]
+    fn test_throughputs() {
+        let mut values = [5.0];
+        let unit = PollsFormatter.scale_throughputs(5.0, &Throughput::Elements(10), &mut values);
+        assert_eq!(unit, "polls/elem");
+        assert_eq!(values, [0.5]);
+        let unit = WakeupsFormatter.scale_throughputs(5.0, &Throughput::Bytes(10), &mut values);
+        assert_eq!(unit, "wakeups/byte");
+    }
+
+    #[test]
+    fn test_bound_order() {
+        let throughput = Throughput::Elements(10);
+        assert_eq!(
+            thrpt_line(&Polls::new(), &throughput, [40.0, 40.0, 60.0]),
+            [
+                "4.0000 polls/elem",
+                "4.0000 polls/elem",
+                "6.0000 polls/elem"
+            ]
+        );
+        assert_eq!(
+            thrpt_line(&Wakeups::new(), &throughput, [30.0, 35.0, 40.0]),
+            [
+                "3.0000 wakeups/elem",
+                "3.5000 wakeups/elem",
+                "4.0000 wakeups/elem"
+            ]
+        );
+    }
+}
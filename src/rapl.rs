@@ -0,0 +1,242 @@
+//! Energy measurement with Linux RAPL (Running Average Power Limit)
+//!
+//! [`RaplEnergy`] measures the energy consumed by a RAPL domain and prints throughputs
+//! in the format `[energy]/[element or byte]` like `nJ/elem` or `µJ/byte`.
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::rapl::RaplEnergy;
+//!
+//! let c = Criterion::default().with_measurement(RaplEnergy::new().unwrap());
+//! ```
+//!
+//! It takes the options of [`InvertedThroughput`](crate::InvertedThroughput) like
+//! [`RaplEnergy::precision`].
+//!
+//! The counters are read from `/sys/class/powercap`, which is usually readable by root only.
+//! The energy is consumed by the whole domain (e.g. the CPU package), so other processes
+//! affect the results.
+
+use crate::units::scale_per_unit;
+use crate::{inversion_options, Inversion};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The RAPL zone of the package 0
+pub const PACKAGE_ZONE: &str = "/sys/class/powercap/intel-rapl:0";
+
+/// The custom measurement of the energy consumed by a RAPL zone, in nanojoules
+pub struct RaplEnergy {
+    energy_path: PathBuf,
+    // the counter wraps around to zero after this value
+    max_energy_range_uj: u64,
+    inversion: Inversion,
+}
+
+impl RaplEnergy {
+    /// Returns a `RaplEnergy` measuring the package 0
+    pub fn new() -> io::Result<Self> {
+        Self::with_zone(PACKAGE_ZONE)
+    }
+
+    /// Returns a `RaplEnergy` measuring the zone in `zone` like
+    /// `/sys/class/powercap/intel-rapl:0:0`
+    ///
+    /// Returns an error if the energy counter of the zone is not readable.
+    pub fn with_zone(zone: impl AsRef<Path>) -> io::Result<Self> {
+        let zone = zone.as_ref();
+        let energy_path = zone.join("energy_uj");
+        read_counter(&energy_path)?;
+        Ok(RaplEnergy {
+            energy_path,
+            max_energy_range_uj: read_counter(&zone.join("max_energy_range_uj"))?,
+            inversion: Inversion::new(),
+        })
+    }
+
+    inversion_options!();
+
+    fn read(&self) -> u64 {
+        read_counter(&self.energy_path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", self.energy_path.display(), e))
+    }
+}
+
+fn read_counter(path: &Path) -> io::Result<u64> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// the microjoules consumed from the reading `start` to `end` of a counter wrapping from
+// `max_energy_range_uj` to 0
+fn consumed_uj(start: u64, end: u64, max_energy_range_uj: u64) -> u64 {
+    if end >= start {
+        end - start
+    } else {
+        end + (max_energy_range_uj + 1) - start
+    }
+}
+
+impl Measurement for RaplEnergy {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        self.read()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        consumed_uj(i, self.read(), self.max_energy_range_uj) * 1000
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for RaplEnergy {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&EnergyFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&EnergyFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&EnergyFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&EnergyFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&EnergyFormatter, values)
+    }
+}
+
+/// The formatter of energies in nanojoules, printing throughputs in the format
+/// `[energy]/[element or byte]`
+pub struct EnergyFormatter;
+
+impl ValueFormatter for EnergyFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        let (factor, unit) = if typical_value < 1.0 {
+            (1e3, "pJ")
+        } else if typical_value < 1e3 {
+            (1.0, "nJ")
+        } else if typical_value < 1e6 {
+            (1e-3, "µJ")
+        } else if typical_value < 1e9 {
+            (1e-6, "mJ")
+        } else {
+            (1e-9, "J")
+        };
+        for val in values {
+            *val *= factor;
+        }
+        unit
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "nJ"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::thrpt_line;
+    use test_case::test_case;
+
+    #[test_case(0.5, 500.0, "pJ" ; "test picojoules")]
+    #[test_case(500.0, 500.0, "nJ" ; "test nanojoules")]
+    #[test_case(5e4, 50.0, "µJ" ; "test microjoules")]
+    #[test_case(5e7, 50.0, "mJ" ; "test millijoules")]
+    #[test_case(5e9, 5.0, "J" ; "test joules")]
+    fn test_scale_values(value: f64, expected_value: f64, expected_unit: &str) {
+        let mut values = [value];
+        let unit = EnergyFormatter.scale_values(value, &mut values);
+        assert_eq!(unit, expected_unit);
+        assert!((values[0] - expected_value).abs() < expected_value * 1e-12);
+    }
+
+    #[test_case(Throughput::Elements(1000), 10.0, "nJ/elem" ; "test elements")]
+    #[test_case(Throughput::Bytes(10), 1.0, "µJ/byte" ; "test bytes")]
+    #[test_case(Throughput::Elements(1_000_000), 10.0, "pJ/elem" ; "test many elements")]
+    fn test_scale_throughputs(throughput: Throughput, expected_value: f64, expected_unit: &str) {
+        let mut values = [1e4];
+        let unit = EnergyFormatter.scale_throughputs(1e4, &throughput, &mut values);
+        assert_eq!(unit, expected_unit);
+        assert!((values[0] - expected_value).abs() < expected_value * 1e-12);
+    }
+
+    #[test]
+    fn test_bound_order() {
+        let dir = std::env::temp_dir().join("criterion-inverted-throughput-test-rapl-bounds");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("energy_uj"), "0\n").unwrap();
+        fs::write(dir.join("max_energy_range_uj"), "100\n").unwrap();
+        let measure = RaplEnergy::with_zone(&dir).unwrap();
+        assert_eq!(
+            thrpt_line(&measure, &Throughput::Elements(10), [1e3, 2e3, 4e3]),
+            ["100.00 nJ/elem", "200.00 nJ/elem", "400.00 nJ/elem"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test_case(10, 30, 20 ; "test no wrap")]
+    #[test_case(100, 0, 1 ; "test wrap to zero")]
+    #[test_case(90, 5, 16 ; "test wrap")]
+    #[test_case(0, 100, 100 ; "test full range")]
+    fn test_consumed_uj(start: u64, end: u64, expected: u64) {
+        assert_eq!(consumed_uj(start, end, 100), expected);
+    }
+
+    #[test]
+    fn test_wrapping_counter() {
+        let dir = std::env::temp_dir().join("criterion-inverted-throughput-test-rapl");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("energy_uj"), "90\n").unwrap();
+        fs::write(dir.join("max_energy_range_uj"), "100\n").unwrap();
+        let measure = RaplEnergy::with_zone(&dir).unwrap();
+        let start = measure.start();
+        fs::write(dir.join("energy_uj"), "5\n").unwrap();
+        // 90 to 100, 0 and 5
+        assert_eq!(measure.end(start), 16_000);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,589 @@
+//! Reading benchmark results stored by criterion
+//!
+//! Criterion stores the estimates of each benchmark in
+//! `target/criterion/<benchmark>/<baseline>/estimates.json` with raw times only.
+//! This module loads them with the throughput settings stored next to them
+//! (`benchmark.json`), so the inverted throughputs can be recomputed after a run.
+//! Reading and writing the files requires the `serde` feature.
+//!
+//! `write_inverted_estimates` writes the estimates per element or byte to
+//! [`INVERTED_ESTIMATES_FILE`] next to `estimates.json`, for tools reading criterion's
+//! directories without knowing the throughputs:
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde")] {
+//! use criterion_inverted_throughput::results::{
+//!     criterion_home, load_records, write_inverted_estimates, LATEST_BASELINE,
+//! };
+//!
+//! // after `cargo bench`
+//! let home = criterion_home();
+//! let records = load_records(&home, LATEST_BASELINE).unwrap();
+//! write_inverted_estimates(&home, LATEST_BASELINE, &records).unwrap();
+//! # }
+//! ```
+
+#[cfg(feature = "serde")]
+use crate::secondary;
+use crate::units::{checked_amount, throughput_amount};
+use criterion::Throughput;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::env;
+#[cfg(feature = "serde")]
+use std::fs;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "serde")]
+use std::process::Command;
+
+/// The baseline criterion saves the latest results to
+pub const LATEST_BASELINE: &str = "new";
+
+/// The file storing the estimates per element or byte next to `estimates.json`
+pub const INVERTED_ESTIMATES_FILE: &str = "inverted_estimates.json";
+
+/// The file storing the CPU utilization measured by
+/// [`WallAndCpuTime`](crate::cpu_time::WallAndCpuTime) next to `estimates.json`
+pub const UTILIZATION_FILE: &str = "utilization.json";
+
+/// Returns the directory criterion stores results in
+///
+/// It is resolved in the same way as criterion does:
+/// `$CRITERION_HOME`, `$CARGO_TARGET_DIR/criterion`, the target directory from
+/// `cargo metadata`, and `target/criterion` in this order.
+#[cfg(feature = "serde")]
+pub fn criterion_home() -> PathBuf {
+    if let Some(value) = env::var_os("CRITERION_HOME") {
+        PathBuf::from(value)
+    } else if let Some(path) = cargo_target_directory() {
+        path.join("criterion")
+    } else {
+        PathBuf::from("target/criterion")
+    }
+}
+
+#[cfg(feature = "serde")]
+fn cargo_target_directory() -> Option<PathBuf> {
+    #[derive(Deserialize)]
+    struct Metadata {
+        target_directory: PathBuf,
+    }
+
+    env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .or_else(|| {
+            let output = Command::new(env::var_os("CARGO")?)
+                .args(["metadata", "--format-version", "1"])
+                .output()
+                .ok()?;
+            let metadata: Metadata = serde_json::from_slice(&output.stdout).ok()?;
+            Some(metadata.target_directory)
+        })
+}
+
+/// An estimate of a statistic with its confidence interval, in the unit of the measurement
+/// (nanoseconds per iteration for wall time)
+///
+/// With the `serde` feature, it is serialized with the fields below, and deserialized
+/// from either them or criterion's `estimates.json`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "RawEstimate")
+)]
+pub struct Estimate {
+    /// The lower bound of the confidence interval
+    pub lower_bound: f64,
+    /// The point estimate
+    pub point_estimate: f64,
+    /// The upper bound of the confidence interval
+    pub upper_bound: f64,
+    /// The standard error of the point estimate
+    pub standard_error: f64,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawEstimate {
+    // as criterion stores
+    Criterion {
+        confidence_interval: RawConfidenceInterval,
+        point_estimate: f64,
+        standard_error: f64,
+    },
+    // as `Estimate` is serialized
+    Flat {
+        lower_bound: f64,
+        point_estimate: f64,
+        upper_bound: f64,
+        standard_error: f64,
+    },
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawConfidenceInterval {
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+#[cfg(feature = "serde")]
+impl From<RawEstimate> for Estimate {
+    fn from(raw: RawEstimate) -> Self {
+        match raw {
+            RawEstimate::Criterion {
+                confidence_interval,
+                point_estimate,
+                standard_error,
+            } => Estimate {
+                lower_bound: confidence_interval.lower_bound,
+                point_estimate,
+                upper_bound: confidence_interval.upper_bound,
+                standard_error,
+            },
+            RawEstimate::Flat {
+                lower_bound,
+                point_estimate,
+                upper_bound,
+                standard_error,
+            } => Estimate {
+                lower_bound,
+                point_estimate,
+                upper_bound,
+                standard_error,
+            },
+        }
+    }
+}
+
+/// The estimates criterion stores in `estimates.json`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct Estimates {
+    /// The mean of the sample
+    pub mean: Estimate,
+    /// The median of the sample
+    pub median: Estimate,
+    /// The median absolute deviation of the sample
+    pub median_abs_dev: Estimate,
+    /// The slope of the linear regression, only available with the linear sampling mode
+    pub slope: Option<Estimate>,
+    /// The standard deviation of the sample
+    pub std_dev: Estimate,
+}
+
+impl Estimate {
+    /// Returns the estimate divided by `amount`
+    pub fn per(&self, amount: f64) -> Estimate {
+        Estimate {
+            lower_bound: self.lower_bound / amount,
+            point_estimate: self.point_estimate / amount,
+            upper_bound: self.upper_bound / amount,
+            standard_error: self.standard_error / amount,
+        }
+    }
+}
+
+impl Estimates {
+    /// Returns the estimate criterion prints as `time`, the slope if available or the mean
+    pub fn typical(&self) -> &Estimate {
+        self.slope.as_ref().unwrap_or(&self.mean)
+    }
+
+    /// Returns the dispersion of the sample per iteration
+    pub fn dispersion(&self) -> Dispersion {
+        Dispersion {
+            std_dev: self.std_dev.point_estimate,
+            median_abs_dev: self.median_abs_dev.point_estimate,
+            coefficient_of_variation: self.std_dev.point_estimate / self.mean.point_estimate,
+        }
+    }
+}
+
+/// The dispersion of the times of a sample, to tell differences between benchmarks
+/// from run-to-run noise
+///
+/// Two benchmarks differing by less than a few standard deviations per element
+/// are hardly distinguishable.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Dispersion {
+    /// The point estimate of the standard deviation
+    pub std_dev: f64,
+    /// The point estimate of the median absolute deviation
+    pub median_abs_dev: f64,
+    /// The standard deviation divided by the mean, the same per iteration and per element
+    pub coefficient_of_variation: f64,
+}
+
+impl Dispersion {
+    /// Returns the dispersion divided by `amount`
+    pub fn per(&self, amount: f64) -> Dispersion {
+        Dispersion {
+            std_dev: self.std_dev / amount,
+            median_abs_dev: self.median_abs_dev / amount,
+            coefficient_of_variation: self.coefficient_of_variation,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawBenchmark {
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    throughput: Option<Throughput>,
+    full_id: String,
+    directory_name: String,
+}
+
+/// The stored result of a benchmark
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchRecord {
+    /// The full id of the benchmark like `group/function/value`
+    pub id: String,
+    /// The group of the benchmark
+    pub group_id: String,
+    /// The function of the benchmark
+    pub function_id: Option<String>,
+    /// The parameter of the benchmark
+    pub value_str: Option<String>,
+    /// The throughput set for the benchmark
+    pub throughput: Option<Throughput>,
+    /// The secondary throughput registered with `secondary` (feature `serde`)
+    pub secondary_throughput: Option<Throughput>,
+    /// The CPU time divided by the wall time, stored in [`UTILIZATION_FILE`] by
+    /// [`WallAndCpuTime`](crate::cpu_time::WallAndCpuTime)
+    pub utilization: Option<f64>,
+    /// The directory of the benchmark relative to the criterion home
+    pub directory_name: String,
+    /// The raw estimates of the benchmark
+    pub estimates: Estimates,
+}
+
+impl BenchRecord {
+    /// Returns the unit of the throughput, `elem` or `byte`
+    pub fn unit(&self) -> Option<&'static str> {
+        self.throughput.as_ref().map(|t| throughput_amount(t).1)
+    }
+
+    /// Returns the typical estimate per element or byte (the inverted throughput),
+    /// or `None` if the throughput is missing, zero or too large to be exact in `f64`
+    pub fn time_per_unit(&self) -> Option<Estimate> {
+        let (amount, _) = checked_amount(self.throughput.as_ref()?).ok()?;
+        Some(self.estimates.typical().per(amount))
+    }
+
+    /// Returns the dispersion of the sample per element or byte, or `None` if
+    /// the throughput is missing, zero or too large to be exact in `f64`
+    pub fn dispersion_per_unit(&self) -> Option<Dispersion> {
+        let (amount, _) = checked_amount(self.throughput.as_ref()?).ok()?;
+        Some(self.estimates.dispersion().per(amount))
+    }
+
+    /// Returns the unit of the secondary throughput, `elem` or `byte`
+    pub fn secondary_unit(&self) -> Option<&'static str> {
+        self.secondary_throughput
+            .as_ref()
+            .map(|t| throughput_amount(t).1)
+    }
+
+    /// Returns the typical estimate per element or byte of the secondary throughput,
+    /// or `None` if it is missing, zero or too large to be exact in `f64`
+    pub fn secondary_time_per_unit(&self) -> Option<Estimate> {
+        let (amount, _) = checked_amount(self.secondary_throughput.as_ref()?).ok()?;
+        Some(self.estimates.typical().per(amount))
+    }
+
+    /// Loads the result of the benchmark in `dir` saved as `baseline`
+    #[cfg(feature = "serde")]
+    pub fn load(dir: &Path, baseline: &str) -> io::Result<Self> {
+        let dir = dir.join(baseline);
+        let benchmark: RawBenchmark = read_json(&dir.join("benchmark.json"))?;
+        let estimates: Estimates = read_json(&dir.join("estimates.json"))?;
+        Ok(BenchRecord {
+            id: benchmark.full_id,
+            group_id: benchmark.group_id,
+            function_id: benchmark.function_id,
+            value_str: benchmark.value_str,
+            throughput: benchmark.throughput,
+            secondary_throughput: None,
+            utilization: load_utilization(&dir.join(UTILIZATION_FILE))?,
+            directory_name: benchmark.directory_name,
+            estimates,
+        })
+    }
+
+    /// Returns the point estimate of the CPU time per element or byte in nanoseconds,
+    /// the wall time per element or byte times the [utilization](Self::utilization)
+    pub fn cpu_time_per_unit(&self) -> Option<f64> {
+        Some(self.time_per_unit()?.point_estimate * self.utilization?)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct Utilization {
+    utilization: f64,
+}
+
+#[cfg(feature = "serde")]
+fn load_utilization(path: &Path) -> io::Result<Option<f64>> {
+    match read_json::<Utilization>(path) {
+        Ok(utilization) => Ok(Some(utilization.utilization)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes `utilization` to [`UTILIZATION_FILE`] in the directory `dir` of a baseline
+#[cfg(feature = "serde")]
+pub(crate) fn write_utilization(dir: &Path, utilization: f64) -> io::Result<()> {
+    let json = serde_json::to_string(&Utilization { utilization })
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(dir.join(UTILIZATION_FILE), json)
+}
+
+/// An estimate per element or byte in nanoseconds, as stored in [`INVERTED_ESTIMATES_FILE`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InvertedEstimate {
+    /// The lower bound of the confidence interval
+    pub lower_bound: f64,
+    /// The point estimate
+    pub point_estimate: f64,
+    /// The upper bound of the confidence interval
+    pub upper_bound: f64,
+    /// The standard error of the point estimate
+    pub standard_error: f64,
+}
+
+impl From<Estimate> for InvertedEstimate {
+    fn from(estimate: Estimate) -> Self {
+        InvertedEstimate {
+            lower_bound: estimate.lower_bound,
+            point_estimate: estimate.point_estimate,
+            upper_bound: estimate.upper_bound,
+            standard_error: estimate.standard_error,
+        }
+    }
+}
+
+/// The estimates of a benchmark per element or byte stored in [`INVERTED_ESTIMATES_FILE`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InvertedEstimates {
+    /// The full id of the benchmark like `group/function/value`
+    pub id: String,
+    /// The amount of the throughput the estimates are divided by
+    pub amount: u64,
+    /// The unit of the throughput, `elem`, `byte` or `bit`
+    pub unit: String,
+    /// The unit of the estimates like `ns/elem`
+    pub estimate_unit: String,
+    /// The mean per element or byte
+    pub mean: InvertedEstimate,
+    /// The median per element or byte
+    pub median: InvertedEstimate,
+    /// The slope per element or byte, only available with the linear sampling mode
+    pub slope: Option<InvertedEstimate>,
+    /// The estimate criterion prints per element or byte, the slope if available or the mean
+    pub typical: InvertedEstimate,
+    /// The dispersion of the sample per element or byte, zero in files written before it
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dispersion: Dispersion,
+}
+
+impl InvertedEstimates {
+    /// Returns the estimates of `record` per element or byte, or `None` if its throughput
+    /// is missing, zero or too large to be exact in `f64`
+    pub fn new(record: &BenchRecord) -> Option<Self> {
+        let (amount, unit) = checked_amount(record.throughput.as_ref()?).ok()?;
+        let per_unit = |estimate: &Estimate| InvertedEstimate::from(estimate.per(amount));
+        Some(InvertedEstimates {
+            id: record.id.clone(),
+            amount: amount as u64,
+            unit: unit.to_string(),
+            estimate_unit: format!("ns/{}", unit),
+            mean: per_unit(&record.estimates.mean),
+            median: per_unit(&record.estimates.median),
+            slope: record.estimates.slope.as_ref().map(per_unit),
+            typical: per_unit(record.estimates.typical()),
+            dispersion: record.estimates.dispersion().per(amount),
+        })
+    }
+
+    /// Loads the estimates stored in the directory of a benchmark `dir` for `baseline`
+    #[cfg(feature = "serde")]
+    pub fn load(dir: &Path, baseline: &str) -> io::Result<Self> {
+        read_json(&dir.join(baseline).join(INVERTED_ESTIMATES_FILE))
+    }
+}
+
+/// Writes [`INVERTED_ESTIMATES_FILE`] next to `estimates.json` of each benchmark of
+/// `records` loaded from `criterion_home` for `baseline`
+///
+/// Returns the number of written files. Benchmarks without throughput are skipped.
+#[cfg(feature = "serde")]
+pub fn write_inverted_estimates(
+    criterion_home: &Path,
+    baseline: &str,
+    records: &[BenchRecord],
+) -> io::Result<usize> {
+    let mut count = 0;
+    for record in records {
+        let Some(estimates) = InvertedEstimates::new(record) else {
+            continue;
+        };
+        let json = serde_json::to_string_pretty(&estimates)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let dir = criterion_home.join(&record.directory_name).join(baseline);
+        fs::write(dir.join(INVERTED_ESTIMATES_FILE), json)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(feature = "serde")]
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    let file = fs::File::open(path)?;
+    serde_json::from_reader(io::BufReader::new(file)).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// Loads all benchmark results under `criterion_home` saved as `baseline`, sorted by id
+///
+/// Use [`criterion_home`] and [`LATEST_BASELINE`] to load the latest results.
+/// The secondary throughputs registered in `criterion_home` are loaded too.
+#[cfg(feature = "serde")]
+pub fn load_records(criterion_home: &Path, baseline: &str) -> io::Result<Vec<BenchRecord>> {
+    let mut records = vec![];
+    collect_records(criterion_home, baseline, &mut records)?;
+    records.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut secondary_throughputs = secondary::load(criterion_home)?;
+    for record in &mut records {
+        record.secondary_throughput = secondary_throughputs.remove(&record.id);
+    }
+    Ok(records)
+}
+
+#[cfg(feature = "serde")]
+fn collect_records(dir: &Path, baseline: &str, records: &mut Vec<BenchRecord>) -> io::Result<()> {
+    if dir.join(baseline).join("benchmark.json").is_file() {
+        records.push(BenchRecord::load(dir, baseline)?);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        // skip html reports and the baselines of the benchmark itself
+        if name == "report" || name == baseline || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        collect_records(&entry.path(), baseline, records)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    const BENCHMARK_JSON: &str = r#"{"group_id":"parse","function_id":null,"value_str":"10","throughput":{"Elements":10},"full_id":"parse/10","directory_name":"parse/10","title":"parse/10"}"#;
+    const ESTIMATES_JSON: &str = r#"{"mean":{"confidence_interval":{"confidence_level":0.95,"lower_bound":339.0,"upper_bound":356.0},"point_estimate":347.0,"standard_error":4.3},"median":{"confidence_interval":{"confidence_level":0.95,"lower_bound":334.0,"upper_bound":365.0},"point_estimate":344.0,"standard_error":6.7},"median_abs_dev":{"confidence_interval":{"confidence_level":0.95,"lower_bound":2.3,"upper_bound":23.6},"point_estimate":17.7,"standard_error":6.2},"slope":{"confidence_interval":{"confidence_level":0.95,"lower_bound":338.0,"upper_bound":357.0},"point_estimate":348.0,"standard_error":5.1},"std_dev":{"confidence_interval":{"confidence_level":0.95,"lower_bound":7.3,"upper_bound":17.1},"point_estimate":14.4,"standard_error":2.2}}"#;
+
+    fn write_benchmark(home: &Path, directory_name: &str, baseline: &str) {
+        let dir = home.join(directory_name).join(baseline);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("benchmark.json"), BENCHMARK_JSON).unwrap();
+        fs::write(dir.join("estimates.json"), ESTIMATES_JSON).unwrap();
+    }
+
+    #[test]
+    fn test_load_records() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-load-records");
+        let _ = fs::remove_dir_all(&home);
+        write_benchmark(&home, "parse/10", "new");
+        write_benchmark(&home, "parse/10", "base");
+        fs::create_dir_all(home.join("parse/report")).unwrap();
+
+        let records = load_records(&home, LATEST_BASELINE).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.id, "parse/10");
+        assert_eq!(record.group_id, "parse");
+        assert_eq!(record.function_id, None);
+        assert_eq!(record.value_str.as_deref(), Some("10"));
+        assert_eq!(record.throughput, Some(Throughput::Elements(10)));
+        assert_eq!(
+            *record.estimates.typical(),
+            Estimate {
+                lower_bound: 338.0,
+                point_estimate: 348.0,
+                upper_bound: 357.0,
+                standard_error: 5.1,
+            }
+        );
+
+        assert_eq!(record.unit(), Some("elem"));
+        assert_eq!(record.time_per_unit().unwrap().point_estimate, 34.8);
+        let dispersion = record.dispersion_per_unit().unwrap();
+        assert_eq!(dispersion.std_dev, 1.44);
+        assert_eq!(dispersion.median_abs_dev, 1.77);
+        assert_eq!(dispersion.coefficient_of_variation, 14.4 / 347.0);
+        assert_eq!(record.secondary_throughput, None);
+        assert_eq!(record.utilization, None);
+        assert_eq!(record.cpu_time_per_unit(), None);
+
+        secondary::register_in(&home, "parse/10", Throughput::Bytes(40)).unwrap();
+        let record = &load_records(&home, LATEST_BASELINE).unwrap()[0];
+        assert_eq!(record.secondary_unit(), Some("byte"));
+        assert_eq!(
+            record.secondary_time_per_unit().unwrap().point_estimate,
+            8.7
+        );
+
+        write_utilization(&home.join("parse/10/new"), 0.5).unwrap();
+        let record = &load_records(&home, LATEST_BASELINE).unwrap()[0];
+        assert_eq!(record.utilization, Some(0.5));
+        assert_eq!(record.cpu_time_per_unit(), Some(17.4));
+
+        assert!(load_records(&home, "missing").unwrap().is_empty());
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_write_inverted_estimates() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-inverted-estimates");
+        let _ = fs::remove_dir_all(&home);
+        write_benchmark(&home, "parse/10", "new");
+
+        let records = load_records(&home, LATEST_BASELINE).unwrap();
+        assert_eq!(
+            write_inverted_estimates(&home, LATEST_BASELINE, &records).unwrap(),
+            1
+        );
+        let estimates = InvertedEstimates::load(&home.join("parse/10"), LATEST_BASELINE).unwrap();
+        assert_eq!(estimates.id, "parse/10");
+        assert_eq!(estimates.amount, 10);
+        assert_eq!(estimates.estimate_unit, "ns/elem");
+        assert_eq!(estimates.mean.point_estimate, 34.7);
+        assert_eq!(estimates.median.lower_bound, 33.4);
+        assert_eq!(estimates.typical, estimates.slope.unwrap());
+        assert_eq!(estimates.typical.upper_bound, 35.7);
+        assert_eq!(estimates.dispersion.std_dev, 1.44);
+        // criterion does not load it as a benchmark
+        assert_eq!(load_records(&home, LATEST_BASELINE).unwrap().len(), 1);
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+//! Context switches and page faults by `getrusage`
+//!
+//! [`Rusage`] measures a counter of `getrusage(RUSAGE_SELF)` in each sample, and prints it
+//! per element or byte like `0.0120 csw/elem`. Benchmarks looking fast may yield to
+//! the scheduler or fault pages in so often that they slow down the rest of a program:
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::rusage::{Counter, Rusage};
+//!
+//! let c = Criterion::default().with_measurement(Rusage::context_switches());
+//! let c = Criterion::default().with_measurement(Rusage::new(Counter::MajorFaults));
+//! ```
+//!
+//! It takes the options of [`InvertedThroughput`](crate::InvertedThroughput) like
+//! [`Rusage::precision`].
+//!
+//! The counters are the ones of all threads of the process, so other threads running while
+//! measuring are counted too. Only Unix is supported.
+
+use crate::units::scale_per_unit;
+use crate::{inversion_options, Inversion};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+
+/// The counter of `getrusage` [`Rusage`] measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Counter {
+    /// Voluntary context switches (`ru_nvcsw`), e.g. waiting for I/O or locks
+    VoluntarySwitches,
+    /// Involuntary context switches (`ru_nivcsw`), preempted by the scheduler
+    InvoluntarySwitches,
+    /// Voluntary and involuntary context switches
+    ContextSwitches,
+    /// Page faults serviced without I/O (`ru_minflt`)
+    MinorFaults,
+    /// Page faults requiring I/O (`ru_majflt`)
+    MajorFaults,
+    /// Minor and major page faults
+    PageFaults,
+}
+
+impl Counter {
+    /// Returns the unit of the counter like `csw`
+    pub fn unit(self) -> &'static str {
+        match self {
+            Counter::VoluntarySwitches => "vcsw",
+            Counter::InvoluntarySwitches => "ivcsw",
+            Counter::ContextSwitches => "csw",
+            Counter::MinorFaults => "minflt",
+            Counter::MajorFaults => "majflt",
+            Counter::PageFaults => "faults",
+        }
+    }
+}
+
+/// The counters of `getrusage` of a sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Usage {
+    /// The voluntary context switches
+    pub voluntary_switches: u64,
+    /// The involuntary context switches
+    pub involuntary_switches: u64,
+    /// The page faults serviced without I/O
+    pub minor_faults: u64,
+    /// The page faults requiring I/O
+    pub major_faults: u64,
+}
+
+impl Usage {
+    /// Returns the counters of the process so far, or `None` if `getrusage` fails
+    pub fn now() -> Option<Self> {
+        // SAFETY: `rusage` is a plain C struct, valid when zeroed
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: `usage` is a valid pointer to a `rusage`
+        if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+            return None;
+        }
+        Some(Usage {
+            voluntary_switches: usage.ru_nvcsw as u64,
+            involuntary_switches: usage.ru_nivcsw as u64,
+            minor_faults: usage.ru_minflt as u64,
+            major_faults: usage.ru_majflt as u64,
+        })
+    }
+
+    /// Returns the value of `counter`
+    pub fn get(&self, counter: Counter) -> u64 {
+        match counter {
+            Counter::VoluntarySwitches => self.voluntary_switches,
+            Counter::InvoluntarySwitches => self.involuntary_switches,
+            Counter::ContextSwitches => self.voluntary_switches + self.involuntary_switches,
+            Counter::MinorFaults => self.minor_faults,
+            Counter::MajorFaults => self.major_faults,
+            Counter::PageFaults => self.minor_faults + self.major_faults,
+        }
+    }
+
+    fn zip(&self, other: &Usage, f: impl Fn(u64, u64) -> u64) -> Usage {
+        Usage {
+            voluntary_switches: f(self.voluntary_switches, other.voluntary_switches),
+            involuntary_switches: f(self.involuntary_switches, other.involuntary_switches),
+            minor_faults: f(self.minor_faults, other.minor_faults),
+            major_faults: f(self.major_faults, other.major_faults),
+        }
+    }
+}
+
+/// The custom measurement of a counter of `getrusage`, printing throughputs in the format
+/// `[counter]/[element or byte]`
+///
+/// # Panics
+///
+/// Measuring panics if `getrusage` fails.
+pub struct Rusage {
+    counter: Counter,
+    formatter: RusageFormatter,
+    inversion: Inversion,
+}
+
+impl Rusage {
+    /// Returns a new `Rusage` measuring `counter`
+    pub fn new(counter: Counter) -> Self {
+        Rusage {
+            counter,
+            formatter: RusageFormatter {
+                unit: counter.unit(),
+            },
+            inversion: Inversion::new(),
+        }
+    }
+
+    /// Returns a new `Rusage` measuring voluntary and involuntary context switches
+    pub fn context_switches() -> Self {
+        Self::new(Counter::ContextSwitches)
+    }
+
+    /// Returns a new `Rusage` measuring minor and major page faults
+    pub fn page_faults() -> Self {
+        Self::new(Counter::PageFaults)
+    }
+
+    /// Returns the counter measured
+    pub fn counter(&self) -> Counter {
+        self.counter
+    }
+
+    inversion_options!();
+}
+
+fn usage_now() -> Usage {
+    Usage::now().expect("getrusage failed")
+}
+
+impl Measurement for Rusage {
+    type Intermediate = Usage;
+    type Value = Usage;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        usage_now()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        usage_now().zip(&i, u64::saturating_sub)
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1.zip(v2, |a, b| a + b)
+    }
+    fn zero(&self) -> Self::Value {
+        Usage::default()
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        val.get(self.counter) as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for Rusage {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&self.formatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&self.formatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&self.formatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&self.formatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&self.formatter, values)
+    }
+}
+
+/// The formatter of counts of `getrusage`, printing throughputs in the format
+/// `[counter]/[element or byte]`
+pub struct RusageFormatter {
+    unit: &'static str,
+}
+
+impl ValueFormatter for RusageFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        self.unit
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        self.unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::thrpt_line;
+    use test_case::test_case;
+
+    #[test]
+    fn test_page_faults() {
+        let measure = Rusage::new(Counter::MinorFaults);
+        let start = measure.start();
+        // touch fresh pages
+        let pages = vec![1u8; 64 << 20];
+        assert_eq!(
+            pages.iter().step_by(4096).map(|&b| b as u64).sum::<u64>(),
+            16384
+        );
+        let usage = measure.end(start);
+        assert!(measure.to_f64(&usage) > 0.0, "{:?}", usage);
+        assert!(usage.get(Counter::PageFaults) >= usage.minor_faults);
+    }
+
+    #[test_case(Counter::ContextSwitches, Throughput::Elements(10), "csw/elem" ; "switches")]
+    #[test_case(Counter::MajorFaults, Throughput::Bytes(10), "majflt/byte" ; "faults")]
+    fn test_throughputs(counter: Counter, throughput: Throughput, expected: &str) {
+        let measure = Rusage::new(counter);
+        let mut values = [5.0];
+        let unit = measure
+            .formatter()
+            .scale_throughputs(5.0, &throughput, &mut values);
+        assert_eq!(unit, expected);
+        assert_eq!(values, [0.5]);
+    }
+
+    #[test]
+    fn test_bound_order() {
+        let measure = Rusage::context_switches();
+        assert_eq!(
+            thrpt_line(&measure, &Throughput::Elements(100), [0.0, 0.0, 3.0]),
+            ["0.0000 csw/elem", "0.0000 csw/elem", "0.0300 csw/elem"]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Secondary throughputs of benchmarks
+//!
+//! Criterion allows a throughput per benchmark, but e.g. parser benchmarks have both rows
+//! and bytes. Register a secondary throughput of a benchmark while benchmarking, then
+//! [`results`](crate::results) and [`export`](crate::export) carry both `ns/elem`
+//! and `ns/byte`:
+//!
+//! ```no_run
+//! use criterion::{Criterion, Throughput};
+//! use criterion_inverted_throughput::{secondary, InvertedThroughput};
+//!
+//! # let input = "a,b\nc,d\n";
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("parse");
+//! g.throughput(Throughput::Elements(2));
+//! secondary::also_per_bytes("parse/csv", input.len() as u64).unwrap();
+//! g.bench_function("csv", |b| b.iter(|| input.lines().count()));
+//! g.finish();
+//! ```
+//!
+//! The secondary throughputs are stored in `secondary-throughputs.json` in the directory
+//! criterion stores results in.
+
+use crate::results::criterion_home;
+use criterion::Throughput;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The file storing secondary throughputs in the directory criterion stores results in
+pub const SECONDARY_THROUGHPUTS_FILE: &str = "secondary-throughputs.json";
+
+/// Registers `bytes` bytes as the secondary throughput of the benchmark `id`
+pub fn also_per_bytes(id: &str, bytes: u64) -> io::Result<()> {
+    register(id, Throughput::Bytes(bytes))
+}
+
+/// Registers `elements` elements as the secondary throughput of the benchmark `id`
+pub fn also_per_elements(id: &str, elements: u64) -> io::Result<()> {
+    register(id, Throughput::Elements(elements))
+}
+
+/// Registers `throughput` as the secondary throughput of the benchmark `id`
+pub fn register(id: &str, throughput: Throughput) -> io::Result<()> {
+    register_in(&criterion_home(), id, throughput)
+}
+
+/// Registers `throughput` as the secondary throughput of the benchmark `id`
+/// in `criterion_home`
+pub fn register_in(criterion_home: &Path, id: &str, throughput: Throughput) -> io::Result<()> {
+    let mut throughputs = load(criterion_home)?;
+    throughputs.insert(id.to_string(), throughput);
+    fs::create_dir_all(criterion_home)?;
+    let json = serde_json::to_string_pretty(&throughputs)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path(criterion_home), json)
+}
+
+/// Loads the secondary throughputs registered in `criterion_home` by benchmark ids
+pub fn load(criterion_home: &Path) -> io::Result<BTreeMap<String, Throughput>> {
+    let path = path(criterion_home);
+    match fs::read(&path) {
+        Ok(json) => serde_json::from_slice(&json).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn path(criterion_home: &Path) -> PathBuf {
+    criterion_home.join(SECONDARY_THROUGHPUTS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_register() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-secondary");
+        let _ = fs::remove_dir_all(&home);
+        assert!(load(&home).unwrap().is_empty());
+
+        register_in(&home, "parse/csv", Throughput::Bytes(10)).unwrap();
+        register_in(&home, "parse/json", Throughput::Bytes(20)).unwrap();
+        register_in(&home, "parse/csv", Throughput::Bytes(30)).unwrap();
+        let throughputs = load(&home).unwrap();
+        assert_eq!(throughputs.len(), 2);
+        assert_eq!(throughputs["parse/csv"], Throughput::Bytes(30));
+        assert_eq!(throughputs["parse/json"], Throughput::Bytes(20));
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
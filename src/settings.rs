@@ -0,0 +1,560 @@
+//! Per-benchmark settings from `inverted-throughput.toml` or environment variables
+//!
+//! Large projects can keep the labels, time units, block sizes and budgets of their
+//! benchmarks in one place instead of hardcoding them in every bench file. [`SETTINGS_FILE`]
+//! in the directory `cargo bench` runs in (the package root) maps globs of benchmark ids,
+//! where `*` matches any characters and `?` one, to settings. Later rules override earlier
+//! ones:
+//!
+//! ```toml
+//! [[bench]]
+//! id = "parse/*"
+//! label = "row"
+//! budget = "75 ns/row"
+//!
+//! [[bench]]
+//! id = "render/*"
+//! label = "frame"
+//! time_unit = "ms"
+//!
+//! [[bench]]
+//! id = "io/*"
+//! block_size = 4096
+//! ```
+//!
+//! Apply the settings of a benchmark while running it:
+//!
+//! ```no_run
+//! use criterion::{Criterion, Throughput};
+//! use criterion_inverted_throughput::settings::Settings;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! let settings = Settings::load().unwrap();
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("io");
+//! let bench = settings.bench("io/read");
+//! // `Throughput::Elements` of 4 KiB blocks, printed like `ns/block`
+//! g.throughput(bench.throughput(Throughput::Bytes(1 << 20)));
+//! let _settings = bench.apply();
+//! g.bench_function("read", |b| b.iter(|| vec![0u8; 1 << 20]));
+//! g.finish();
+//! ```
+//!
+//! Reading the file requires the `toml` feature. [`SETTINGS_ENV`] overrides the path of
+//! the file, and the variables `INVERTED_THROUGHPUT_LABEL`, `INVERTED_THROUGHPUT_TIME_UNIT`,
+//! `INVERTED_THROUGHPUT_BLOCK_SIZE` and `INVERTED_THROUGHPUT_BUDGET` add a last rule for
+//! the benchmarks matching `INVERTED_THROUGHPUT_BENCH` (all by default).
+//! `criterion-invert check` checks the budgets against the latest results.
+//!
+//! A [`denom_formatter`](crate::InvertedThroughput::denom_formatter) set to the measurement
+//! takes precedence over the time units of the settings.
+
+use crate::budget::{Budget, BudgetError};
+use crate::denominator::{self, LabelGuard};
+use crate::results::{BenchRecord, Estimate};
+use criterion::Throughput;
+use std::cell::Cell;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// The file of the settings in the directory benchmarks run in
+pub const SETTINGS_FILE: &str = "inverted-throughput.toml";
+
+/// The environment variable overriding the path of [`SETTINGS_FILE`]
+pub const SETTINGS_ENV: &str = "INVERTED_THROUGHPUT_CONFIG";
+
+const ENV_PREFIX: &str = "INVERTED_THROUGHPUT_";
+
+thread_local! {
+    static TIME_UNIT: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// A rule of [`Settings`] for the benchmarks matching a glob
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "toml",
+    derive(serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Rule {
+    /// The glob of benchmark ids like `parse/*`
+    pub id: String,
+    /// The label of elements like `row`
+    pub label: Option<String>,
+    /// The fixed time unit of times per element, `ps`, `ns`, `µs` (or `us`), `ms` or `s`
+    pub time_unit: Option<String>,
+    /// The bytes of a block, to print times per block of bytes
+    pub block_size: Option<u64>,
+    /// The budget like `75 ns/elem`, or per the label of the rule like `75 ns/row`
+    pub budget: Option<String>,
+}
+
+impl Rule {
+    // the label of elements of the rule, `block` with a block size by default
+    fn elements_label(&self) -> Option<&str> {
+        self.label.as_deref().or(self.block_size.map(|_| "block"))
+    }
+
+    fn parse(&self) -> Result<BenchSettings, SettingsError> {
+        let invalid =
+            |message: String| SettingsError::Invalid(format!("`{}`: {}", self.id, message));
+        let time_unit = match self.time_unit.as_deref() {
+            None => None,
+            Some("ps") => Some("ps"),
+            Some("ns") => Some("ns"),
+            Some("µs" | "us") => Some("µs"),
+            Some("ms") => Some("ms"),
+            Some("s") => Some("s"),
+            Some(unit) => return Err(invalid(format!("unknown time unit `{}`", unit))),
+        };
+        if self.block_size == Some(0) {
+            return Err(invalid("the block size must be positive".to_string()));
+        }
+        Ok(BenchSettings {
+            label: self.label.clone().map(denominator::intern),
+            time_unit,
+            block_size: self.block_size,
+            budget: self
+                .budget
+                .as_deref()
+                .map(|budget| Budget::parse_labeled(budget, self.elements_label()))
+                .transpose()
+                .map_err(invalid)?,
+        })
+    }
+}
+
+/// The rules mapping benchmark ids to their settings
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "toml",
+    derive(serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Settings {
+    /// The rules in the order they are applied, `[[bench]]` tables in the file
+    #[cfg_attr(feature = "toml", serde(default, rename = "bench"))]
+    pub rules: Vec<Rule>,
+}
+
+impl Settings {
+    /// Loads [`SETTINGS_FILE`] in the current directory, or the file of [`SETTINGS_ENV`],
+    /// with the rule of the environment variables
+    ///
+    /// A missing [`SETTINGS_FILE`] is no error.
+    pub fn load() -> Result<Self, SettingsError> {
+        let file = Path::new(SETTINGS_FILE);
+        let settings = match env::var_os(SETTINGS_ENV) {
+            Some(path) => Self::load_from(Path::new(&path))?,
+            None if file.exists() => Self::load_from(file)?,
+            None => Settings::default(),
+        };
+        settings.with_env()
+    }
+
+    /// Appends the rule of the environment variables `INVERTED_THROUGHPUT_*`, if any is set
+    pub fn with_env(mut self) -> Result<Self, SettingsError> {
+        if let Some(rule) = env_rule(|key| env::var(format!("{}{}", ENV_PREFIX, key)).ok())? {
+            rule.parse()?;
+            self.rules.push(rule);
+        }
+        Ok(self)
+    }
+
+    /// Loads the settings in the TOML file `path`
+    pub fn load_from(path: &Path) -> Result<Self, SettingsError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the settings in TOML
+    ///
+    /// It fails without the `toml` feature.
+    pub fn from_toml(text: &str) -> Result<Self, SettingsError> {
+        #[cfg(feature = "toml")]
+        {
+            let settings: Settings =
+                toml::from_str(text).map_err(|e| SettingsError::Invalid(e.to_string()))?;
+            for rule in &settings.rules {
+                rule.parse()?;
+            }
+            Ok(settings)
+        }
+        #[cfg(not(feature = "toml"))]
+        {
+            let _ = text;
+            Err(SettingsError::Invalid(format!(
+                "reading {} requires the `toml` feature",
+                SETTINGS_FILE
+            )))
+        }
+    }
+
+    /// Returns the settings of the benchmark `id` like `parse/1k` merged from the matching
+    /// rules
+    ///
+    /// # Panics
+    ///
+    /// Panics if a matching rule is invalid. Loaded rules are validated when loading.
+    pub fn bench(&self, id: &str) -> BenchSettings {
+        self.rules
+            .iter()
+            .filter(|rule| glob_match(&rule.id, id))
+            .map(|rule| rule.parse().unwrap_or_else(|e| panic!("{}", e)))
+            .fold(BenchSettings::default(), BenchSettings::merge)
+    }
+
+    /// Checks the benchmarks of `records` with budgets in their settings
+    ///
+    /// Returns the ids of the checked benchmarks with the results of [`Budget::check`].
+    pub fn check_budgets(
+        &self,
+        records: &[BenchRecord],
+    ) -> Vec<(String, Result<Estimate, BudgetError>)> {
+        records
+            .iter()
+            .filter_map(|record| {
+                let budget = self.bench(&record.id).budget?;
+                Some((record.id.clone(), budget.check(record)))
+            })
+            .collect()
+    }
+
+    /// Returns whether any rule has a budget
+    pub fn has_budgets(&self) -> bool {
+        self.rules.iter().any(|rule| rule.budget.is_some())
+    }
+
+    /// Returns the rules with budgets matching no benchmark of `records`, e.g. with typos
+    /// in their globs
+    pub fn unmatched_budgets(&self, records: &[BenchRecord]) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.budget.is_some())
+            .filter(|rule| {
+                !records
+                    .iter()
+                    .any(|record| glob_match(&rule.id, &record.id))
+            })
+            .collect()
+    }
+}
+
+// the rule of the environment variables `INVERTED_THROUGHPUT_*` read by `var`, if any is set
+fn env_rule(var: impl Fn(&str) -> Option<String>) -> Result<Option<Rule>, SettingsError> {
+    let block_size = match var("BLOCK_SIZE") {
+        Some(size) => Some(size.parse().map_err(|_| {
+            SettingsError::Invalid(format!("invalid {}BLOCK_SIZE `{}`", ENV_PREFIX, size))
+        })?),
+        None => None,
+    };
+    let rule = Rule {
+        id: var("BENCH").unwrap_or_else(|| "*".to_string()),
+        label: var("LABEL"),
+        time_unit: var("TIME_UNIT"),
+        block_size,
+        budget: var("BUDGET"),
+    };
+    let any = rule.label.is_some()
+        || rule.time_unit.is_some()
+        || rule.block_size.is_some()
+        || rule.budget.is_some();
+    Ok(any.then_some(rule))
+}
+
+// whether `id` matches `pattern`, where `*` matches any characters and `?` one
+fn glob_match(pattern: &str, id: &str) -> bool {
+    let (pattern, id): (Vec<char>, Vec<char>) = (pattern.chars().collect(), id.chars().collect());
+    let (mut p, mut i) = (0, 0);
+    // the position of the last `*` and the position in `id` it matched up to
+    let mut star = None;
+    while i < id.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == id[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, i));
+            p += 1;
+        } else if let Some((star_p, star_i)) = star {
+            p = star_p + 1;
+            i = star_i + 1;
+            star = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The settings of a benchmark
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BenchSettings {
+    /// The label of elements
+    pub label: Option<&'static str>,
+    /// The fixed time unit of times per element
+    pub time_unit: Option<&'static str>,
+    /// The bytes of a block
+    pub block_size: Option<u64>,
+    /// The budget
+    pub budget: Option<Budget>,
+}
+
+impl BenchSettings {
+    // `other` overriding `self`
+    fn merge(self, other: BenchSettings) -> Self {
+        BenchSettings {
+            label: other.label.or(self.label),
+            time_unit: other.time_unit.or(self.time_unit),
+            block_size: other.block_size.or(self.block_size),
+            budget: other.budget.or(self.budget),
+        }
+    }
+
+    /// Returns `throughput` in blocks with a block size, `Throughput::Elements` of the blocks
+    /// (rounded up) for `Throughput::Bytes`
+    pub fn throughput(&self, throughput: Throughput) -> Throughput {
+        match (self.block_size, throughput) {
+            (Some(size), Throughput::Bytes(bytes)) => Throughput::Elements(bytes.div_ceil(size)),
+            (_, throughput) => throughput,
+        }
+    }
+
+    /// Returns the label of elements, `block` with a block size by default
+    pub fn elements_label(&self) -> Option<&'static str> {
+        self.label.or(self.block_size.map(|_| "block"))
+    }
+
+    /// Labels elements and fixes the time unit of the times printed in the current thread
+    /// until the returned guard is dropped
+    pub fn apply(&self) -> SettingsGuard {
+        SettingsGuard {
+            _label: self.elements_label().map(denominator::label_elements),
+            previous_time_unit: TIME_UNIT.with(|current| current.replace(self.time_unit)),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// Returns the time unit the settings applied in the current thread fix, if any
+pub(crate) fn fixed_time_unit() -> Option<&'static str> {
+    TIME_UNIT.with(Cell::get)
+}
+
+/// The guard applying [`BenchSettings`] until it is dropped
+///
+/// The previous settings are restored on drop, so guards can be nested.
+#[must_use = "the settings are reset when the guard is dropped"]
+pub struct SettingsGuard {
+    _label: Option<LabelGuard>,
+    previous_time_unit: Option<&'static str>,
+    // the settings are thread-local
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for SettingsGuard {
+    fn drop(&mut self) {
+        TIME_UNIT.with(|time_unit| time_unit.set(self.previous_time_unit));
+    }
+}
+
+/// The error returned when settings cannot be loaded
+#[derive(Debug)]
+pub enum SettingsError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The settings are malformed or invalid
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "failed to read settings: {}", e),
+            SettingsError::Invalid(message) => write!(f, "invalid settings: {}", message),
+        }
+    }
+}
+
+impl Error for SettingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SettingsError::Io(e) => Some(e),
+            SettingsError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InvertedThroughput;
+    use criterion::measurement::{Measurement, ValueFormatter};
+    use test_case::test_case;
+
+    fn rule(id: &str) -> Rule {
+        Rule {
+            id: id.to_string(),
+            ..Rule::default()
+        }
+    }
+
+    #[test_case("parse/*", "parse/1k", true ; "star")]
+    #[test_case("parse/*", "parse", false ; "star requires the slash")]
+    #[test_case("*/1?", "parse/1k", true ; "question")]
+    #[test_case("*", "", true ; "empty")]
+    #[test_case("a*b*c", "aXbYbZc", true ; "backtrack")]
+    #[test_case("a*b", "aXbYc", false ; "no match")]
+    fn test_glob_match(pattern: &str, id: &str, expected: bool) {
+        assert_eq!(glob_match(pattern, id), expected);
+    }
+
+    #[test]
+    fn test_bench() {
+        let settings = Settings {
+            rules: vec![
+                Rule {
+                    label: Some("row".to_string()),
+                    budget: Some("75 ns/row".to_string()),
+                    ..rule("parse/*")
+                },
+                Rule {
+                    budget: Some("50 ns/elem".to_string()),
+                    ..rule("parse/csv")
+                },
+                Rule {
+                    block_size: Some(4096),
+                    ..rule("io/*")
+                },
+            ],
+        };
+        assert_eq!(
+            settings.bench("parse/csv"),
+            BenchSettings {
+                label: Some("row"),
+                budget: Some(Budget::ns_per_elem(50.0)),
+                ..BenchSettings::default()
+            }
+        );
+        assert_eq!(settings.bench("render"), BenchSettings::default());
+
+        let io = settings.bench("io/read");
+        assert_eq!(io.elements_label(), Some("block"));
+        assert_eq!(
+            io.throughput(Throughput::Bytes(10000)),
+            Throughput::Elements(3)
+        );
+        assert_eq!(
+            io.throughput(Throughput::Elements(10)),
+            Throughput::Elements(10)
+        );
+    }
+
+    #[test]
+    fn test_unmatched_budgets() {
+        let records = [crate::test_support::RecordBuilder::new("parse/10", 100.0).build()];
+        let settings = Settings {
+            rules: vec![
+                Rule {
+                    budget: Some("75 ns/elem".to_string()),
+                    ..rule("parse/*")
+                },
+                Rule {
+                    budget: Some("75 ns/elem".to_string()),
+                    ..rule("prase/*")
+                },
+                Rule {
+                    label: Some("frame".to_string()),
+                    ..rule("render/*")
+                },
+            ],
+        };
+        assert!(settings.has_budgets());
+        assert_eq!(settings.unmatched_budgets(&records), [&settings.rules[1]]);
+        assert!(!Settings::default().has_budgets());
+    }
+
+    #[test]
+    fn test_apply() {
+        let measure = InvertedThroughput::new();
+        let settings = BenchSettings {
+            label: Some("frame"),
+            time_unit: Some("ms"),
+            ..BenchSettings::default()
+        };
+        {
+            let _settings = settings.apply();
+            let mut values = [2e4];
+            let unit =
+                measure
+                    .formatter()
+                    .scale_throughputs(2e4, &Throughput::Elements(10), &mut values);
+            assert_eq!(unit, "ms/frame");
+            assert_eq!(values, [0.002]);
+        }
+        assert_eq!(fixed_time_unit(), None);
+        assert_eq!(
+            measure.format_throughput(&Throughput::Elements(10), 2e4),
+            "2.0000 µs/elem"
+        );
+    }
+
+    #[test]
+    fn test_env_rule() {
+        assert_eq!(env_rule(|_| None).unwrap(), None);
+        let env = env_rule(|key| match key {
+            "BENCH" => Some("parse/*".to_string()),
+            "BLOCK_SIZE" => Some("512".to_string()),
+            _ => None,
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            env,
+            Rule {
+                block_size: Some(512),
+                ..rule("parse/*")
+            }
+        );
+        assert!(env_rule(|key| (key == "BLOCK_SIZE").then(|| "4k".to_string())).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml() {
+        let settings = Settings::from_toml(
+            r#"
+            [[bench]]
+            id = "render/*"
+            label = "frame"
+            time_unit = "us"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.bench("render/scene"),
+            BenchSettings {
+                label: Some("frame"),
+                time_unit: Some("µs"),
+                ..BenchSettings::default()
+            }
+        );
+        assert!(Settings::from_toml("[[bench]]\nid = \"*\"\ntime_unit = \"min\"").is_err());
+        assert!(Settings::from_toml("[[bench]]\nid = \"*\"\nunknown = 1").is_err());
+        // the label of another rule is no unit of the budget
+        assert!(Settings::from_toml(
+            "[[bench]]\nid = \"*\"\nlabel = \"row\"\n\
+             [[bench]]\nid = \"parse/*\"\nbudget = \"50 ns/row\""
+        )
+        .is_err());
+    }
+}
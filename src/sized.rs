@@ -0,0 +1,240 @@
+//! Benchmarks with their own throughputs in a group
+//!
+//! A group sweeping input sizes needs `BenchmarkGroup::throughput` before each benchmark,
+//! and a forgotten or stale one silently divides by the wrong amount.
+//! [`bench_sized`] sets the throughput and registers the benchmark at once, with the size
+//! as its parameter like `parse/1000`:
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::sized::bench_sized;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("sum");
+//! for size in [1_000, 10_000, 100_000] {
+//!     let input: Vec<u64> = (0..size).collect();
+//!     bench_sized(&mut g, "iter", size, |b, _| b.iter(|| input.iter().sum::<u64>()));
+//! }
+//! g.finish();
+//! ```
+//!
+//! [`bench_with_input_throughput`] derives the throughput from the input itself by
+//! [`HasThroughput`], so that the declared size cannot drift from the actual input:
+//!
+//! ```no_run
+//! use criterion::{BenchmarkId, Criterion};
+//! use criterion_inverted_throughput::sized::bench_with_input_throughput;
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let mut g = c.benchmark_group("parse");
+//! for input in ["1,2,3", "1,2,3,4,5,6"] {
+//!     // `Throughput::Bytes(input.len())`
+//!     bench_with_input_throughput(&mut g, BenchmarkId::new("csv", input.len()), input, |b, i| {
+//!         b.iter(|| i.split(',').count())
+//!     });
+//! }
+//! g.finish();
+//! ```
+//!
+//! Criterion cannot unset the throughput of a group, so the throughput of the last sized
+//! benchmark stays set for the benchmarks registered after it in the group.
+
+use criterion::measurement::Measurement;
+use criterion::{Bencher, BenchmarkGroup, BenchmarkId, Throughput};
+
+/// The input of a benchmark knowing its throughput
+///
+/// Slices, arrays and `Vec`s of [`Element`]s are `Throughput::Elements` of their lengths,
+/// and bytes (`[u8]`, `str` and `String`) are `Throughput::Bytes` of their lengths.
+pub trait HasThroughput {
+    /// Returns the throughput of processing the input once
+    fn throughput(&self) -> Throughput;
+}
+
+/// The element of slices counted by [`HasThroughput`]
+///
+/// It is implemented for the primitive types except `u8`, whose slices are bytes, and
+/// for `String`. Implement it for the elements of your inputs.
+pub trait Element {}
+
+macro_rules! elements {
+    ($($t:ty),*) => {
+        $(impl Element for $t {})*
+    };
+}
+
+elements!(i8, i16, i32, i64, i128, isize, u16, u32, u64, u128, usize, f32, f64, bool, char, String);
+
+impl<T: Element> HasThroughput for [T] {
+    fn throughput(&self) -> Throughput {
+        Throughput::Elements(self.len() as u64)
+    }
+}
+
+impl HasThroughput for [u8] {
+    fn throughput(&self) -> Throughput {
+        Throughput::Bytes(self.len() as u64)
+    }
+}
+
+impl HasThroughput for str {
+    fn throughput(&self) -> Throughput {
+        Throughput::Bytes(self.len() as u64)
+    }
+}
+
+impl HasThroughput for String {
+    fn throughput(&self) -> Throughput {
+        self.as_str().throughput()
+    }
+}
+
+impl<T> HasThroughput for Vec<T>
+where
+    [T]: HasThroughput,
+{
+    fn throughput(&self) -> Throughput {
+        self.as_slice().throughput()
+    }
+}
+
+impl<T, const N: usize> HasThroughput for [T; N]
+where
+    [T]: HasThroughput,
+{
+    fn throughput(&self) -> Throughput {
+        self.as_slice().throughput()
+    }
+}
+
+impl<T: HasThroughput + ?Sized> HasThroughput for &T {
+    fn throughput(&self) -> Throughput {
+        (**self).throughput()
+    }
+}
+
+/// Registers the benchmark `id` in `group` with the throughput of `input`, like
+/// `BenchmarkGroup::bench_with_input`
+pub fn bench_with_input_throughput<M, I, F>(
+    group: &mut BenchmarkGroup<'_, M>,
+    id: BenchmarkId,
+    input: &I,
+    f: F,
+) where
+    M: Measurement,
+    I: HasThroughput + ?Sized,
+    F: FnMut(&mut Bencher<'_, M>, &I),
+{
+    group.throughput(input.throughput());
+    group.bench_with_input(id, input, f);
+}
+
+/// Registers the benchmark `{name}/{size}` in `group` with `Throughput::Elements(size)`
+///
+/// `f` is given the size as the input.
+pub fn bench_sized<M, F>(group: &mut BenchmarkGroup<'_, M>, name: &str, size: u64, f: F)
+where
+    M: Measurement,
+    F: FnMut(&mut Bencher<'_, M>, &u64),
+{
+    bench_with_throughput(group, name, size, Throughput::Elements(size), f);
+}
+
+/// Registers the benchmark `{name}/{size}` in `group` with `Throughput::Bytes(size)`
+///
+/// `f` is given the size as the input.
+pub fn bench_sized_bytes<M, F>(group: &mut BenchmarkGroup<'_, M>, name: &str, size: u64, f: F)
+where
+    M: Measurement,
+    F: FnMut(&mut Bencher<'_, M>, &u64),
+{
+    bench_with_throughput(group, name, size, Throughput::Bytes(size), f);
+}
+
+fn bench_with_throughput<M, F>(
+    group: &mut BenchmarkGroup<'_, M>,
+    name: &str,
+    size: u64,
+    throughput: Throughput,
+    f: F,
+) where
+    M: Measurement,
+    F: FnMut(&mut Bencher<'_, M>, &u64),
+{
+    group.throughput(throughput);
+    group.bench_with_input(BenchmarkId::new(name, size), &size, f);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "serde")]
+    use crate::results::{load_records, LATEST_BASELINE};
+    #[cfg(feature = "serde")]
+    use criterion::Criterion;
+    #[cfg(feature = "serde")]
+    use std::env;
+    #[cfg(feature = "serde")]
+    use std::fs;
+    #[cfg(feature = "serde")]
+    use std::hint::black_box;
+    #[cfg(feature = "serde")]
+    use std::time::Duration;
+    use test_case::test_case;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_bench_sized() {
+        let home = env::temp_dir().join("criterion-inverted-throughput-test-sized");
+        let _ = fs::remove_dir_all(&home);
+        let mut c = Criterion::default()
+            .output_directory(&home)
+            .sample_size(10)
+            .warm_up_time(Duration::from_millis(1))
+            .measurement_time(Duration::from_millis(10))
+            .without_plots();
+        let mut g = c.benchmark_group("sum");
+        for size in [10, 20] {
+            bench_sized(&mut g, "iter", size, |b, &size| {
+                b.iter(|| (0..size).map(black_box).sum::<u64>())
+            });
+        }
+        bench_sized_bytes(&mut g, "bytes", 30, |b, &size| b.iter(|| black_box(size)));
+        bench_with_input_throughput(
+            &mut g,
+            BenchmarkId::new("slice", 3),
+            &[1u64, 2, 3],
+            |b, i| b.iter(|| i.iter().map(black_box).sum::<u64>()),
+        );
+        g.finish();
+
+        let mut records = load_records(&home, LATEST_BASELINE).unwrap();
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        let sizes: Vec<(&str, Option<Throughput>)> = records
+            .iter()
+            .map(|record| (record.id.as_str(), record.throughput.clone()))
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![
+                ("sum/bytes/30", Some(Throughput::Bytes(30))),
+                ("sum/iter/10", Some(Throughput::Elements(10))),
+                ("sum/iter/20", Some(Throughput::Elements(20))),
+                ("sum/slice/3", Some(Throughput::Elements(3))),
+            ]
+        );
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test_case(&&[1u32, 2, 3][..], Throughput::Elements(3) ; "slice")]
+    #[test_case(&vec![1.0f64; 4], Throughput::Elements(4) ; "vec")]
+    #[test_case(&["a".to_string()], Throughput::Elements(1) ; "strings")]
+    #[test_case(&[0u8; 5], Throughput::Bytes(5) ; "bytes")]
+    #[test_case(&"héllo", Throughput::Bytes(6) ; "str")]
+    #[test_case(&"ab".to_string(), Throughput::Bytes(2) ; "string")]
+    fn test_has_throughput(input: &dyn HasThroughput, expected: Throughput) {
+        assert_eq!(input.throughput(), expected);
+    }
+}
@@ -0,0 +1,277 @@
+//! Helpers to test formatters of inverted throughputs
+//!
+//! These are the invariants the tests of this crate check against criterion's `WallTime`:
+//! the inverted values are the times divided by the amount of the throughput, and they are
+//! the reciprocals of criterion's throughputs. A measurement built on this crate can check
+//! its own formatter with them:
+//!
+//! ```
+//! use criterion::measurement::{Measurement, ValueFormatter, WallTime};
+//! use criterion::Throughput;
+//! use criterion_inverted_throughput::test_support::{
+//!     assert_nearly_eq, assert_nearly_inversion, normalize_amount, normalize_time, Data,
+//! };
+//! use criterion_inverted_throughput::InvertedThroughput;
+//!
+//! let data = Data::new(1e6, Throughput::Elements(10));
+//! let mut inverted = data.values.clone();
+//! let unit = InvertedThroughput::new().scale_throughputs(
+//!     data.typical_value,
+//!     &data.throughput,
+//!     &mut inverted,
+//! );
+//! let inverted: Vec<f64> = inverted.iter().map(|&x| normalize_time(unit, x)).collect();
+//!
+//! let expected: Vec<f64> = data.values.iter().map(|&x| x / 1e9 / 10.0).collect();
+//! assert_nearly_eq(&expected, &inverted);
+//!
+//! let mut throughputs = data.values.clone();
+//! let unit = WallTime.formatter().scale_throughputs(
+//!     data.typical_value,
+//!     &data.throughput,
+//!     &mut throughputs,
+//! );
+//! let throughputs: Vec<f64> = throughputs.iter().map(|&x| normalize_amount(unit, x)).collect();
+//! assert_nearly_inversion(&inverted, &throughputs);
+//! ```
+//!
+//! [`thrpt_line`] formats estimates in the order criterion's console report does, to check
+//! that the bounds are printed in ascending order.
+//!
+//! Enable the `test-support` feature, usually in `[dev-dependencies]`, to use this module.
+
+#[cfg(test)]
+use crate::results::{BenchRecord, Estimate, Estimates};
+use criterion::measurement::ValueFormatter;
+use criterion::Throughput;
+
+/// Synthetic estimates around a typical value with a throughput
+#[derive(Debug, Clone)]
+pub struct Data {
+    /// The typical value in nanoseconds
+    pub typical_value: f64,
+    /// The values in nanoseconds
+    pub values: Vec<f64>,
+    /// The throughput of the values
+    pub throughput: Throughput,
+}
+
+impl Data {
+    /// Returns 10 values from 90% to 110% of `typical_value` in nanoseconds
+    pub fn new(typical_value: f64, throughput: Throughput) -> Self {
+        let mut values: Vec<f64> = vec![];
+        for x in -5..5 {
+            // generate values in 90%-110% times of typical value
+            values.push(typical_value * (1f64 - (x as f64 * 0.02)))
+        }
+        Self {
+            typical_value,
+            values,
+            throughput,
+        }
+    }
+}
+
+/// Converts `value` scaled to the unit `denom` (like `ns/elem`) to seconds
+///
+/// # Panics
+///
+/// Panics if `denom` does not start with a time unit from `fs` to `s`.
+pub fn normalize_time(denom: &str, value: f64) -> f64 {
+    if denom.starts_with("fs") {
+        value / 1e15
+    } else if denom.starts_with("ps") {
+        value / 1e12
+    } else if denom.starts_with("ns") {
+        value / 1e9
+    } else if denom.starts_with("µs") {
+        value / 1e6
+    } else if denom.starts_with("ms") {
+        value / 1e3
+    } else if denom.starts_with('s') {
+        value
+    } else {
+        panic!("Unexpected denom for time: {}", denom)
+    }
+}
+
+/// Converts `value` scaled to the unit `denom` (like `Melem/s`) to the amount without prefix
+pub fn normalize_amount(denom: &str, value: f64) -> f64 {
+    if denom.starts_with('G') {
+        value * 1e9
+    } else if denom.starts_with('M') {
+        value * 1e6
+    } else if denom.starts_with('K') {
+        value * 1e3
+    } else {
+        value
+    }
+}
+
+/// Formats the `time` line of the bounds `[lower, point, upper]` with `formatter`, then
+/// returns the `thrpt` line, as criterion's console report does
+///
+/// Criterion formats the throughputs of the upper bound, the point estimate and the lower
+/// bound of times in this order, so a formatter of inverted throughputs has to swap the
+/// bounds to print them in ascending order.
+pub fn thrpt_line(
+    formatter: &dyn ValueFormatter,
+    throughput: &Throughput,
+    bounds: [f64; 3],
+) -> [String; 3] {
+    for value in bounds {
+        formatter.format_value(value);
+    }
+    let [lower, point, upper] = bounds;
+    [upper, point, lower].map(|value| formatter.format_throughput(throughput, value))
+}
+
+/// The builder of the [`BenchRecord`]s of the tests of this crate
+#[cfg(test)]
+pub(crate) struct RecordBuilder {
+    id: String,
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    throughput: Option<Throughput>,
+    point_estimate: f64,
+    margin: f64,
+}
+
+#[cfg(test)]
+impl RecordBuilder {
+    /// Returns a builder of the benchmark `id` of the group `parse` without throughput,
+    /// with all estimates `point_estimate` ± 10
+    pub(crate) fn new(id: &str, point_estimate: f64) -> Self {
+        RecordBuilder {
+            id: id.to_string(),
+            group_id: "parse".to_string(),
+            function_id: None,
+            value_str: None,
+            throughput: None,
+            point_estimate,
+            margin: 10.0,
+        }
+    }
+
+    /// Sets the throughput
+    pub(crate) fn throughput(mut self, throughput: Option<Throughput>) -> Self {
+        self.throughput = throughput;
+        self
+    }
+
+    /// Sets the distance from the point estimate to the bounds
+    pub(crate) fn margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Sets the group, the function and the parameter
+    pub(crate) fn ids(
+        mut self,
+        group_id: &str,
+        function_id: Option<&str>,
+        value_str: Option<&str>,
+    ) -> Self {
+        self.group_id = group_id.to_string();
+        self.function_id = function_id.map(str::to_string);
+        self.value_str = value_str.map(str::to_string);
+        self
+    }
+
+    /// Returns the record
+    pub(crate) fn build(self) -> BenchRecord {
+        let estimate = Estimate {
+            lower_bound: self.point_estimate - self.margin,
+            point_estimate: self.point_estimate,
+            upper_bound: self.point_estimate + self.margin,
+            standard_error: 1.0,
+        };
+        BenchRecord {
+            directory_name: self.id.clone(),
+            id: self.id,
+            group_id: self.group_id,
+            function_id: self.function_id,
+            value_str: self.value_str,
+            throughput: self.throughput,
+            secondary_throughput: None,
+            utilization: None,
+            estimates: Estimates {
+                mean: estimate,
+                median: estimate,
+                median_abs_dev: estimate,
+                slope: None,
+                std_dev: estimate,
+            },
+        }
+    }
+}
+
+/// Asserts that `a` and `b` are equal up to a relative error of `1e-12`
+///
+/// # Panics
+///
+/// Panics if the lengths differ, or a value of `a` is zero or not nearly equal to `b`.
+#[track_caller]
+pub fn assert_nearly_eq(a: &[f64], b: &[f64]) {
+    assert_eq!(a.len(), b.len(), "left: {:?} !~= right: {:?}", a, b);
+    for i in 0..a.len() {
+        assert_ne!(a[i].abs(), 0.0, "left: {:?} !~= right: {:?}", a, b);
+        assert!(
+            (a[i] - b[i]).abs() < a[i].abs() * 1e-12,
+            "left: {:?} !~= right: {:?}",
+            a,
+            b
+        )
+    }
+}
+
+/// Asserts that the products of `a` and `b` are nearly 1, within 7.5%
+///
+/// # Panics
+///
+/// Panics if the lengths differ, or a value of `a` is zero or not nearly the reciprocal of `b`.
+#[track_caller]
+pub fn assert_nearly_inversion(a: &[f64], b: &[f64]) {
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "left: {:?} <not inversion> right: {:?}",
+        a,
+        b
+    );
+    for i in 0..a.len() {
+        assert_ne!(
+            a[i].abs(),
+            0.0,
+            "left: {:?} <not inversion> right: {:?}",
+            a,
+            b
+        );
+        assert!(
+            (a[i] * b[i] - 1f64).abs() < 0.075,
+            "left: {:?} <not inversion> right: {:?} (index: {}, abs(sub(1.0)): {})",
+            a,
+            b,
+            i,
+            (a[i] * b[i] - 1f64).abs(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic]
+    fn test_not_nearly_eq() {
+        assert_nearly_eq(&[1.0, 2.0], &[1.0, 2.1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_not_inversion() {
+        assert_nearly_inversion(&[1.0, 2.0], &[1.0, 1.0]);
+    }
+}
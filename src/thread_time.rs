@@ -0,0 +1,473 @@
+//! CPU time and cycles of the benchmark thread
+//!
+//! Wall times include the time the thread is preempted, which is noise for short routines.
+//! [`ThreadCpuTime`] measures the CPU time of the current thread, and [`ThreadCycles`]
+//! the CPU cycles the current thread consumed, printed like `ns/elem` and `cycles/elem`
+//! through the same pipeline as [`InvertedThroughput`](crate::InvertedThroughput):
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::thread_time::ThreadCycles;
+//!
+//! let c = Criterion::default().with_measurement(ThreadCycles::new());
+//! ```
+//!
+//! On Windows, the CPU time is `GetThreadTimes` in 100 ns and the cycles are
+//! `QueryThreadCycleTime`, which counts the cycles of the thread at the invariant TSC rate
+//! including the time in the kernel. For low-noise wall times on Windows, use
+//! [`ClockSource::PerformanceCounter`](crate::clock::ClockSource::PerformanceCounter).
+//!
+//! On macOS, the CPU time is `thread_info(THREAD_BASIC_INFO)` in microseconds, which is not
+//! inflated by the time the thread waits for a core. The cycles are read from the fixed
+//! counters of the private kperf framework with the `kperf` feature. kperf requires root
+//! (run the benchmarks with `sudo -E cargo bench`) or a binary signed with
+//! the `com.apple.private.kernel.kpc` entitlement, which only Apple can grant, otherwise
+//! [`thread_cycles`] returns `None`. Cycles of Apple silicon are comparable across runs
+//! even if the thread migrates between efficiency and performance cores, while the times
+//! are not.
+//!
+//! Criterion measures in the thread calling the benchmarks, so work in other threads
+//! is not counted.
+
+#[cfg(any(windows, feature = "kperf"))]
+use crate::units::scale_per_unit;
+use crate::{inversion_options, Inversion, TimeFormatter};
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::Throughput;
+use std::time::Duration;
+
+#[cfg(windows)]
+mod sys {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn GetThreadTimes(
+            thread: isize,
+            creation: *mut u64,
+            exit: *mut u64,
+            kernel: *mut u64,
+            user: *mut u64,
+        ) -> i32;
+        fn QueryThreadCycleTime(thread: isize, cycles: *mut u64) -> i32;
+    }
+
+    pub(super) fn thread_cpu_time() -> Option<u64> {
+        let (mut creation, mut exit, mut kernel, mut user) = (0, 0, 0, 0);
+        // SAFETY: the pointers are valid pointers to `u64` laid out like `FILETIME`
+        if unsafe {
+            GetThreadTimes(
+                GetCurrentThread(),
+                &mut creation,
+                &mut exit,
+                &mut kernel,
+                &mut user,
+            )
+        } == 0
+        {
+            return None;
+        }
+        // in 100 nanoseconds
+        Some((kernel + user) * 100)
+    }
+
+    pub(super) fn thread_cycles() -> Option<u64> {
+        let mut cycles = 0;
+        // SAFETY: `cycles` is a valid pointer to a `u64`
+        if unsafe { QueryThreadCycleTime(GetCurrentThread(), &mut cycles) } == 0 {
+            return None;
+        }
+        Some(cycles)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    // `time_value_t`
+    #[repr(C)]
+    #[derive(Default)]
+    struct TimeValue {
+        seconds: i32,
+        microseconds: i32,
+    }
+
+    // `thread_basic_info`
+    #[repr(C)]
+    #[derive(Default)]
+    struct ThreadBasicInfo {
+        user_time: TimeValue,
+        system_time: TimeValue,
+        cpu_usage: i32,
+        policy: i32,
+        run_state: i32,
+        flags: i32,
+        suspend_count: i32,
+        sleep_time: i32,
+    }
+
+    const THREAD_BASIC_INFO: u32 = 3;
+
+    extern "C" {
+        static mach_task_self_: u32;
+        fn mach_thread_self() -> u32;
+        fn mach_port_deallocate(task: u32, name: u32) -> i32;
+        fn thread_info(thread: u32, flavor: u32, info: *mut i32, count: *mut u32) -> i32;
+    }
+
+    pub(super) fn thread_cpu_time() -> Option<u64> {
+        let mut info = ThreadBasicInfo::default();
+        let mut count =
+            (std::mem::size_of::<ThreadBasicInfo>() / std::mem::size_of::<i32>()) as u32;
+        // SAFETY: `info` is valid for `count` integers, and the port of the thread is
+        // deallocated after use
+        let result = unsafe {
+            let thread = mach_thread_self();
+            let result = thread_info(
+                thread,
+                THREAD_BASIC_INFO,
+                &mut info as *mut ThreadBasicInfo as *mut i32,
+                &mut count,
+            );
+            mach_port_deallocate(mach_task_self_, thread);
+            result
+        };
+        if result != 0 {
+            return None;
+        }
+        let micros = |time: &TimeValue| time.seconds as u64 * 1_000_000 + time.microseconds as u64;
+        Some((micros(&info.user_time) + micros(&info.system_time)) * 1_000)
+    }
+
+    #[cfg(feature = "kperf")]
+    pub(super) use kperf::thread_cycles;
+
+    #[cfg(feature = "kperf")]
+    mod kperf {
+        use std::ffi::c_void;
+        use std::sync::OnceLock;
+
+        const KPERF_PATH: &[u8] = b"/System/Library/PrivateFrameworks/kperf.framework/kperf\0";
+        // the class of the fixed counters, the first of which counts cycles
+        const KPC_CLASS_FIXED_MASK: u32 = 1;
+        const MAX_COUNTERS: usize = 32;
+
+        type GetThreadCounters = unsafe extern "C" fn(u32, u32, *mut u64) -> i32;
+
+        struct Kperf {
+            get_thread_counters: GetThreadCounters,
+            counters: u32,
+        }
+
+        // SAFETY: `symbol` is NUL-terminated, and the caller casts it to its signature
+        unsafe fn symbol(library: *mut c_void, symbol: &[u8]) -> Option<*mut c_void> {
+            let pointer = libc::dlsym(library, symbol.as_ptr() as *const libc::c_char);
+            (!pointer.is_null()).then_some(pointer)
+        }
+
+        // loads kperf and enables the fixed counters of threads
+        fn load() -> Option<Kperf> {
+            // SAFETY: the path and the names are NUL-terminated, and the symbols are cast
+            // to the signatures of kperf
+            unsafe {
+                let library =
+                    libc::dlopen(KPERF_PATH.as_ptr() as *const libc::c_char, libc::RTLD_LAZY);
+                if library.is_null() {
+                    return None;
+                }
+                let set_counting: unsafe extern "C" fn(u32) -> i32 =
+                    std::mem::transmute(symbol(library, b"kpc_set_counting\0")?);
+                let set_thread_counting: unsafe extern "C" fn(u32) -> i32 =
+                    std::mem::transmute(symbol(library, b"kpc_set_thread_counting\0")?);
+                let get_counter_count: unsafe extern "C" fn(u32) -> u32 =
+                    std::mem::transmute(symbol(library, b"kpc_get_counter_count\0")?);
+                let get_thread_counters: GetThreadCounters =
+                    std::mem::transmute(symbol(library, b"kpc_get_thread_counters\0")?);
+                // fail without root or the entitlement
+                if set_counting(KPC_CLASS_FIXED_MASK) != 0
+                    || set_thread_counting(KPC_CLASS_FIXED_MASK) != 0
+                {
+                    return None;
+                }
+                let counters = get_counter_count(KPC_CLASS_FIXED_MASK).min(MAX_COUNTERS as u32);
+                (counters > 0).then_some(Kperf {
+                    get_thread_counters,
+                    counters,
+                })
+            }
+        }
+
+        pub(in super::super) fn thread_cycles() -> Option<u64> {
+            static KPERF: OnceLock<Option<Kperf>> = OnceLock::new();
+            let kperf = KPERF.get_or_init(load).as_ref()?;
+            let mut counters = [0u64; MAX_COUNTERS];
+            // SAFETY: `counters` is valid for `kperf.counters` values, and the thread 0 is
+            // the current thread
+            if unsafe { (kperf.get_thread_counters)(0, kperf.counters, counters.as_mut_ptr()) } != 0
+            {
+                return None;
+            }
+            Some(counters[0])
+        }
+    }
+}
+
+/// Returns the CPU time consumed by the current thread in nanoseconds,
+/// or `None` if it is not supported on this platform
+pub fn thread_cpu_time() -> Option<u64> {
+    sys::thread_cpu_time()
+}
+
+/// Returns the CPU cycles consumed by the current thread,
+/// or `None` if it is not supported on this platform
+///
+/// On macOS, it requires the `kperf` feature, and root or the entitlement of kperf.
+#[cfg(any(windows, feature = "kperf"))]
+pub fn thread_cycles() -> Option<u64> {
+    sys::thread_cycles()
+}
+
+/// The custom measurement of the CPU time of the current thread printing it per element
+/// or byte
+///
+/// # Panics
+///
+/// Measuring panics if the CPU time of the thread is not supported on this platform.
+pub struct ThreadCpuTime {
+    inversion: Inversion,
+}
+
+impl ThreadCpuTime {
+    /// Returns a new `ThreadCpuTime`
+    pub fn new() -> Self {
+        ThreadCpuTime {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+impl Default for ThreadCpuTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn cpu_now() -> u64 {
+    thread_cpu_time().expect("the CPU time of the thread is not supported on this platform")
+}
+
+impl Measurement for ThreadCpuTime {
+    type Intermediate = u64;
+    type Value = Duration;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        cpu_now()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        Duration::from_nanos(cpu_now().saturating_sub(i))
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        *v1 + *v2
+    }
+    fn zero(&self) -> Self::Value {
+        Duration::ZERO
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        val.as_nanos() as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+impl ValueFormatter for ThreadCpuTime {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&TimeFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&TimeFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&TimeFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&TimeFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&TimeFormatter, values)
+    }
+}
+
+/// The custom measurement of the CPU cycles of the current thread printing them per element
+/// or byte
+///
+/// # Panics
+///
+/// Measuring panics if the cycles of the thread are not supported on this platform.
+#[cfg(any(windows, feature = "kperf"))]
+pub struct ThreadCycles {
+    inversion: Inversion,
+}
+
+#[cfg(any(windows, feature = "kperf"))]
+impl ThreadCycles {
+    /// Returns a new `ThreadCycles`
+    pub fn new() -> Self {
+        ThreadCycles {
+            inversion: Inversion::new(),
+        }
+    }
+
+    inversion_options!();
+}
+
+#[cfg(any(windows, feature = "kperf"))]
+impl Default for ThreadCycles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(windows, feature = "kperf"))]
+fn cycles_now() -> u64 {
+    thread_cycles().expect("the cycles of the thread are not supported on this platform")
+}
+
+#[cfg(any(windows, feature = "kperf"))]
+impl Measurement for ThreadCycles {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {
+        self.inversion.start();
+        cycles_now()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        cycles_now().saturating_sub(i)
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+    fn zero(&self) -> Self::Value {
+        0
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        *val as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        self
+    }
+}
+
+#[cfg(any(windows, feature = "kperf"))]
+impl ValueFormatter for ThreadCycles {
+    fn format_value(&self, value: f64) -> String {
+        self.inversion.format_value(&CyclesFormatter, value)
+    }
+
+    fn format_throughput(&self, throughput: &Throughput, value: f64) -> String {
+        self.inversion
+            .format_throughput(&CyclesFormatter, throughput, value)
+    }
+
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        self.inversion
+            .scale_values(&CyclesFormatter, typical_value, values)
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        self.inversion
+            .scale_throughputs(&CyclesFormatter, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, values: &mut [f64]) -> &'static str {
+        self.inversion.scale_for_machines(&CyclesFormatter, values)
+    }
+}
+
+// the formatter of numbers of cycles, printing throughputs in the format
+// `cycles/[element or byte]`
+#[cfg(any(windows, feature = "kperf"))]
+struct CyclesFormatter;
+
+#[cfg(any(windows, feature = "kperf"))]
+impl ValueFormatter for CyclesFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "cycles"
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "cycles"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(any(windows, feature = "kperf"))]
+    use test_case::test_case;
+
+    #[test]
+    fn test_thread_cpu_time() {
+        let cpu = ThreadCpuTime::new();
+        let start = cpu.start();
+        // busy the thread
+        let mut x = 0u64;
+        for i in 0..100_000_000u64 {
+            x = std::hint::black_box(x.wrapping_add(i));
+        }
+        assert!(cpu.end(start) > Duration::ZERO);
+    }
+
+    // kperf requires root
+    #[cfg(windows)]
+    #[test]
+    fn test_thread_cycles() {
+        let cycles = ThreadCycles::new();
+        let start = cycles.start();
+        let mut x = 0u64;
+        for i in 0..10_000_000u64 {
+            x = std::hint::black_box(x.wrapping_add(i));
+        }
+        assert!(cycles.end(start) > 0);
+    }
+
+    #[cfg(any(windows, feature = "kperf"))]
+    #[test_case(Throughput::Elements(10), "12.346 cycles/elem" ; "elements")]
+    #[test_case(Throughput::Bytes(10), "12.346 cycles/byte" ; "bytes")]
+    fn test_format_throughput(throughput: Throughput, expected: &str) {
+        let measure = ThreadCycles::new();
+        assert_eq!(measure.format_throughput(&throughput, 123.456), expected);
+    }
+}
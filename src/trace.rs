@@ -0,0 +1,168 @@
+//! `tracing` events of benchmark results
+//!
+//! Emits an event per benchmark with its id, the amount of its throughput and the estimates
+//! per element or byte, so that the costs flow into the pipelines collecting `tracing` output.
+//! [`register`] emits the event of each benchmark as it completes, through the
+//! [`hooks`](crate::hooks) run after it:
+//!
+//! ```no_run
+//! use criterion::Criterion;
+//! use criterion_inverted_throughput::{hooks, results, trace, InvertedThroughput};
+//!
+//! trace::register();
+//! let mut c = Criterion::default().with_measurement(InvertedThroughput::new());
+//! let home = results::criterion_home();
+//! hooks::bench_function(&mut c, &home, "sum", |b| b.iter(|| (0..100u64).sum::<u64>())).unwrap();
+//! ```
+//!
+//! [`emit_latest`] instead emits the events of all benchmarks in a batch after the run,
+//! reading the results criterion stored, e.g. in the `main` of a benchmark target
+//! (criterion's `criterion_main!` is replaced):
+//!
+//! ```no_run
+//! use criterion::{criterion_group, Criterion};
+//! use criterion_inverted_throughput::trace;
+//!
+//! # fn bench_parse(_: &mut Criterion) {}
+//! criterion_group!(benches, bench_parse);
+//!
+//! fn main() {
+//!     benches();
+//!     Criterion::default().configure_from_args().final_summary();
+//!     trace::emit_latest(None).unwrap();
+//! }
+//! ```
+//!
+//! The events are `INFO` events with the target `criterion_inverted_throughput` and
+//! the fields:
+//!
+//! - `id`: the full id of the benchmark like `parse/1k`
+//! - `group`: the group of the benchmark
+//! - `amount`: the amount of the throughput, like the element count, as an integer
+//! - `unit`: the unit of the throughput, `elem` or `byte`
+//! - `lower_ns`, `point_ns` and `upper_ns`: the estimates in nanoseconds per element or byte
+//!
+//! Benchmarks without throughput are skipped.
+
+use crate::compat;
+use crate::hooks;
+use crate::results::{criterion_home, load_records, BenchRecord, LATEST_BASELINE};
+use std::io;
+
+/// Emits an event per benchmark of `records`
+///
+/// Returns the number of emitted events.
+pub fn emit(records: &[BenchRecord]) -> usize {
+    let mut count = 0;
+    for record in records {
+        let (Some(throughput), Some(unit), Some(estimate)) =
+            (&record.throughput, record.unit(), record.time_per_unit())
+        else {
+            continue;
+        };
+        let (amount, _) = compat::amount(throughput);
+        ::tracing::info!(
+            target: "criterion_inverted_throughput",
+            id = %record.id,
+            group = %record.group_id,
+            amount,
+            unit,
+            lower_ns = estimate.lower_bound,
+            point_ns = estimate.point_estimate,
+            upper_ns = estimate.upper_bound,
+            "benchmark completed"
+        );
+        count += 1;
+    }
+    count
+}
+
+/// Registers a hook emitting the event of each benchmark when the [`hooks`] run after it
+pub fn register() {
+    hooks::register(|record| {
+        emit(std::slice::from_ref(record));
+    });
+}
+
+/// Emits an event per benchmark of the latest results criterion stored, in `group` if any,
+/// in a batch after the run
+///
+/// Returns the number of emitted events.
+pub fn emit_latest(group: Option<&str>) -> io::Result<usize> {
+    let mut records = load_records(&criterion_home(), LATEST_BASELINE)?;
+    if let Some(group) = group {
+        records.retain(|record| record.group_id == group);
+    }
+    Ok(emit(&records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::RecordBuilder;
+    use criterion::Throughput;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    fn record(id: &str, throughput: Option<Throughput>, point_estimate: f64) -> BenchRecord {
+        RecordBuilder::new(id, point_estimate)
+            .throughput(throughput)
+            .build()
+    }
+
+    // records the fields of events
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<Vec<String>>>>);
+
+    impl Visit for Events {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            let mut events = self.0.lock().unwrap();
+            let event = events.last_mut().unwrap();
+            event.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Events {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            self.0.lock().unwrap().push(Vec::new());
+            event.record(&mut self.clone());
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_emit() {
+        let events = Events::default();
+        let records = [
+            record("parse/10", Some(Throughput::Elements(10)), 1000.0),
+            record("parse/none", None, 1000.0),
+        ];
+        let count = tracing::subscriber::with_default(events.clone(), || emit(&records));
+        assert_eq!(count, 1);
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec![vec![
+                "message=benchmark completed",
+                "id=parse/10",
+                "group=parse",
+                "amount=10",
+                "unit=\"elem\"",
+                "lower_ns=99.0",
+                "point_ns=100.0",
+                "upper_ns=101.0",
+            ]]
+        );
+    }
+}
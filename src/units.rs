@@ -0,0 +1,209 @@
+//! The math inverting throughputs, for custom reporters
+//!
+//! The measurements of this crate print values per element or byte with these functions,
+//! so that a reporter using them gets the same numbers and units:
+//!
+//! ```
+//! use criterion::Throughput;
+//! use criterion_inverted_throughput::units;
+//!
+//! let throughput = Throughput::Elements(10);
+//! // 2 µs per iteration
+//! let mut values = [units::time_per_unit(2000.0, &throughput).unwrap()];
+//! let time_unit = units::scale_time(values[0], &mut values);
+//! assert_eq!(format!("{} {}", values[0], units::unit_per(time_unit, "elem")), "200 ns/elem");
+//! ```
+//!
+//! Values are in nanoseconds per iteration unless noted.
+
+use crate::compat::{self, Amount};
+use crate::denominator;
+use criterion::measurement::{Measurement, ValueFormatter, WallTime};
+use criterion::Throughput;
+
+/// Returns the amount of `throughput` and its unit, `elem`, `byte` or `bit`
+pub fn throughput_amount(throughput: &Throughput) -> (f64, &'static str) {
+    match compat::amount(throughput) {
+        (v, Amount::Bytes | Amount::BytesDecimal) => (v as f64, "byte"),
+        (v, Amount::Elements) => (v as f64, "elem"),
+        (v, Amount::Bits) => (v as f64, "bit"),
+    }
+}
+
+/// The maximum amount of throughputs exact in `f64`, 2^53
+pub const MAX_EXACT_AMOUNT: u64 = 1 << 53;
+
+/// Returns the amount of `throughput` like [`throughput_amount`], or an error if it is zero
+/// or too large to be exact in `f64`
+pub fn checked_amount(throughput: &Throughput) -> Result<(f64, &'static str), String> {
+    let (amount, _) = compat::amount(throughput);
+    if amount == 0 {
+        Err(format!("the throughput {:?} is zero", throughput))
+    } else if amount > MAX_EXACT_AMOUNT {
+        Err(format!(
+            "the throughput {:?} exceeds 2^53 and is not exact in f64",
+            throughput
+        ))
+    } else {
+        Ok(throughput_amount(throughput))
+    }
+}
+
+/// Returns `value` per iteration divided by the amount of `throughput`, or `None` if
+/// the throughput is invalid (see [`checked_amount`])
+pub fn time_per_unit(value: f64, throughput: &Throughput) -> Option<f64> {
+    let (amount, _) = checked_amount(throughput).ok()?;
+    Some(value / amount)
+}
+
+/// Scales `values` in nanoseconds as criterion does for `typical_value`, returning
+/// the time unit like `µs`
+pub fn scale_time(typical_value: f64, values: &mut [f64]) -> &'static str {
+    WallTime.formatter().scale_values(typical_value, values)
+}
+
+/// Divides `values` by the amount of `throughput` and scales them with `formatter`,
+/// returning the unit like `ns/elem`
+///
+/// `typical_value` is the one per iteration. Values per element or byte below 1 ps are
+/// scaled to femtoseconds like `fs/elem`. Invalid throughputs (see [`checked_amount`]) are
+/// not divided by, and the unit is per call like `ns/call`.
+pub fn scale_per_unit(
+    formatter: &dyn ValueFormatter,
+    typical_value: f64,
+    throughput: &Throughput,
+    values: &mut [f64],
+) -> &'static str {
+    // invalid throughputs are reported per call
+    let (units, unit_denom) = checked_amount(throughput).unwrap_or((1.0, "call"));
+    for val in &mut *values {
+        *val /= units;
+    }
+    let mut value_denom = formatter.scale_values(typical_value / units, values);
+    // times per unit of huge throughputs fall below 1 ps
+    if value_denom == "ps" && typical_value > 0.0 && typical_value / units < 1e-3 {
+        for val in &mut *values {
+            *val *= 1e3;
+        }
+        value_denom = "fs";
+    }
+    unit_per(value_denom, unit_denom)
+}
+
+/// Returns the unit of `value_denom` per `unit_denom` like `ns/elem`
+///
+/// Elements are labeled by [`denominator::label_elements`] in the current thread.
+/// `unit_denom` is `elem`, `byte`, `bit` or `call`, and `value_denom` is a unit of values,
+/// like `ns`, `pJ`, `allocs` or `csw`.
+pub fn unit_per(value_denom: &str, unit_denom: &str) -> &'static str {
+    let unit_denom = match unit_denom {
+        "elem" => denominator::elements_label().unwrap_or(unit_denom),
+        _ => unit_denom,
+    };
+    denominator::intern(format!("{}/{}", value_denom, unit_denom))
+}
+
+/// Returns the power of 1000 (1024 for binary bytes) not larger than the amount of
+/// `throughput` and its prefix, like `(1000.0, "K")` for `Throughput::Elements(10_000)`
+pub fn denominator_prefix(throughput: &Throughput) -> (f64, &'static str) {
+    let (amount, base, prefixes) = match compat::amount(throughput) {
+        (v, Amount::Bytes) => (v, 1024, ["Ki", "Mi", "Gi", "Ti"]),
+        (v, _) => (v, 1000, ["K", "M", "G", "T"]),
+    };
+    let mut factor = 1;
+    let mut prefix = "";
+    for p in prefixes {
+        if amount / base < factor {
+            break;
+        }
+        factor *= base;
+        prefix = p;
+    }
+    (factor as f64, prefix)
+}
+
+/// Returns `unit` like `µs/elem` with the denominator prefixed by `prefix` of
+/// [`denominator_prefix`], like `µs/Kelem` or `µs/KiB`
+pub fn prefixed_unit(unit: &'static str, throughput: &Throughput, prefix: &str) -> &'static str {
+    if prefix.is_empty() {
+        return unit;
+    }
+    let Some((value_denom, unit_denom)) = unit.split_once('/') else {
+        return unit;
+    };
+    match compat::amount(throughput).1 {
+        Amount::Bytes | Amount::BytesDecimal => {
+            denominator::intern(format!("{}/{}B", value_denom, prefix))
+        }
+        Amount::Elements | Amount::Bits => {
+            denominator::intern(format!("{}/{}{}", value_denom, prefix, unit_denom))
+        }
+    }
+}
+
+/// Formats approximate cycles with a decimal below 10 cycles, like `4.5` or `205`
+pub fn format_cycles(cycles: f64) -> String {
+    if cycles < 10.0 {
+        format!("{:.1}", cycles)
+    } else {
+        format!("{:.0}", cycles)
+    }
+}
+
+/// Returns `unit` with `µ` replaced by `u`
+pub fn ascii_unit(unit: &'static str) -> &'static str {
+    match unit {
+        "µs" => "us",
+        "µs/byte" => "us/byte",
+        "µs/elem" => "us/elem",
+        "µs/call" => "us/call",
+        // labeled by `denominator`
+        _ if unit.starts_with('µ') => denominator::intern(unit.replacen('µ', "u", 1)),
+        _ => unit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn test_format_cycles() {
+        assert_eq!(format_cycles(4.54), "4.5");
+        assert_eq!(format_cycles(205.14), "205");
+    }
+
+    #[test_case(Throughput::Elements(10), Some(200.0) ; "elements")]
+    #[test_case(Throughput::Bytes(4), Some(500.0) ; "bytes")]
+    #[test_case(Throughput::Elements(0), None ; "zero")]
+    fn test_time_per_unit(throughput: Throughput, expected: Option<f64>) {
+        assert_eq!(time_per_unit(2000.0, &throughput), expected);
+    }
+
+    #[test_case(Throughput::Elements(999), 1.0, "" ; "below kilo")]
+    #[test_case(Throughput::Elements(10_000), 1e3, "K" ; "kilo elements")]
+    #[test_case(Throughput::Bytes(1 << 20), (1 << 20) as f64, "Mi" ; "mebibytes")]
+    #[test_case(Throughput::BytesDecimal(1_000_000), 1e6, "M" ; "megabytes")]
+    fn test_denominator_prefix(throughput: Throughput, factor: f64, prefix: &str) {
+        assert_eq!(denominator_prefix(&throughput), (factor, prefix));
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(unit_per("ns", "elem"), "ns/elem");
+        assert_eq!(unit_per("ns", "bit"), "ns/bit");
+        assert_eq!(unit_per("fs", "call"), "fs/call");
+        assert_eq!(unit_per("furlongs", "elem"), "furlongs/elem");
+        // interned once
+        assert!(std::ptr::eq(
+            unit_per("furlongs", "elem"),
+            unit_per("furlongs", "elem")
+        ));
+        assert_eq!(
+            prefixed_unit("µs/byte", &Throughput::Bytes(1 << 10), "Ki"),
+            "µs/KiB"
+        );
+        assert_eq!(ascii_unit("µs/KiB"), "us/KiB");
+    }
+}
@@ -0,0 +1,253 @@
+//! Deterministic instruction counts with valgrind
+//!
+//! Wall times are too noisy for some CI environments. This module runs each benchmark
+//! once under cachegrind (like [iai](https://crates.io/crates/iai)) and reports
+//! the number of instructions per element or byte, like `instructions/elem`.
+//!
+//! Define a benchmark target with `harness = false` and call [`main`] in its `main`:
+//!
+//! ```no_run
+//! use criterion::Throughput;
+//! use criterion_inverted_throughput::valgrind::{self, Benchmark};
+//! use std::hint::black_box;
+//!
+//! fn parse() {
+//!     black_box("1,2,3,4").split(',').for_each(|s| {
+//!         black_box(s.parse::<u32>().unwrap());
+//!     });
+//! }
+//!
+//! fn main() {
+//!     valgrind::main(&[Benchmark::new("parse", parse).throughput(Throughput::Elements(4))]);
+//! }
+//! ```
+//!
+//! The benchmark binary runs itself under valgrind for each benchmark, and subtracts the
+//! instructions of a run without benchmarks to exclude the overhead of the process.
+//! Valgrind is looked up from `$VALGRIND`, or `valgrind` in `PATH`.
+
+use crate::units::scale_per_unit;
+use crate::Inversion;
+use criterion::measurement::ValueFormatter;
+use criterion::Throughput;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::hint::black_box;
+use std::io;
+use std::path::Path;
+use std::process::{self, Command};
+
+// the environment variable telling the process run under valgrind which benchmark to run
+const RUN_ENV: &str = "CRITERION_INVERTED_THROUGHPUT_VALGRIND_RUN";
+const CALIBRATION: &str = "calibration";
+
+/// A benchmark whose instructions are counted
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    id: &'static str,
+    throughput: Option<Throughput>,
+    routine: fn(),
+}
+
+impl Benchmark {
+    /// Returns a benchmark of `routine` named `id`
+    pub fn new(id: &'static str, routine: fn()) -> Self {
+        Benchmark {
+            id,
+            throughput: None,
+            routine,
+        }
+    }
+
+    /// Sets the throughput of a run of the routine
+    pub fn throughput(mut self, throughput: Throughput) -> Self {
+        self.throughput = Some(throughput);
+        self
+    }
+}
+
+/// The number of instructions of a benchmark
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionCount {
+    /// The id of the benchmark
+    pub id: String,
+    /// The throughput of the benchmark
+    pub throughput: Option<Throughput>,
+    /// The number of instructions of a run of the routine
+    pub instructions: u64,
+}
+
+impl InstructionCount {
+    /// Returns the number of instructions per element or byte,
+    /// or `None` if the throughput is missing or invalid
+    pub fn per_unit(&self) -> Option<f64> {
+        let (amount, _) = crate::units::checked_amount(self.throughput.as_ref()?).ok()?;
+        Some(self.instructions as f64 / amount)
+    }
+
+    /// Returns the number of instructions per element or byte with its unit,
+    /// like `123.45 instructions/elem`
+    pub fn format_per_unit(&self) -> Option<String> {
+        self.throughput.as_ref().map(|throughput| {
+            Inversion::new()
+                .format_throughput(&InstructionsFormatter, throughput, self.instructions as f64)
+                .trim_start()
+                .to_string()
+        })
+    }
+}
+
+/// Runs `benchmarks` under valgrind and prints their instructions
+///
+/// Exits the process with an error if valgrind fails.
+pub fn main(benchmarks: &[Benchmark]) {
+    match run(benchmarks) {
+        Ok(counts) => {
+            for count in counts {
+                println!("{}", count.id);
+                println!("  instructions:  {}", count.instructions);
+                if let Some(per_unit) = count.format_per_unit() {
+                    println!("  per unit:      {}", per_unit);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to count instructions with valgrind: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs `benchmarks` under valgrind and returns their instructions
+///
+/// In the process run under valgrind, it runs a benchmark and exits the process.
+pub fn run(benchmarks: &[Benchmark]) -> io::Result<Vec<InstructionCount>> {
+    if let Some(target) = env::var_os(RUN_ENV) {
+        if let Some(benchmark) = benchmarks.iter().find(|b| target == b.id) {
+            black_box(benchmark.routine)();
+        }
+        process::exit(0);
+    }
+
+    let calibration = count_instructions(CALIBRATION)?;
+    benchmarks
+        .iter()
+        .map(|benchmark| {
+            Ok(InstructionCount {
+                id: benchmark.id.to_string(),
+                throughput: benchmark.throughput.clone(),
+                instructions: count_instructions(benchmark.id)?.saturating_sub(calibration),
+            })
+        })
+        .collect()
+}
+
+fn count_instructions(target: &str) -> io::Result<u64> {
+    let out_file = env::temp_dir().join(format!(
+        "criterion-inverted-throughput-cachegrind-{}.out",
+        process::id()
+    ));
+    let mut out_file_arg = OsString::from("--cachegrind-out-file=");
+    out_file_arg.push(&out_file);
+    let status = Command::new(env::var_os("VALGRIND").unwrap_or_else(|| "valgrind".into()))
+        .args(["--tool=cachegrind", "--cache-sim=no", "--quiet"])
+        .arg(out_file_arg)
+        .arg(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(RUN_ENV, target)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "valgrind exited with {} running `{}`",
+            status, target
+        )));
+    }
+    let instructions = read_instructions(&out_file);
+    let _ = fs::remove_file(&out_file);
+    instructions
+}
+
+fn read_instructions(path: &Path) -> io::Result<u64> {
+    parse_instructions(&fs::read_to_string(path)?).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no instructions in {}", path.display()),
+        )
+    })
+}
+
+// parses the `Ir` in the summary of cachegrind output
+fn parse_instructions(out: &str) -> Option<u64> {
+    let events = out.lines().find_map(|line| line.strip_prefix("events:"))?;
+    let summary = out.lines().find_map(|line| line.strip_prefix("summary:"))?;
+    let index = events.split_whitespace().position(|event| event == "Ir")?;
+    summary.split_whitespace().nth(index)?.parse().ok()
+}
+
+/// The formatter of numbers of instructions, printing throughputs in the format
+/// `instructions/[element or byte]`
+pub struct InstructionsFormatter;
+
+impl ValueFormatter for InstructionsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "instructions"
+    }
+
+    fn scale_throughputs(
+        &self,
+        typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        scale_per_unit(self, typical_value, throughput, values)
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "instructions"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CACHEGRIND_OUT: &str = "\
+desc: I1 cache: 32768 B, 64 B, 8-way associative
+cmd: target/release/deps/parse-1234
+events: Ir I1mr ILmr
+fl=src/main.rs
+fn=main
+1 10 0 0
+summary: 123456 12 3
+";
+
+    #[test]
+    fn test_parse_instructions() {
+        assert_eq!(parse_instructions(CACHEGRIND_OUT), Some(123456));
+        assert_eq!(parse_instructions("events: Ir\n"), None);
+    }
+
+    #[test]
+    fn test_format_per_unit() {
+        let count = InstructionCount {
+            id: "parse".to_string(),
+            throughput: Some(Throughput::Elements(1000)),
+            instructions: 123456,
+        };
+        assert_eq!(count.per_unit(), Some(123.456));
+        assert_eq!(count.format_per_unit().unwrap(), "123.46 instructions/elem");
+
+        let count = InstructionCount {
+            throughput: None,
+            ..count
+        };
+        assert_eq!(count.format_per_unit(), None);
+
+        let count = InstructionCount {
+            throughput: Some(Throughput::Elements(0)),
+            ..count
+        };
+        assert_eq!(count.format_per_unit().unwrap(), "123456 instructions/call");
+    }
+}
@@ -0,0 +1,55 @@
+//! Time measurement with `performance.now()` on wasm32
+//!
+//! `WallTime` of criterion relies on `std::time::Instant`, which is not available on
+//! `wasm32-unknown-unknown`. With the `wasm` feature, [`PerformanceNow`] is used as the inner
+//! measurement of [`InvertedThroughput`](crate::InvertedThroughput) on wasm32, so the times
+//! of samples are measured in browsers, web workers and Node.js through `wasm-bindgen`.
+//!
+//! It is not enough on its own: criterion itself still calls `std::time::Instant::now()`,
+//! e.g. in `Bencher::iter` to track the time of the warm-up and of the measurement, which
+//! panics on `wasm32-unknown-unknown`. Run the benchmarks with a runtime providing the clock
+//! of `std` (e.g. on `wasm32-wasip1`) or with criterion patched to a shim of `Instant`.
+
+use criterion::measurement::{Measurement, ValueFormatter, WallTime};
+use std::time::Duration;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// The measurement of wall times with `performance.now()`, in the same values as `WallTime`
+///
+/// The resolution depends on the runtime; browsers may coarsen `performance.now()`
+/// to protect against timing attacks.
+pub struct PerformanceNow;
+
+impl Measurement for PerformanceNow {
+    // milliseconds since the time origin
+    type Intermediate = f64;
+    type Value = Duration;
+
+    fn start(&self) -> Self::Intermediate {
+        performance_now()
+    }
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        Duration::from_secs_f64(((performance_now() - i) / 1e3).max(0.0))
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        *v1 + *v2
+    }
+    fn zero(&self) -> Self::Value {
+        Duration::ZERO
+    }
+    fn to_f64(&self, val: &Self::Value) -> f64 {
+        val.as_nanos() as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        // formatting values of `WallTime` does not read the clock
+        WallTime.formatter()
+    }
+}
@@ -0,0 +1,85 @@
+// the `criterion-invert` binary on synthetic results
+#![cfg(feature = "cli")]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const BENCHMARK_JSON: &str = r#"{"group_id":"parse","function_id":null,"value_str":"10","throughput":{"Elements":10},"full_id":"parse/10","directory_name":"parse/10","title":"parse/10"}"#;
+const ESTIMATES_JSON: &str = r#"{"mean":{"confidence_interval":{"confidence_level":0.95,"lower_bound":339.0,"upper_bound":356.0},"point_estimate":347.0,"standard_error":4.3},"median":{"confidence_interval":{"confidence_level":0.95,"lower_bound":334.0,"upper_bound":365.0},"point_estimate":344.0,"standard_error":6.7},"median_abs_dev":{"confidence_interval":{"confidence_level":0.95,"lower_bound":2.3,"upper_bound":23.6},"point_estimate":17.7,"standard_error":6.2},"slope":{"confidence_interval":{"confidence_level":0.95,"lower_bound":338.0,"upper_bound":357.0},"point_estimate":348.0,"standard_error":5.1},"std_dev":{"confidence_interval":{"confidence_level":0.95,"lower_bound":7.3,"upper_bound":17.1},"point_estimate":14.4,"standard_error":2.2}}"#;
+
+// a directory with the results of `parse/10` in `criterion` and the settings `config`, if any
+fn workspace(name: &str, config: Option<&str>) -> PathBuf {
+    let dir = env::temp_dir().join(format!("criterion-inverted-throughput-test-cli-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    let results = dir.join("criterion").join("parse/10").join("new");
+    fs::create_dir_all(&results).unwrap();
+    fs::write(results.join("benchmark.json"), BENCHMARK_JSON).unwrap();
+    fs::write(results.join("estimates.json"), ESTIMATES_JSON).unwrap();
+    if let Some(config) = config {
+        fs::write(dir.join("inverted-throughput.toml"), config).unwrap();
+    }
+    dir
+}
+
+// runs `criterion-invert check` in `dir`, without the settings of the environment
+fn check(dir: &Path) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_criterion-invert"));
+    command
+        .args(["check", "--dir"])
+        .arg(dir.join("criterion"))
+        .current_dir(dir);
+    for key in [
+        "CONFIG",
+        "BENCH",
+        "LABEL",
+        "TIME_UNIT",
+        "BLOCK_SIZE",
+        "BUDGET",
+    ] {
+        command.env_remove(format!("INVERTED_THROUGHPUT_{}", key));
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn test_check() {
+    let dir = workspace(
+        "check",
+        Some("[[bench]]\nid = \"parse/*\"\nbudget = \"50 ns/elem\"\n"),
+    );
+    let output = check(&dir);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ok: parse/10 34.800 ns/elem\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_check_without_config() {
+    let dir = workspace("without-config", None);
+    let output = check(&dir);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("no rule has a budget"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_check_unmatched_glob() {
+    let dir = workspace(
+        "unmatched-glob",
+        Some("[[bench]]\nid = \"prase/*\"\nbudget = \"50 ns/elem\"\n"),
+    );
+    let output = check(&dir);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "failed: the budget of `prase/*` matches no benchmark\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
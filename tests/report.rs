@@ -0,0 +1,52 @@
+// the benchmarks run with criterion 0.5 of the dev-dependencies
+#![cfg(feature = "criterion_0_5")]
+
+use criterion::{Criterion, Throughput};
+use criterion_inverted_throughput::InvertedThroughput;
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::Duration;
+
+fn run_benchmark(output_directory: &Path, measurement: InvertedThroughput) {
+    let _ = fs::remove_dir_all(output_directory);
+    let mut c = Criterion::default()
+        .with_measurement(measurement)
+        .output_directory(output_directory)
+        .sample_size(10)
+        .nresamples(1000)
+        .warm_up_time(Duration::from_millis(10))
+        .measurement_time(Duration::from_millis(100))
+        .with_plots();
+    let mut g = c.benchmark_group("report");
+    g.throughput(Throughput::Elements(10));
+    g.bench_function("sum", |b| b.iter(|| (0..10u64).map(black_box).sum::<u64>()));
+    g.finish();
+}
+
+#[test]
+fn test_report_per_unit() {
+    let dir = env::temp_dir().join("criterion-inverted-throughput-test-report-per-unit");
+    run_benchmark(&dir, InvertedThroughput::new().plots_per_unit(true));
+
+    let report = dir.join("report").join("sum").join("report");
+    let html = fs::read_to_string(report.join("index.html")).unwrap();
+    assert!(html.contains("s/elem"), "no time per element in the report");
+    let pdf = fs::read_to_string(report.join("pdf.svg")).unwrap();
+    assert!(pdf.contains("s/elem)"), "no time per element on the axis");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_report_per_iteration() {
+    let dir = env::temp_dir().join("criterion-inverted-throughput-test-report-per-iteration");
+    run_benchmark(&dir, InvertedThroughput::new());
+
+    let report = dir.join("report").join("sum").join("report");
+    let html = fs::read_to_string(report.join("index.html")).unwrap();
+    assert!(html.contains("s/elem"), "no time per element in the report");
+    let pdf = fs::read_to_string(report.join("pdf.svg")).unwrap();
+    assert!(!pdf.contains("s/elem"), "time per element on the axis");
+    fs::remove_dir_all(&dir).unwrap();
+}